@@ -0,0 +1,23 @@
+use pes_core::{BaseEnvPolicy, BaseEnvService, PesError};
+
+#[no_mangle]
+pub extern "Rust" fn new_base_env_service() -> Box<dyn BaseEnvService> {
+    Box::new(DevBaseEnv::new())
+}
+
+/// This plugin is responsible for deciding which variables of the calling environment are 
+/// retained in a pes shell. Should `PES_BASE_ENV` be set, the policy is read from the config
+/// file it points to. Otherwise, the default whitelist of pes_core is used (see `BaseEnvPolicy::from_env`).
+pub struct DevBaseEnv;
+
+impl DevBaseEnv {
+    fn new() -> DevBaseEnv {
+        DevBaseEnv
+    }
+}
+
+impl BaseEnvService for DevBaseEnv {
+    fn base_env_policy(&self) -> Result<BaseEnvPolicy, PesError> {
+        BaseEnvPolicy::from_env()
+    }
+}
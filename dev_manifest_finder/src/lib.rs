@@ -1,6 +1,9 @@
 use pes_core::ManifestFinderService;
 use std::path::PathBuf;
 
+// the names the manifest may have, in order of precedence
+const MANIFEST_NAMES: &[&str] = &["manifest.yaml", "manifest.toml"];
+
 #[no_mangle]
 pub extern "Rust" fn new_finder_service() -> Box<dyn ManifestFinderService> {
     Box::new(DevManifestFinder::new())
@@ -20,10 +23,14 @@ impl DevManifestFinder {
 }
 
 impl ManifestFinderService for DevManifestFinder {
-    // This implementation is dead simple. The manifest.yaml file is 
-    // expected to be in the root of the package.
-    fn find_manifest(&self, mut distribution: PathBuf) -> PathBuf {
-        distribution.push("manifest.yaml");
-        distribution
+    // This implementation is dead simple. The manifest is expected to be in the root 
+    // of the package, as either a manifest.yaml or manifest.toml file, with the former
+    // taking precedence should both exist.
+    fn find_manifest(&self, distribution: PathBuf) -> PathBuf {
+        MANIFEST_NAMES
+            .iter()
+            .map(|name| distribution.join(name))
+            .find(|manifest| manifest.exists())
+            .unwrap_or_else(|| distribution.join(MANIFEST_NAMES[0]))
     }
 }
\ No newline at end of file
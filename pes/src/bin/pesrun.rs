@@ -4,11 +4,12 @@ use std::path::PathBuf;
 use peslib::PesError;
 use peslib::PluginMgr;
 use peslib::LockFile;
+use peslib::{EnvBuilder, PackageRepository};
+use peslib::constants::DEFAULT_TARGET;
 use pes::utils::launch_cmd;
 use std::collections::VecDeque;
-use std::env;
 use std::fs;
-use log::debug;
+use log::{debug, trace};
 
 
 use anyhow::{Result, anyhow};
@@ -26,6 +27,10 @@ struct Opt {
     /// Name of package which the command resides in
     #[structopt(long="pes-pkg")]
     pkg: String,
+
+    /// Name of the locked target whose environment the command is run in
+    #[structopt(long="pes-target", default_value=DEFAULT_TARGET)]
+    target: String,
 }
 
 
@@ -39,6 +44,8 @@ fn _main() -> Result<()> {
         return Err(anyhow!(format!("extracted executable from shebang line is not res-run. it is {}", cmd)));
     }
     let wrapper = args.pop_front().ok_or_else(|| anyhow!("unable to get wrapper from front of args"))?;
+    // the remaining args were supplied to the wrapper, and are passed on to the command
+    let cmd_args = Vec::from(args);
     
     // read file and construct args from file, parsing non-shebang line and adding in 
     let wrapper_contents = fs::read_to_string(&wrapper)?;
@@ -51,10 +58,10 @@ fn _main() -> Result<()> {
 
     // any additional args
     let opt = Opt::from_iter(args);
-    let Opt{pkg, cmd, lockfile} = opt;
-    debug!("executing run_cmd(lockfile: {:?}, cmd: {:?}) for package: {})",&lockfile,  &cmd,  &pkg);
+    let Opt{pkg, cmd, lockfile, target} = opt;
+    debug!("executing run_cmd(lockfile: {:?}, cmd: {:?}, target: {}) for package: {})",&lockfile,  &cmd, &target, &pkg);
     
-    run_cmd(lockfile, cmd.as_str())?;
+    run_cmd(lockfile, &cmd.to_string_lossy(), &cmd_args, &target)?;
     
     Ok(())
 }
@@ -69,13 +76,20 @@ fn main() {
     };
 }
 
-// run a command 
-fn run_cmd(lockfile: PathBuf, cmd: &str) -> Result<(), PesError> {
+// run a command, with its args, within the environment of the locked target
+fn run_cmd(lockfile: PathBuf, cmd: &str, args: &[String], target: &str) -> Result<(), PesError> {
     let plugin_mgr = PluginMgr::new()?;
    
     let lockfile = LockFile::from_file(lockfile)?;
-    let solution = lockfile.selected_dependencies_for("run")?;
-    launch_cmd(&plugin_mgr, solution, cmd)?;
+    let repos = PackageRepository::from_plugin(&plugin_mgr)?;
+    let env = EnvBuilder::from_plugin(&plugin_mgr)?
+        .with_target(target)
+        .build_for_lockfile(&repos, &lockfile)?;
+    // warn should the lockfile no longer reflect the state of the repositories
+    for mismatch in lockfile.verify().into_iter().chain(lockfile.verify_env(target, &env)) {
+        eprintln!("Warning: {}", mismatch);
+    }
+    launch_cmd(env, cmd, args)?;
     
     Ok(())
 }
@@ -1,8 +1,10 @@
 use structopt::{StructOpt, clap::ArgGroup};
 use std::path::PathBuf;
+use peslib::TargetPolicy;
 
 const DEFAULT_LOG_LEVEL: &str = "warn";
 const DEFAULT_TARGET: &str = "run";
+const DEFAULT_TRANSITIVE: &str = "run";
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "pes", about = "PES - the Package Environment System command line")]
@@ -15,6 +17,10 @@ pub struct Opt {
     #[structopt(short="l", long="log-level", default_value = DEFAULT_LOG_LEVEL)]
     pub log_level: String,
 
+    /// Report errors, including explanations of failed solves, as json
+    #[structopt(long = "json", global = true)]
+    pub json: bool,
+
     #[structopt(subcommand)]  // Note that we mark a field as a subcommand
     pub subcmd: SubCmds
 }
@@ -54,6 +60,10 @@ pub enum SubCmds {
         /// Output solve to a pes lock-file
          output: Option<PathBuf>,
 
+         #[structopt(long="with-env")]
+         /// Record the environment composed for each target in the lock-file. Used with -l | --lock-file
+         with_env: bool,
+
         #[structopt(group="env_action")]
         /// provide a list of constraints
          constraints: Vec<String>,
@@ -66,9 +76,33 @@ pub enum SubCmds {
          /// Include prereleases in the solve. By default, false
          include_pre: bool,
 
-         #[structopt(short="t", long="target", default_value=DEFAULT_TARGET)]
-         /// Provide a target to calculate the dependencies for. Used with -d | --distribution
-         target: String
+         #[structopt(long="prefer", parse(from_os_str))]
+         /// Provide a pes lock-file whose versions are preferred, where the solve allows them. Adding a constraint
+         /// to a locked request thus leaves the remaining versions unchanged where possible
+         prefer: Option<PathBuf>,
+
+         #[structopt(short="t", long="target", default_value=DEFAULT_TARGET, number_of_values=1)]
+         /// Provide a target to calculate the dependencies for. May be supplied more than once (eg -t run -t build),
+         /// in which case each target is solved, and all of them recorded in the lock-file
+         target: Vec<String>,
+
+         #[structopt(long="transitive", default_value=DEFAULT_TRANSITIVE)]
+         /// Provide the target(s) used to calculate the dependencies of transitive dependencies, as a 
+         /// comma separated list. Each entry may be a target name, "same" (use --target all the way down),
+         /// or a per package override of the form <package>:<target> (eg run,gcc:build). Used with -d | --distribution
+         transitive: TargetPolicy,
+    },
+    #[structopt(name = "lock")]
+    /// Work with pes lock-files
+    Lock {
+        #[structopt(subcommand)]
+        cmd: LockCmds,
+    },
+    #[structopt(name = "manifest")]
+    /// Work with package manifests
+    Manifest {
+        #[structopt(subcommand)]
+        cmd: ManifestCmds,
     },
     #[structopt(name = "shell", group = ArgGroup::with_name("shell_action").required(true))]
     /// Solve a dependency closure based on supplied package constraints, build an environment,
@@ -86,8 +120,66 @@ pub enum SubCmds {
          /// Include prereleases in the solve. By default, false
          include_pre: bool,
 
+         #[structopt(long="prefer", parse(from_os_str))]
+         /// Provide a pes lock-file whose versions are preferred, where the solve allows them. Used with constraints
+         prefer: Option<PathBuf>,
+
+        #[structopt(short="t", long="target", default_value=DEFAULT_TARGET)]
+        /// Provide the target whose environment is built. When used with a lock file, the locked target is entered
+         target: String,
+
         #[structopt(group = "shell_action")]
         /// provide a list of constraints
          constraints: Vec<String>,
     }
-}
\ No newline at end of file
+}
+#[derive(Debug, StructOpt)]
+pub enum LockCmds {
+    #[structopt(name = "diff")]
+    /// Report the packages added, removed, upgraded and downgraded in each target, going from one lock-file to another
+    Diff {
+        /// The original lock-file
+        #[structopt(parse(from_os_str))]
+        from: PathBuf,
+
+        /// The lock-file compared against the original
+        #[structopt(parse(from_os_str))]
+        to: PathBuf,
+    },
+    #[structopt(name = "update")]
+    /// Re-solve the request recorded in a lock-file, holding every package other than those supplied at its
+    /// locked version, and rewrite the lock-file
+    Update {
+        /// The lock-file to update
+        #[structopt(parse(from_os_str))]
+        lockfile: PathBuf,
+
+        /// The packages free to change. Should none be supplied, every package is free to change
+        packages: Vec<String>,
+
+        /// Report the changes rather than rewriting the lock-file
+        #[structopt(short="n", long="dry-run")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum ManifestCmds {
+    #[structopt(name = "migrate")]
+    /// Rewrite the manifest, which may either be provided or found, against the latest schema. The
+    /// manifest is rewritten in full, which discards its comments and formatting, and so is only
+    /// rewritten given --discard-formatting
+    Migrate {
+        /// Provide an explicit path to a manifest.
+        #[structopt(short="m", long="manifest", parse(from_os_str))]
+        manifest: Option<PathBuf>,
+
+        /// Print the migrated manifest rather than rewriting it
+        #[structopt(short="n", long="dry-run")]
+        dry_run: bool,
+
+        /// Rewrite the manifest, discarding its comments and formatting
+        #[structopt(long="discard-formatting")]
+        discard_formatting: bool,
+    },
+}
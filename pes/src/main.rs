@@ -5,15 +5,19 @@ mod cli_opts;
 use structopt::StructOpt;
 use users::{get_current_uid, get_user_by_uid};
 use log::info;
-use peslib::prelude::*;
+use peslib::{prelude::*, lock::LockDiff, manifest::MANIFEST_SCHEMA};
 
 use cli_opts::*;
 use pes::{
     utils::{
         audit_manifest_file, 
         audit_manifest_for_current_location, 
+        find_manifest,
+        hostname,
         init_log, 
+        migrate_manifest_file,
         launch_shell,
+        requested_packages,
         check_distribution
     },
     {
@@ -71,130 +75,324 @@ fn env_cmd(subcmd: SubCmds) -> Result<(), PesError> {
     let presenter = Presenter::new(&plugin_mgr);
 
     match subcmd {
-        // Here the user has specified a specific distribution (eg foo-1.0.1) and a target
+        // Here the user has specified a specific distribution (eg foo-1.0.1) and one or more targets
         SubCmds::Env {
             distribution: Some(dist),
-            target,
+            target: targets,
+            transitive,
             output,
             include_pre,
+            with_env,
+            prefer,
             ..
         } => {
-            let (distmap, results) =
-                perform_solve_for_distribution_and_target(&plugin_mgr, dist.as_str(), target.as_str(), include_pre)?;
-            
-            if let Some(output) = output {
-                // get the user from the current process
-                let user = get_user_by_uid(get_current_uid()).unwrap();
-                let user = user.name();
-                // reconstruct the request string from args
-                let request = std::env::args().collect::<Vec<_>>().join(" ");
-                // create a new lockfile
-                let mut lockfile = LockFile::new(request, user.to_string_lossy());
-                // add the target distribution to the lockfile (first?)
-                lockfile.add_dist(target.as_str(), dist.as_str())?;
-                // add remaining distributions to lockfile
-                for result in results {
-                    let dist = format!("{}-{}", result.0, result.1);
+            let preferences = prefer.map(LockFile::from_file).transpose()?;
+            // create a new lockfile, recording the distribution and target policy solved
+            let mut lockfile = new_lockfile(&plugin_mgr, include_pre)
+                .with_root_distribution(dist.as_str(), &transitive);
+
+            for target in &targets {
+                let (distmap, results, variants) = perform_pinned_solve_for_distribution_and_target(
+                    &plugin_mgr, dist.as_str(), target.as_str(), &transitive, &[], preferences.as_ref(), include_pre
+                )?;
+                
+                if output.is_some() {
+                    // add the target distribution to the lockfile (first?)
                     lockfile.add_dist(target.as_str(), dist.as_str())?;
+                    // add remaining distributions to lockfile
+                    lock_solution(&mut lockfile, target.as_str(), &results, &variants)?;
+                } else {
+                    if targets.len() > 1 {
+                        println!("\n{}", target);
+                    }
+                    presenter.solve_results_tree(
+                        PresentationInput::Target{distribution: dist.as_str(), target: target.as_str(), target_policy: &transitive},
+                        &(&distmap, &results, &variants),
+                    ).expect("present_solve_resutls_tree failed");
                 }
+            }
+            if let Some(output) = output {
+                record_lockfile_state(&plugin_mgr, &mut lockfile, &targets, with_env)?;
                 lockfile.to_file(output, true)?;
-            } else {
-
-                presenter.solve_results_tree(
-                    PresentationInput::Target{distribution: dist.as_str(), target: target.as_str()},
-                    &(&distmap, &results),
-                ).expect("present_solve_resutls_tree failed");
-                
             }
         }
-        // here the user has specified a set of constraints instead of a specific distribution. This is
-        // used to generate a solve for runtime
+        // here the user has specified a set of constraints instead of a specific distribution. The
+        // dependencies of every package are calculated using each of the requested targets in turn. 
+        // Should an output lockfile be supplied, the solves are recorded in it, in addition to being presented.
         SubCmds::Env {
             constraints,
             include_pre,
-            output: None,
+            output,
+            with_env,
+            target: targets,
+            prefer,
             ..
         } => {
-            // perform the solve given the constraints, and filter out the ROOT_REQUEST from the
-            // results, as we dont want to present that to the end user
+            let preferences = prefer.map(LockFile::from_file).transpose()?;
             let constraints: Vec<&str> = constraints.iter().map(AsRef::as_ref).collect();
             info!("perfoming solve with constraints: {:?}", &constraints);
-           
-            let (distmap, results) = perform_solve(&plugin_mgr, &constraints, include_pre)?;
-            info!("solve returned: {:#?}", &results);
 
-            presenter.solve_results_tree(
-                PresentationInput::Constraints(constraints),
-                &(&distmap, &results),
-            ).expect("present_solve_resutls_tree failed");
+            // create a new lockfile, recording the constraints solved
+            let mut lockfile = new_lockfile(&plugin_mgr, include_pre).with_constraints(&constraints)?;
+
+            for target in &targets {
+                // perform the solve given the constraints
+                let (distmap, results, variants) = perform_pinned_solve_for_target(
+                    &plugin_mgr, &constraints, target.as_str(), &[], preferences.as_ref(), include_pre
+                )?;
+                info!("solve for target {} returned: {:#?}", target, &results);
 
+                if output.is_some() {
+                    lock_solution(&mut lockfile, target.as_str(), &results, &variants)?;
+                }
+                if targets.len() > 1 {
+                    println!("\n{}", target);
+                }
+                presenter.solve_results_tree(
+                    PresentationInput::Constraints{constraints: constraints.clone(), target: target.as_str()},
+                    &(&distmap, &results, &variants),
+                ).expect("present_solve_resutls_tree failed");
+            }
+            if let Some(output) = output {
+                record_lockfile_state(&plugin_mgr, &mut lockfile, &targets, with_env)?;
+                lockfile.to_file(output, true)?;
+            }
         }
-        // here the user has specified a set of constraints as well as an output lockfile. Rather
-        // than display the results, we write them to a file.
-        SubCmds::Env {
-            constraints,
-            include_pre,
-            output: Some(output),
-            ..
-        } => {
-            let constraints: Vec<&str> = constraints.iter().map(AsRef::as_ref).collect();
-            // perform the solve given the constraints
-           
-            let (distmap, results) = perform_solve(&plugin_mgr, &constraints, include_pre)?;
+        _ => println!("Unsupported argument combination for pes env"),
+    };
+    Ok(())
+}
+
+// audit the supplied manifest, or the manifest found from the current location, exiting with 
+// a failure status should any errors be found
+fn audit_cmd(manifest: Option<std::path::PathBuf>, json: bool) -> Result<(), PesError> {
+    let report = match manifest {
+        Some(manifest) => audit_manifest_file(manifest)?,
+        None => audit_manifest_for_current_location()?,
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report).expect("unable to serialize audit report"));
+    } else {
+        println!("{}", report);
+    }
+    if report.has_errors() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
 
-            // calculate the request string
-            let request = std::env::args().collect::<Vec<_>>().join(" ");
-            // extract the user's login from the current process
-            let user = get_user_by_uid(get_current_uid()).unwrap();
-            let user = user.name();
-            // create a new lockfile
-            let mut lockfile = LockFile::new(request, user.to_string_lossy());
-            for result in &results {
-                let dist = format!("{}-{}", result.0, result.1);
-                lockfile.add_dist("run", dist.as_str())?;
+fn manifest_cmd(cmd: ManifestCmds) -> Result<(), PesError> {
+    match cmd {
+        ManifestCmds::Migrate { manifest, dry_run, discard_formatting } => {
+            let manifest = match manifest {
+                Some(manifest) => manifest,
+                None => find_manifest()?,
+            };
+            let schema = migrate_manifest_file(&manifest, dry_run, discard_formatting)?;
+            if dry_run {
+                return Ok(());
+            }
+            if schema < MANIFEST_SCHEMA {
+                println!("Migrated {} from schema {} to schema {}", manifest.display(), schema, MANIFEST_SCHEMA);
+            } else {
+                println!("{} is already at schema {}", manifest.display(), MANIFEST_SCHEMA);
             }
+        }
+    }
+    Ok(())
+}
 
-            presenter.solve_results_tree(
-                PresentationInput::Constraints(constraints),
-                &(&distmap, &results),
-            ).expect("present_solve_resutls_tree failed");
+// construct a lockfile recording the provenance of the solve: the command line, user, host, and the
+// repositories searched
+fn new_lockfile(plugin_mgr: &PluginMgr, include_pre: bool) -> LockFile {
+    // reconstruct the request string from args
+    let request = std::env::args().collect::<Vec<_>>().join(" ");
+    new_lockfile_for_request(plugin_mgr, request, include_pre)
+}
+
+// construct a lockfile recording the provenance of the solve, given the request solved
+fn new_lockfile_for_request(plugin_mgr: &PluginMgr, request: String, include_pre: bool) -> LockFile {
+    // get the user from the current process
+    let user = get_user_by_uid(get_current_uid()).unwrap();
+    let lockfile = LockFile::new(request, user.name().to_string_lossy())
+        .with_repos(plugin_mgr.repos())
+        .with_include_pre(include_pre);
+    match hostname() {
+        Some(hostname) => lockfile.with_hostname(hostname),
+        None => lockfile,
+    }
+}
 
-            lockfile.to_file(output, true)?;
+// record where each locked distribution was found, along with the environment composed for each of the
+// targets should `with_env` be true
+fn record_lockfile_state(plugin_mgr: &PluginMgr, lockfile: &mut LockFile, targets: &[String], with_env: bool) -> Result<(), PesError> {
+    let repos = PackageRepository::from_plugin(plugin_mgr)?;
+    lockfile.record_distributions(&repos)?;
+    if with_env {
+        let builder = EnvBuilder::from_plugin(plugin_mgr)?;
+        for target in targets {
+            let env = builder.clone()
+                .with_target(target.as_str())
+                .build_for_lockfile(&repos, lockfile)?;
+            lockfile.set_env(target.as_str(), env);
         }
-        _ => println!("Unsupported argument combination for pes env"),
-    };
+    }
+    Ok(())
+}
+
+fn lock_cmd(cmd: LockCmds, json: bool) -> Result<(), PesError> {
+    match cmd {
+        LockCmds::Diff { from, to } => {
+            let diff = LockFile::from_file(from)?.diff(&LockFile::from_file(to)?);
+            present_lock_diff(&diff, json);
+        }
+        LockCmds::Update { lockfile: path, packages, dry_run } => {
+            let plugin_mgr = PluginMgr::new()?;
+            let lockfile = LockFile::from_file(&path)?;
+            let updated = update_lockfile(&plugin_mgr, &lockfile, &packages)?;
+            present_lock_diff(&lockfile.diff(&updated), json);
+            if !dry_run {
+                updated.to_file(path, true)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// re-solve the request recorded by the lockfile, for each of its targets, holding every package other than
+// those supplied at its locked version
+fn update_lockfile(plugin_mgr: &PluginMgr, lockfile: &LockFile, packages: &[String]) -> Result<LockFile, PesError> {
+    let targets = lockfile.targets().cloned().collect::<Vec<_>>();
+    // every package freed must be locked for at least one target
+    if let Some(package) = packages
+        .iter()
+        .find(|package| !targets.iter().any(|target| lockfile.version(target, package).is_some()))
+    {
+        return Err(PesError::PackageNotLocked(package.clone()));
+    }
+
+    // should no packages be supplied, every package is free to change
+    let pins_for = |target: &str| if packages.is_empty() { Vec::new() } else { lockfile.pins_for(target, packages) };
+    // the pinned packages prefer their locked variants as well as being held at their locked versions
+    let preferences = if packages.is_empty() { None } else { Some(lockfile.without_packages(packages)) };
+
+    let mut updated = new_lockfile_for_request(plugin_mgr, lockfile.request().to_string(), lockfile.include_pre());
+    let constraints = lockfile.constraints().iter().map(ToString::to_string).collect::<Vec<_>>();
+    let constraints = constraints.iter().map(AsRef::as_ref).collect::<Vec<&str>>();
+    match lockfile.root_distribution() {
+        Some(dist) => {
+            let transitive = lockfile.transitive().map(str::parse::<TargetPolicy>).transpose()?.unwrap_or_default();
+            updated = updated.with_root_distribution(dist, &transitive);
+            for target in &targets {
+                let pins = pins_for(target);
+                let (_, results, variants) = perform_pinned_solve_for_distribution_and_target(
+                    plugin_mgr, dist, target, &transitive, &pins, preferences.as_ref(), lockfile.include_pre()
+                )?;
+                // the root distribution is locked first, as in pes env
+                updated.add_dist(target, dist)?;
+                lock_solution(&mut updated, target, &results, &variants)?;
+            }
+        }
+        None if !constraints.is_empty() => {
+            updated = updated.with_constraints(&constraints)?;
+            for target in &targets {
+                let pins = pins_for(target);
+                let (_, results, variants) =
+                    perform_pinned_solve_for_target(
+                    plugin_mgr, &constraints, target, &pins, preferences.as_ref(), lockfile.include_pre()
+                )?;
+                lock_solution(&mut updated, target, &results, &variants)?;
+            }
+        }
+        None => return Err(PesError::MissingLockRequest),
+    }
+    // the environment is recorded for those targets which recorded it before
+    let env_targets = targets.into_iter().filter(|target| lockfile.env_for(target).is_some()).collect::<Vec<_>>();
+    record_lockfile_state(plugin_mgr, &mut updated, &env_targets, true)?;
+    Ok(updated)
+}
+
+// add the distributions and variants of a solve to the lockfile, under the target
+fn lock_solution(
+    lockfile: &mut LockFile,
+    target: &str,
+    results: &SelectedDependencies<String, SemanticVersion>,
+    variants: &VariantMap,
+) -> Result<(), PesError> {
+    for result in results {
+        let dist = format!("{}-{}", result.0, result.1);
+        lockfile.add_dist(target, dist.as_str())?;
+    }
+    for (package, variant) in variants {
+        lockfile.add_variant(target, package, variant);
+    }
     Ok(())
 }
 
+// present the changes to each target, as json should `json` be true
+fn present_lock_diff(diff: &LockDiff, json: bool) {
+    if json {
+        println!("{}", serde_json::to_string_pretty(diff).expect("unable to serialize lock diff"));
+    } else if diff.is_empty() {
+        println!("No changes");
+    } else {
+        for (target, changes) in diff {
+            println!("{}", target);
+            for change in changes {
+                println!("   {}", change);
+            }
+        }
+    }
+}
+
 fn shell_cmd(subcmd: SubCmds) -> Result<(), PesError> {
     let plugin_mgr = PluginMgr::new()?;
     match subcmd {
         SubCmds::Shell {
             lockfile: Some(lockfile),
+            target,
             ..
         } => {
             let lockfile = LockFile::from_file(lockfile)?;
-            let solution = lockfile.selected_dependencies_for("run")?;
-            launch_shell(&plugin_mgr, solution)
+            let repos = PackageRepository::from_plugin(&plugin_mgr)?;
+            let env = EnvBuilder::from_plugin(&plugin_mgr)?
+                .with_target(target.as_str())
+                .build_for_lockfile(&repos, &lockfile)?;
+            // warn should the lockfile no longer reflect the state of the repositories
+            for mismatch in lockfile.verify().into_iter().chain(lockfile.verify_env(&target, &env)) {
+                eprintln!("Warning: {}", mismatch);
+            }
+            launch_shell(env)
         }
         SubCmds::Shell {
             constraints,
             include_pre,
             lockfile: None,
+            target,
+            prefer,
             ..
         } => {
             let presenter = Presenter::new(&plugin_mgr);
 
             let constraints: Vec<&str> = constraints.iter().map(AsRef::as_ref).collect();
+            let preferences = prefer.map(LockFile::from_file).transpose()?;
             
-            let (distmap, solution) = perform_solve(&plugin_mgr, &constraints, include_pre)?;
+            let (distmap, solution, variants) = perform_pinned_solve_for_target(
+                &plugin_mgr, &constraints, target.as_str(), &[], preferences.as_ref(), include_pre
+            )?;
+            let requested = requested_packages(&constraints)?;
 
             presenter.solve_results_tree(
-                PresentationInput::Constraints(constraints),
-                &(&distmap, &solution),
+                PresentationInput::Constraints{constraints, target: target.as_str()},
+                &(&distmap, &solution, &variants),
             ).expect("present_solve_resutls_tree failed");
 
-            launch_shell(&plugin_mgr, solution)
+            let repos = PackageRepository::from_plugin(&plugin_mgr)?;
+            let env = EnvBuilder::from_plugin(&plugin_mgr)?
+                .with_target(target)
+                .with_requested(requested)
+                .build_for_solution(&repos, &solution, &variants)?;
+            launch_shell(env)
         }
         _ => panic!("SubCmd expected to be SubCmds::Shell variant"),
     }?;
@@ -202,25 +400,21 @@ fn shell_cmd(subcmd: SubCmds) -> Result<(), PesError> {
 }
 
 fn main() {
-    match _main() {
-        Ok(_) => (),
-        Err(PesError::NoSolution(msg)) => {
+    let opt = Opt::from_args();
+    let json = opt.json;
+    // errors are reported, then signalled to the caller by a non-zero exit status
+    match _main(opt) {
+        Ok(_) => return,
+        Err(e) if json => {
+            let report = match e {
+                PesError::NoSolution(failure) => serde_json::to_string_pretty(&failure),
+                e => serde_json::to_string_pretty(&serde_json::json!({ "error": e.to_string() })),
+            };
+            println!("{}", report.expect("unable to serialize error"));
+        }
+        Err(PesError::NoSolution(failure)) => {
             eprintln!("\nError\n");
-            let parts = msg
-                .split("and")
-                .enumerate()
-                .map(|(c, x)| {
-                    if c > 0 {
-                        format!("\tand {}", x)
-                    } else {
-                        format!("{}", x)
-                    }
-                })
-                .collect::<Vec<_>>();
-            for part in parts {
-                eprintln!("{}", part);
-            }
-            eprintln!("");
+            eprintln!("{}", failure);
         }
         Err(e) => {
             eprintln!("\nError\n");
@@ -228,28 +422,23 @@ fn main() {
             eprintln!("");
         }
     };
+    std::process::exit(1);
 }
 
-fn _main() -> Result<(), PesError> {
-    let opt = Opt::from_args();
+fn _main(opt: Opt) -> Result<(), PesError> {
     if opt.debug {
         println!("{:?}", opt);
     }
     let Opt {
-        log_level, subcmd, ..
+        log_level, subcmd, json, ..
     } = opt;
     init_log(&log_level);
     match subcmd {
-        SubCmds::Audit {
-            manifest: Some(manifest),
-        } => {
-            audit_manifest_file(manifest)?;
-        }
+        SubCmds::Audit { manifest } => audit_cmd(manifest, json)?,
         SubCmds::Dist { .. } => dist_cmd(subcmd)?,
-        SubCmds::Audit { manifest: None } => {
-            audit_manifest_for_current_location()?;
-        }
         SubCmds::Env { .. } => env_cmd(subcmd)?,
+        SubCmds::Lock { cmd } => lock_cmd(cmd, json)?,
+        SubCmds::Manifest { cmd } => manifest_cmd(cmd)?,
         SubCmds::Shell { .. } => shell_cmd(subcmd)?,
     };
     Ok(())
@@ -8,15 +8,16 @@ use std::{
 };
 
 use prettytable::{color, format, Attr, Cell, Row, Table};
-use peslib::{PluginMgr, Manifest, PesError, ReleaseType};
+use peslib::{parser::parse_consuming_package_version, PluginMgr, Manifest, PesError, ReleaseType, TargetPolicy, TransitiveTarget};
 use crate::{aliases::SolveRefResult};
 
 
 /// Specify input to presentation method
 #[derive(Debug)]
 pub enum PresentationInput<'a> {
-    Constraints(Vec<&'a str>),
-    Target{distribution: &'a str, target: &'a str}
+    /// the constraints solved, along with the target used to calculate the dependencies of every package
+    Constraints{constraints: Vec<&'a str>, target: &'a str},
+    Target{distribution: &'a str, target: &'a str, target_policy: &'a TargetPolicy}
 }
 
 /// specify a filter for the presenter
@@ -124,11 +125,11 @@ impl<'a> Presenter<'a> {
         fn present<'aa> (
             // vector of one or more requested packages as fed to the solver
             packages: Vec<&'aa str>,
-            // the optional target to solve for. This will default to "run". Furthermore, 
-            // we are only interested in the target when the depth is 0, as we solve for 
-            // transitive dependencies using the "run" target, regardless of the initial target
-            // request
+            // the target requested for the root of the solve. This will default to "run". 
+            // The packages presented here are all dependencies, so the target_policy determines
+            // which of their targets were used to solve for their own dependencies
             target: &str,
+            target_policy: &TargetPolicy,
             // see aliases.rs
             solve: &SolveRefResult, 
             // keep track of visited packages
@@ -144,14 +145,20 @@ impl<'a> Presenter<'a> {
                 // get the distribution from the solve results
                 let version = solve.1.get(pkg).expect(format!("distribution {} not available in solve results  {:#?}",pkg, solve.1).as_str());
                 let distribution = format!("{}-{}", pkg, version);
+                // present the selected variant, if any, alongside the distribution
+                let variant = solve.2.get(pkg);
+                let label = match variant {
+                    Some(variant) => format!("{} [{}]", &distribution, variant),
+                    None => distribution.clone(),
+                };
                 if memo.contains(&distribution) {
                     // if it is in the memo, simply present with a ":" in front 
-                    println!("{}:{}", &indent, &distribution)
+                    println!("{}:{}", &indent, &label)
                 } else {
                     // otherwise, add it to the memo, print it out, and invoke present recursively 
                     //(which means opening the manifest and building constraints), capturing memo from the return value
                     memo.insert(distribution.to_string());
-                    println!("{}{}", &indent, &distribution);
+                    println!("{}{}", &indent, &label);
                     // get manifest and list of constraints
                     let distpath = solve.0.get(&distribution).expect(format!("unable to get path to distribution {} from DistPathMap: {:#?} and solve: {:#?}", &distribution, solve.0, solve.1).as_str());
                     let  distpath = PathBuf::from(distpath.as_str());
@@ -160,13 +167,17 @@ impl<'a> Presenter<'a> {
                     // open the manifest and get the constraints for the target
                     let manifest = Manifest::from_path(manifest_path).expect("couldnt construct manifest from path");
                     // construct the package vec from the requires
-                    let requires = manifest.get_requires(target).unwrap_or_else(|_| Vec::new());
+                    let mut requires = manifest.get_transitive_requires(target_policy, target).unwrap_or_else(|_| Vec::new());
+                    if let Some(variant) = variant.and_then(|v| manifest.variant(v)) {
+                        requires.append(&mut variant.get_all_requires().unwrap_or_else(|_| Vec::new()));
+                    }
                     let constraints = requires.iter().map(|r| { r.name.to_string() }).collect::<Vec<_>>();
                     let constraints_ref = constraints.iter().map(AsRef::as_ref).collect::<Vec<_>>();
 
                     memo = present(
                         constraints_ref,
                         target,
+                        target_policy,
                         solve, 
                         memo,
                         depth+1,
@@ -189,13 +200,23 @@ impl<'a> Presenter<'a> {
         // create the package_distribution_map used to associate the package name with the distribution
         //let pkg_dist_map = new_package_dist_map(&solve.0);
 
+        // a constraint solve uses the requested target all the way down
+        let constraints_policy = match &requirements {
+            PresentationInput::Constraints{target, ..} => TargetPolicy::new(TransitiveTarget::Target(target.to_string())),
+            PresentationInput::Target{..} => TargetPolicy::default(),
+        };
         // construct constraints from requirements
-        let (constraints, target) = match requirements {
-            PresentationInput::Constraints(constraints) => {
-                let c = constraints.iter().map(|v| package_from_dist(v).to_string()).collect::<Vec<_>>();
-                (c, "run")
+        let (constraints, target, target_policy) = match requirements {
+            PresentationInput::Constraints{constraints, target} => {
+                // weak requirements do not pull packages into the solve, so there is nothing to present for them
+                let c = constraints
+                    .iter()
+                    .filter(|v| !v.starts_with('~'))
+                    .map(|v| package_from_dist(v).to_string())
+                    .collect::<Vec<_>>();
+                (c, target, &constraints_policy)
             },
-            PresentationInput::Target{distribution, target} => {
+            PresentationInput::Target{distribution, target, target_policy} => {
                 // split the package from the distribution
                 //let package = package_from_dist(distribution);
                 // look up the distribution path from the solve.0
@@ -207,9 +228,13 @@ impl<'a> Presenter<'a> {
                 // open the manifest and get the constraints for the target
                 let manifest = Manifest::from_path(manifest_path).expect("couldnt construct manifest from path");
                 // construct the package vec from the requires
-                let requires = manifest.get_requires(target).expect("unable to get requires for target");
+                let mut requires = manifest.get_requires(target).expect("unable to get requires for target");
+                let package = parse_consuming_package_version(distribution).map(|(name, _)| name).unwrap_or(distribution);
+                if let Some(variant) = solve.2.get(package).and_then(|v| manifest.variant(v)) {
+                    requires.append(&mut variant.get_all_requires().expect("unable to get requires for variant"));
+                }
                 let constraints = requires.iter().map(|r| { r.name.to_string() }).collect::<Vec<_>>();
-                (constraints, target)
+                (constraints, target, target_policy)
             }
         };
         // convert constraints from Vec<String> to Vec<&str>
@@ -217,6 +242,7 @@ impl<'a> Presenter<'a> {
         let _ = present(
             constraints_ref,
             target,
+            target_policy,
             solve, 
             memo,
             0,
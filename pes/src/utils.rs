@@ -1,15 +1,16 @@
 //! utils command
 use std::{
-    cell::RefCell, collections::HashMap, env, ffi::CString, path::PathBuf, rc::Rc, str::FromStr,
+    collections::HashMap, env, ffi::CString, path::PathBuf, str::FromStr,
 };
 
-use itertools::join;
 use log::{debug, info, trace};
 use nix::unistd::execve;
 use peslib::{
-    constants::{MANIFEST_NAME, ROOT_REQUEST},
-    jsys::*, parser::parse_consuming_all_paths_with_provider, prelude::*,
-    PluginMgr, SelectedDependencies, SemanticVersion
+    constants::MANIFEST_NAMES,
+    audit::{self, AuditReport}, manifest::{ManifestFormat, PackageManifest, MANIFEST_SCHEMA}, 
+    parser::{parse_consuming_package_range, parse_consuming_weak_package_range},
+    prelude::*,
+    PluginMgr
 };
 
 /// validate that a distribution exists
@@ -25,16 +26,40 @@ pub fn check_distribution<D: AsRef<str>>(plugin_mgr: &PluginMgr, dist: D) -> Res
 }
 
 /// audit manifest file for issues
-pub fn audit_manifest_file<M: Into<PathBuf>>(manifest: M) -> Result<bool, PesError> {
-    let manifest = Manifest::from_path_unchecked(manifest)?;
-    manifest.validate()?;
+pub fn audit_manifest_file<M: Into<PathBuf>>(manifest: M) -> Result<AuditReport, PesError> {
+    audit::audit_manifest_file(manifest.into())
+}
 
-    Ok(true)
+/// Migrate the manifest file to the latest schema, rewriting it in its original format. Should `dry_run` be
+/// true, the migrated manifest is printed rather than written. As rewriting the manifest discards its comments and
+/// formatting, an outdated manifest is only rewritten given `discard_formatting`. Returns the schema the manifest
+/// was written against.
+pub fn migrate_manifest_file<M: Into<PathBuf>>(manifest: M, dry_run: bool, discard_formatting: bool) -> Result<u32, PesError> {
+    let manifest = manifest.into();
+    let contents = std::fs::read_to_string(&manifest)?;
+    let format = ManifestFormat::for_file(&manifest, &contents);
+    let (package_manifest, schema) = PackageManifest::from_str_migrated(&contents, format)?;
+    if dry_run {
+        if schema < MANIFEST_SCHEMA {
+            eprintln!("Warning: migrating {} discards its comments and formatting", manifest.display());
+        }
+        println!("{}", package_manifest.to_string_as(format)?);
+    } else if schema < MANIFEST_SCHEMA {
+        if !discard_formatting {
+            return Err(PesError::CliArgError(format!(
+                "migrating {} discards its comments and formatting. Pass --discard-formatting to rewrite it regardless",
+                manifest.display()
+            )));
+        }
+        package_manifest.validate()?;
+        std::fs::write(&manifest, package_manifest.to_string_as(format)?)?;
+    }
+    Ok(schema)
 }
 
 /// Given the CWD, find the manifest, assuming you are calling from within a package, and then
 /// perform an audit.
-pub fn audit_manifest_for_current_location() -> Result<bool, PesError> {
+pub fn audit_manifest_for_current_location() -> Result<AuditReport, PesError> {
     let manifest = find_manifest()?;
     audit_manifest_file(manifest)
 }
@@ -46,15 +71,14 @@ pub fn find_manifest() -> Result<PathBuf, PesError> {
     info!("searching for manifest in {:?}", &cwd);
 
     loop {
-        cwd.push(MANIFEST_NAME);
-        if cwd.exists() {
-            info!("find_manifest() - Found manifest: {:?}", &cwd);
-            return Ok(cwd);
+        for name in MANIFEST_NAMES {
+            let manifest = cwd.join(name);
+            if manifest.exists() {
+                info!("find_manifest() - Found manifest: {:?}", &manifest);
+                return Ok(manifest);
+            }
         }
-        // pop off manifest name and parent levbel
-        if cwd.pop() == false {
-            break;
-        };
+        // pop off the parent level
         if cwd.pop() == false {
             break;
         };
@@ -63,6 +87,15 @@ pub fn find_manifest() -> Result<PathBuf, PesError> {
     Err(PesError::ManifestNotFound(env::current_dir()?))
 }
 
+/// Retrieve the name of the current host, should it be available
+pub fn hostname() -> Option<String> {
+    let mut buffer = [0u8; 256];
+    nix::unistd::gethostname(&mut buffer)
+        .ok()
+        .map(|name| name.to_string_lossy().into_owned())
+        .filter(|name| !name.is_empty())
+}
+
 /// Initialize the log given the provided level
 pub fn init_log(level: &str) {
     match level {
@@ -74,111 +107,52 @@ pub fn init_log(level: &str) {
     pretty_env_logger::init();
 }
 
-/// launch an interactive shell given a solution
-pub fn launch_shell(
-    plugin_mgr: &PluginMgr,
-    solution: SelectedDependencies<String, SemanticVersion>,
-) -> Result<(), PesError> {
-    
-    // iterate through the solve. For each package version, find it in a repository and store it
-    // in a hashmap
-    fn build_manifest_hashmap(
-        solution: SelectedDependencies<String, SemanticVersion>, 
-        repos: &Vec<PackageRepository>
-    ) -> Result< HashMap::<String, (PathBuf, Manifest)>, PesError> {
-        let mut manifests = HashMap::<String, (PathBuf, Manifest)>::new();
-        // define a var to hold a list of distributions for which we cannot find manifests
-        let mut missing_manifests = Vec::new();
-        // solution is a HashMap of (package,version) pairs
-        for (package, version) in solution.iter() {
-            // search through repositories for registered manifests
-            let mut manifest_path = None;
-            for repo in repos {
-                let version_str = version.to_string();
-                // let distribution = PathBuf::from("")
-                match repo.manifest(package, &version_str) {
-                    Ok(path) => manifest_path = Some(path),
-                    Err(_) => (),
-                }
-            }
-            // if we found a manifest path, construct the actual manifest and
-            // add it to the hashmap tracking manifests
-            if let Some(mut path) = manifest_path {
-                let distribution = format!("{}-{}", package, version);
-                let mani = Manifest::from_path(&path)?;
-                // remove manifest from path
-                // todo: introduce abstraction for finding manifest & root of package
-                path.pop();
-                manifests.insert(distribution, (path, mani));
-            } else if package.as_str() != ROOT_REQUEST {
-                let distribution = format!("{}-{}", package, version);
-                // if we were unable to find the manifest, add it to the list of missing manifests
-                missing_manifests.push(distribution);
-            }
-        }
-    
-        if missing_manifests.len() > 0 {
-            Err(PesError::MissingManifests(missing_manifests))
-        } else {
-            Ok(manifests)
-        }
-    }
-
-    // construct a list of repositories
-    let repos = PackageRepository::from_plugin(plugin_mgr)?;
-    let manifests = build_manifest_hashmap(solution, &repos)?;
-    
-    // hashmap to store env vars
-    //let mut env_vars = HashMap::new();
-    let jsys = JsysCleanEnv::new();
-    // TODO: change base_env2 call to base_env
-    let mut env_vars = jsys.base_env2();
-    // instantiate provider
-    let provider = std::rc::Rc::new(RefCell::new(BasicVarProvider::new()));
+/// Retrieve the names of the packages requested by a list of constraints (eg maya-2019 ~python-3), in the
+/// order in which they were requested
+pub fn requested_packages<'a>(constraints: &[&'a str]) -> Result<Vec<&'a str>, PesError> {
+    constraints
+        .iter()
+        .map(|constraint| {
+            let (name, _) = if constraint.trim_start().starts_with('~') {
+                parse_consuming_weak_package_range(constraint)?
+            } else {
+                parse_consuming_package_range(constraint)?
+            };
+            Ok(name)
+        })
+        .collect()
+}
 
-    // iterate through package manifests, building environment
-    for (name, (root, manifest)) in manifests {
-        debug!("name: {}", name);
-        {
-            let mut prov = provider.borrow_mut();
-            prov.insert_var("root", root.as_path().display().to_string());
-        }
-        for (key, value) in manifest.environment() {
-            debug!("{} {}", &key, value);
-            let result = parse_consuming_all_paths_with_provider(Rc::clone(&provider), value)?;
-            debug!("{:?}", result);
-            {
-                if let Some(val) = env_vars.get_mut(key) {
-                    *val += result;
-                } else {
-                    env_vars.insert(key.clone(), result);
-                }
-            }
-        }
-    }
-    let mut c_env_vars: Vec<std::ffi::CString> = Vec::with_capacity(env_vars.len());
+// convert an environment into the `NAME=value` strings expected by execve, sorted by name such that
+// the environment is identical between runs
+fn execve_env(env_vars: HashMap<String, String>) -> Vec<CString> {
+    let mut env_vars = env_vars.into_iter().collect::<Vec<_>>();
+    env_vars.sort();
     debug!("OUTPUT VARS");
-    // construct environment vec<CString> for execve call
-    for (k, v) in env_vars {
-        let existing_paths = v.inner();
-        // construct required format for execve
-        let existing_paths = format!("{}={}", k, join(existing_paths, ":"));
-        info!("{}", &existing_paths);
-        //let existing_paths : std::ffi::CString = existing_paths.bytes().into();
-        let existing_paths =
-            std::ffi::CString::new(&existing_paths[..]).expect("unable to convert to cstring");
-        c_env_vars.push(existing_paths);
-    }
+    env_vars
+        .into_iter()
+        .map(|(k, v)| {
+            let var = format!("{}={}", k, v);
+            info!("{}", &var);
+            CString::new(var).expect("unable to convert to cstring")
+        })
+        .collect()
+}
 
-    // identify shell
+// replace the current process with /usr/bin/env, invoked with the supplied args within the environment
+fn exec_env(env_vars: HashMap<String, String>, args: Vec<CString>) -> Result<(), PesError> {
     let env_cmd = CString::new("/usr/bin/env").unwrap();
+    // call execve with environment vec. execve only returns should it fail
+    let Err(e) = execve(&env_cmd, &args[..], &execve_env(env_vars)[..]);
+    Err(PesError::PesError(format!("unable to execute /usr/bin/env: {}", e)))
+}
+
+/// launch an interactive shell within the supplied environment (see `peslib::EnvBuilder`)
+pub fn launch_shell(env_vars: HashMap<String, String>) -> Result<(), PesError> {
+    // identify shell
     let shell = std::env::var("SHELL").unwrap_or("bash".to_string());
     let shell = Shell::from_str(shell.as_str())?;
-    let args = shell.env_args();
-
-    // call execve with environment vec
-    execve(&env_cmd, &args[..], &c_env_vars[..]).unwrap();
-    Ok(())
+    exec_env(env_vars, shell.env_args())
 }
 
 // store the args needed to launch a shell for the shell subcommand
@@ -221,106 +195,13 @@ impl Shell {
 }
 
 
-pub fn launch_cmd(
-    plugin_mgr: &PluginMgr,
-    solution: SelectedDependencies<String, SemanticVersion>,
-    cmd: &str
-) -> Result<(), PesError> {
-    
-    // iterate through the solve. For each package version, find it in a repository and store it
-    // in a hashmap
-    fn build_manifest_hashmap(
-        solution: SelectedDependencies<String, SemanticVersion>, 
-        repos: &Vec<PackageRepository>
-    ) -> Result< HashMap::<String, (PathBuf, Manifest)>, PesError> {
-        let mut manifests = HashMap::<String, (PathBuf, Manifest)>::new();
-        // define a var to hold a list of distributions for which we cannot find manifests
-        let mut missing_manifests = Vec::new();
-        // solution is a HashMap of (package,version) pairs
-        for (package, version) in solution.iter() {
-            // search through repositories for registered manifests
-            let mut manifest_path = None;
-            for repo in repos {
-                let version_str = version.to_string();
-                // let distribution = PathBuf::from("")
-                match repo.manifest(package, &version_str) {
-                    Ok(path) => manifest_path = Some(path),
-                    Err(_) => (),
-                }
-            }
-            // if we found a manifest path, construct the actual manifest and
-            // add it to the hashmap tracking manifests
-            if let Some(mut path) = manifest_path {
-                let distribution = format!("{}-{}", package, version);
-                let mani = Manifest::from_path(&path)?;
-                // remove manifest from path
-                // todo: introduce abstraction for finding manifest & root of package
-                path.pop();
-                manifests.insert(distribution, (path, mani));
-            } else if package.as_str() != ROOT_REQUEST {
-                let distribution = format!("{}-{}", package, version);
-                // if we were unable to find the manifest, add it to the list of missing manifests
-                missing_manifests.push(distribution);
-            }
-        }
-    
-        if missing_manifests.len() > 0 {
-            Err(PesError::MissingManifests(missing_manifests))
-        } else {
-            Ok(manifests)
-        }
-    }
-
-    // construct a list of repositories
-    let repos = PackageRepository::from_plugin(plugin_mgr)?;
-    let manifests = build_manifest_hashmap(solution, &repos)?;
-    
-    // hashmap to store env vars
-    //let mut env_vars = HashMap::new();
-    let jsys = JsysCleanEnv::new();
-    // TODO: change base_env2 call to base_env
-    let mut env_vars = jsys.base_env2();
-    // instantiate provider
-    let provider = std::rc::Rc::new(RefCell::new(BasicVarProvider::new()));
-
-    // iterate through package manifests, building environment
-    for (name, (root, manifest)) in manifests {
-        debug!("name: {}", name);
-        {
-            let mut prov = provider.borrow_mut();
-            prov.insert_var("root", root.as_path().display().to_string());
-        }
-        for (key, value) in manifest.environment() {
-            debug!("{} {}", &key, value);
-            let result = parse_consuming_all_paths_with_provider(Rc::clone(&provider), value)?;
-            debug!("{:?}", result);
-            {
-                if let Some(val) = env_vars.get_mut(key) {
-                    *val += result;
-                } else {
-                    env_vars.insert(key.clone(), result);
-                }
-            }
-        }
-    }
-    let mut c_env_vars: Vec<std::ffi::CString> = Vec::with_capacity(env_vars.len());
-    debug!("OUTPUT VARS");
-    // construct environment vec<CString> for execve call
-    for (k, v) in env_vars {
-        let existing_paths = v.inner();
-        // construct required format for execve
-        let existing_paths = format!("{}={}", k, join(existing_paths, ":"));
-        info!("{}", &existing_paths);
-        //let existing_paths : std::ffi::CString = existing_paths.bytes().into();
-        let existing_paths =
-            std::ffi::CString::new(&existing_paths[..]).expect("unable to convert to cstring");
-        c_env_vars.push(existing_paths);
-    }
-
-    let env_cmd = CString::new("/usr/bin/env").unwrap();
-    let arg = CString::new(cmd).expect("Unable to convert to cstring");
-    let args = vec![arg];
-    // call execve with environment vec
-    execve(&env_cmd, &args[..], &c_env_vars[..]).unwrap();
-    Ok(())
+/// execute a command, with the supplied arguments, within the supplied environment (see `peslib::EnvBuilder`)
+pub fn launch_cmd<S: AsRef<str>>(env_vars: HashMap<String, String>, cmd: &str, args: &[S]) -> Result<(), PesError> {
+    // env's argv[0] is its own name, followed by the command to run and its arguments
+    let args = std::iter::once("env")
+        .chain(std::iter::once(cmd))
+        .chain(args.iter().map(AsRef::as_ref))
+        .map(|arg| CString::new(arg).map_err(|e| PesError::PesError(format!("invalid argument {}: {}", arg, e))))
+        .collect::<Result<Vec<_>, _>>()?;
+    exec_env(env_vars, args)
 }
@@ -0,0 +1,145 @@
+//! The base environment of a pes shell
+//!
+//! Before the environments of the packages in a solve are applied, pes starts from a base
+//! environment, derived from that of the calling process. Which of the caller's variables make
+//! it into the base environment is decided by a `BaseEnvPolicy`, provided by the *base_env*
+//! plugin. A policy may be read from a yaml or toml config file, like so:
+//!
+//! ```toml
+//! mode = "whitelist"
+//! vars = ["HOME", "USER", "PATH"]
+//! ```
+//!
+//! The `mode` is one of `whitelist`, `blacklist` or `pass-through`, the latter of which takes no `vars`.
+//! Should `PES_BASE_ENV` name such a file, it supplies the default policy (see `BaseEnvPolicy::from_env`).
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::PesError;
+
+/// The name of the environment variable naming the base environment config file
+pub const BASE_ENV_VARNAME: &str = "PES_BASE_ENV";
+
+/// The variables, needed by a desktop session, which are retained in the absence of a config file
+pub const DEFAULT_WHITELIST: &[&str] = &[
+    "JSYS_PROJECT",
+    "JSYS_SEQUENCE",
+    "JSYS_SHOT",
+    "JSYS_LEVEL",
+    "JSYS_ROOT",
+    "_",
+    "COLORTERM",
+    "DBUS_SESSION_BUS_ADDRESS",
+    "DEFAULTS_PATH",
+    "DESKTOP_SESSION",
+    "DISPLAY",
+    "GDMSESSION",
+    "G_ENABLE_DIAGNOSTIC",
+    "GNOME_DESKTOP_SESSION_ID",
+    "GNOME_SHELL_SESSION_MODE",
+    "GNOME_TERMINAL_SCREEN",
+    "GNOME_TERMINAL_SERVICE",
+    "GPG_AGENT_INFO",
+    "GTK_IM_MODULE",
+    "GTK_MODULES",
+    "HOME",
+    "INVOCATION_ID",
+    "JOURNAL_STREAM",
+    "LANG",
+    "LANGUAGE",
+    "LC_ADDRESS",
+    "LC_IDENTIFICATION",
+    "LC_MEASUREMENT",
+    "LC_MONETARY",
+    "LC_NAME",
+    "LC_NUMERIC",
+    "LC_PAPER",
+    "LC_TELEPHONE",
+    "LC_TIME",
+    "LESSCLOSE",
+    "LESSOPEN",
+    "LOGNAME",
+    "LS_COLORS",
+    "MANAGERPID",
+    "MANDATORY_PATH",
+    "PAPERSIZE",
+    "PATH",
+    "PWD",
+    "QT_ACCESSIBILITY",
+    "QT_IM_MODULE",
+    "SESSION_MANAGER",
+    "SHELL",
+    "SHLVL",
+    "SSH_AGENT_PID",
+    "SSH_AUTH_SOCK",
+    "TERM",
+    "USER",
+    "USERNAME",
+    "VTE_VERSION",
+    "WINDOWPATH",
+    "XAUTHORITY",
+    "XDG_CONFIG_DIRS",
+    "XDG_CURRENT_DESKTOP",
+    "XDG_DATA_DIRS",
+    "XDG_MENU_PREFIX",
+    "XDG_RUNTIME_DIR",
+    "XDG_SESSION_CLASS",
+    "XDG_SESSION_DESKTOP",
+    "XDG_SESSION_TYPE",
+    "XMODIFIERS",
+];
+
+/// Policy determining which variables of the calling process's environment are retained in
+/// the base environment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "kebab-case")]
+pub enum BaseEnvPolicy {
+    /// Retain only the listed variables
+    Whitelist { vars: Vec<String> },
+    /// Retain every variable except those listed
+    Blacklist { vars: Vec<String> },
+    /// Retain every variable
+    PassThrough,
+}
+
+impl BaseEnvPolicy {
+    /// Retrieve the default policy. This is read from the config file named by `PES_BASE_ENV`, should it be
+    /// set, and whitelists `DEFAULT_WHITELIST` otherwise.
+    pub fn from_env() -> Result<Self, PesError> {
+        match std::env::var(BASE_ENV_VARNAME) {
+            Ok(config) => Self::from_file(config),
+            Err(_) => Ok(Self::Whitelist { vars: DEFAULT_WHITELIST.iter().map(|var| var.to_string()).collect() }),
+        }
+    }
+
+    /// Read a policy from a config file. The file is parsed as toml should its extension be `toml`, and
+    /// as yaml otherwise.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, PesError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Ok(toml::from_str(&contents)?),
+            _ => Ok(serde_yaml::from_str(&contents)?),
+        }
+    }
+
+    /// Determine whether the policy retains the supplied variable
+    pub fn retains(&self, var: &str) -> bool {
+        match self {
+            Self::Whitelist { vars } => vars.iter().any(|v| v == var),
+            Self::Blacklist { vars } => !vars.iter().any(|v| v == var),
+            Self::PassThrough => true,
+        }
+    }
+
+    /// Apply the policy to an environment, given as (name, value) pairs (eg `std::env::vars()`), returning
+    /// the variables retained
+    pub fn apply<I: IntoIterator<Item = (String, String)>>(&self, environment: I) -> HashMap<String, String> {
+        environment.into_iter().filter(|(var, _)| self.retains(var)).collect()
+    }
+}
+
+#[cfg(test)]
+#[path = "./unit_tests/base_env.rs"]
+mod unit_tests;
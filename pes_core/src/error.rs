@@ -1,5 +1,6 @@
 //! Custom error types and type aliases for Pes
 use std::path::PathBuf;
+use crate::solve_failure::SolveFailure;
 use thiserror::Error as ThisError;
 use toml;
 use serde_yaml;
@@ -66,6 +67,22 @@ pub enum PesError {
     #[error("Distribution path not found for distribution: {0}")]
     DistributionPathNotFound(String),
 
+    /// A distribution uses the version reserved by the solver to represent an absent package
+    #[error("Distribution {0:?} uses version 0.0.0-alpha, which is reserved to represent an absent package")]
+    ReservedVersion(PathBuf),
+
+    /// Two distributions of a package have versions which differ only by build metadata, and thus
+    /// share the same precedence
+    #[error("Distributions {first:?} and {second:?} of '{package}' differ only by build metadata")]
+    DuplicateDistribution {
+        /// The package
+        package: String,
+        /// Path to the first distribution
+        first: PathBuf,
+        /// Path to the second distribution
+        second: PathBuf,
+    },
+
     /// Indicates an include specified in the manifest does not
     /// map to a target
     #[error("Missing Include '{include:?}' for target '{target:?}' ")]
@@ -75,10 +92,70 @@ pub enum PesError {
         /// The include name
         include: String,
     },
+    /// An environment expression references variables which are not defined
+    #[error("Unknown variable(s) {} in environment expression '{expression}'", .variables.join(", "))]
+    UnknownVariables {
+        /// The environment expression
+        expression: String,
+        /// The unknown variables, as written (eg `{rooot}` or `$HOEM`)
+        variables: Vec<String>,
+    },
+
+    /// The includes of a target form a cycle
+    #[error("Circular target include: {}", .0.join(" -> "))]
+    CircularInclude(Vec<String>),
+
+    /// A package appears in more than one of the targets contributing to a target, with ranges which do not overlap
+    #[error("Target '{target}' constrains '{package}' to incompatible ranges: {first} and {second}")]
+    IncompatibleRanges {
+        /// The target
+        target: String,
+        /// The package
+        package: String,
+        /// The range contributed by the first target
+        first: String,
+        /// The range contributed by a subsequent target
+        second: String,
+    },
+
     /// Problem parsing path
     #[error("Invalid Path: {0:?}")]
     InvalidPath(PathBuf),
 
+    /// The name of a package variant is invalid
+    #[error("Invalid Variant name: '{0}'")]
+    InvalidVariant(String),
+
+    /// The schema of a manifest is malformed
+    #[error("Invalid manifest schema: {0}")]
+    InvalidSchema(String),
+
+    /// The schema of a manifest is unknown to this version of pes
+    #[error("Unsupported manifest schema {schema}. Schemas 1 through {latest} are supported")]
+    UnsupportedSchema {
+        /// The schema declared by the manifest
+        schema: u64,
+        /// The latest schema supported
+        latest: u64,
+    },
+
+    /// The schema of a lockfile is unknown to this version of pes
+    #[error("Unsupported lockfile schema {schema}. Schemas 1 through {latest} are supported")]
+    UnsupportedLockFileSchema {
+        /// The schema declared by the lockfile
+        schema: u64,
+        /// The latest schema supported
+        latest: u64,
+    },
+
+    /// The lockfile records neither a root distribution nor constraints, and so cannot be re-solved
+    #[error("The lockfile does not record the request it solved, and so cannot be re-solved")]
+    MissingLockRequest,
+
+    /// The package is not locked for any target of the lockfile
+    #[error("Package '{0}' is not locked")]
+    PackageNotLocked(String),
+
     /// Indicates that an io::Error has taken place
     #[error("io::Error {0:?}")]
     IoError(#[from] std::io::Error),
@@ -91,8 +168,9 @@ pub enum PesError {
     #[error("PesError {0}")]
     PesError(String),
 
-    #[error("No solution for request {0}")]
-    NoSolution(String),
+    /// The solver was unable to find a dependency closure satisfying the request
+    #[error("No solution for request\n{0}")]
+    NoSolution(Box<SolveFailure>),
 
     #[error("No Repositories Found at Path(2): {0}")]
     NoRepositories(String),
@@ -2,11 +2,12 @@
 //! plugins for peslib.
 //! 
 //! ## Plugins
-//! There are currently two types of plugins:
+//! There are currently three types of plugins:
 //! - A *repo_finder*, which is responsible for locating package repositories
 //! - A *manifest_finder*, which is responsible for finding the manifest within a distribution. 
+//! - A *base_env*, which is responsible for determining the base environment of a pes shell. 
 //!
-//! Both of these tasks are highly dependent upon a particular organization's designs, and the 
+//! Each of these tasks are highly dependent upon a particular organization's designs, and the 
 //! plugin system affords the most flexibility in terms of adapting the system to a given 
 //! organization's needs without having to fork the core.
 //!
@@ -16,14 +17,18 @@
 //! one should prefer using the error types from the *pes_core* crate when authoring a plugin.
 
 pub mod traits;
+pub mod base_env;
 pub mod error;
 pub mod semantic_version;
 pub mod parser_atoms;
 pub mod release_type;
+pub mod solve_failure;
 pub use error::{PesError, PNResult, PesNomError, PNCompleteResult};
 pub use traits::*;
+pub use base_env::BaseEnvPolicy;
 pub use semantic_version::SemanticVersion;
 pub use release_type::ReleaseType;
+pub use solve_failure::{FailureCause, FailureTerm, Relation, SolveFailure};
 
 pub mod prelude {
     pub use super::*;
@@ -5,7 +5,8 @@
 //! - beta
 //! - release candidate (rc)
 //! - release
-//! These variants are listed in sort order. 
+//!   These variants are listed in sort order. Pre-releases may additionally be numbered (eg beta.2), 
+//!   which is handled by the `SemanticVersion`.
 //! 
 //! We also provide conversions from and to strings
 use std::{
@@ -2,7 +2,9 @@
 //! Traits and implementations to create and compare versions.
 
 use std::{
+    cmp::Ordering,
     fmt::{self, Debug, Display},
+    hash::{Hash, Hasher},
     str::FromStr
 };
 use crate::{error::PesError, ReleaseType};
@@ -10,16 +12,60 @@ use pubgrub::version::Version;
 
 
 
-/// Type for semantic versions: major.minor.patch or major.minor,patch-release_type
+/// Type for semantic versions: major.minor.patch or major.minor,patch-release_type, optionally 
+/// followed by a pre-release number and build metadata.
 /// Examples:
 /// - 1.2.3-beta
+/// - 1.2.3-beta.2 (the second beta)
+/// - 1.2.3+build.77 (build metadata)
 /// - 1.2.3 (implicitly releast type Release)
-#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+///
+/// As per the semver 2.0 spec, a pre-release without a number precedes all of the numbered pre-releases 
+/// of the same type (1.2.3-rc < 1.2.3-rc.1 < 1.2.3-rc.10), and build metadata is preserved, but 
+/// ignored when comparing versions.
+#[derive(Debug, Clone)]
 pub struct SemanticVersion {
     pub major: u32,
     pub minor: u32,
     pub patch: u32,
-    pub release_type: ReleaseType
+    pub release_type: ReleaseType,
+    /// The number of the pre-release, should it have one (eg 2 in 1.2.3-beta.2)
+    pub pre_release: Option<u32>,
+    /// Build metadata (eg build.77 in 1.2.3+build.77)
+    pub build: Option<String>,
+}
+
+impl SemanticVersion {
+    // the components of the version which participate in precedence
+    fn precedence(&self) -> (u32, u32, u32, ReleaseType, Option<u32>) {
+        (self.major, self.minor, self.patch, self.release_type, self.pre_release)
+    }
+}
+
+impl PartialEq for SemanticVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.precedence() == other.precedence()
+    }
+}
+
+impl Eq for SemanticVersion {}
+
+impl Hash for SemanticVersion {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.precedence().hash(state)
+    }
+}
+
+impl PartialOrd for SemanticVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SemanticVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.precedence().cmp(&other.precedence())
+    }
 }
 
 
@@ -52,8 +98,24 @@ impl SemanticVersion {
             major,
             minor,
             patch,
-            release_type
+            release_type,
+            pre_release: None,
+            build: None,
+        }
+    }
+
+    /// Set the number of the pre-release (eg 1.2.3-beta.2). This is ignored for releases.
+    pub fn with_pre_release(mut self, pre_release: u32) -> Self {
+        if self.release_type != ReleaseType::Release {
+            self.pre_release = Some(pre_release);
         }
+        self
+    }
+
+    /// Set the build metadata (eg 1.2.3+build.77)
+    pub fn with_build<B: Into<String>>(mut self, build: B) -> Self {
+        self.build = Some(build.into());
+        self
     }
     
     /// Version 0.0.0.
@@ -63,6 +125,12 @@ impl SemanticVersion {
         Self::new(0, 0, 0, ReleaseType::Release)
     }
 
+    /// Version 0.0.0-alpha. This is the lowest possible version. The solver reserves it to
+    /// represent a package which is absent from the dependency closure.
+    pub fn lowest() -> Self {
+        Self::new(0, 0, 0, ReleaseType::Alpha)
+    }
+
     /// Version 1.0.0.
     pub fn one() -> Self {
         Self::new(1, 0, 0, ReleaseType::Release)
@@ -106,6 +174,20 @@ impl Into<(u32, u32, u32, ReleaseType)> for SemanticVersion {
 
 // Bump versions.
 impl SemanticVersion {
+    /// Retrieve the smallest version which is greater than this one, disregarding build metadata. 
+    /// The successor of a pre-release is the next numbered pre-release of the same type, while the
+    /// successor of a release is the first alpha of the next patch.
+    pub fn next(&self) -> Self {
+        match (self.release_type, self.pre_release) {
+            (ReleaseType::Release, _) => Self::new(self.major, self.minor, self.patch + 1, ReleaseType::Alpha),
+            (release_type, None) => Self::new(self.major, self.minor, self.patch, release_type).with_pre_release(0),
+            (release_type, Some(number)) => match number.checked_add(1) {
+                Some(number) => Self::new(self.major, self.minor, self.patch, release_type).with_pre_release(number),
+                None => Self::new(self.major, self.minor, self.patch, release_type).bump_release_type(),
+            },
+        }
+    }
+
     /// Bump up the ReleaseType. If the current ReleaseType is Release, then bump the patch
     pub fn bump_release_type(self) -> Self {
         match self.release_type {
@@ -129,7 +211,7 @@ impl SemanticVersion {
     pub fn bump_major(self) -> Self {
         Self::new(self.major + 1, 0, 0, self.release_type)
     }
-    ///
+
     pub fn release(self) -> Self {
         Self::new(self.major, self.minor, self.patch, ReleaseType::Release)
     }
@@ -147,67 +229,70 @@ impl FromStr for SemanticVersion {
                 parse_error: e.to_string(),
             })
         };
-
-        let pieces = s.split('-').collect::<Vec<_>>();
-        match &pieces[..] {
-            [version, release_type] => {
-                let mut parts = version.split('.');
-                match (parts.next(), parts.next(), parts.next()) {
-                    (Some(major), Some(minor), Some(patch)) => {
-                        let major = parse_u32(major)?;
-                        let minor = parse_u32(minor)?;
-                        let patch = parse_u32(patch)?;
-                        let release_type = ReleaseType::from_str(release_type)?;
-                        Ok(Self {
-                            major,
-                            minor,
-                            patch,
-                            release_type
-                        })
-                    },
-                    _ => Err(Self::Err::InvalidSemanticVersion(s.to_string())),
+        // build metadata follows the first '+', and consists of dot separated, non-empty, alphanumeric identifiers
+        let (version, build) = match s.split_once('+') {
+            Some((version, build)) => {
+                let valid = build
+                    .split('.')
+                    .all(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'));
+                if !valid {
+                    return Err(Self::Err::InvalidSemanticVersion(s.to_string()));
                 }
+                (version, Some(build))
             }
-            [version] => {
-                let mut parts = version.split('.');
-                match (parts.next(), parts.next(), parts.next()) {
-                    (Some(major), Some(minor), Some(patch)) => {
-                        let major = parse_u32(major)?;
-                        let minor = parse_u32(minor)?;
-                        let patch = parse_u32(patch)?;
-            
-                        Ok(Self::new(
-                            major,
-                            minor,
-                            patch,
-                            ReleaseType::Release
-                        ))
-                    },
-                    _ => Err(Self::Err::InvalidSemanticVersion(s.to_string())),
-                }
-            },
-            _ => Err(Self::Err::InvalidSemanticVersion(s.to_string()))
+            None => (s, None),
+        };
+        let (version, pre_release) = match version.split_once('-') {
+            Some((version, pre_release)) => (version, Some(pre_release)),
+            None => (version, None),
+        };
+        let mut parts = version.split('.');
+        let mut semver = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(major), Some(minor), Some(patch), None) => {
+                Self::new(parse_u32(major)?, parse_u32(minor)?, parse_u32(patch)?, ReleaseType::Release)
+            }
+            _ => return Err(Self::Err::InvalidSemanticVersion(s.to_string())),
+        };
+        if let Some(pre_release) = pre_release {
+            // the pre-release is a release type, optionally followed by a number (eg beta.2)
+            let (release_type, number) = match pre_release.split_once('.') {
+                Some((release_type, number)) => (release_type, Some(parse_u32(number)?)),
+                None => (pre_release, None),
+            };
+            semver.release_type = ReleaseType::from_str(release_type)?;
+            if semver.release_type == ReleaseType::Release && number.is_some() {
+                return Err(Self::Err::InvalidSemanticVersion(s.to_string()));
+            }
+            semver.pre_release = number;
         }
-        
+        semver.build = build.map(|b| b.to_string());
+        Ok(semver)
     }
 }
 
 impl Display for SemanticVersion {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.release_type {
-            ReleaseType::Release =>     write!(f, "{}.{}.{}", self.major, self.minor, self.patch),
-            _ => write!(f, "{}.{}.{}-{}", self.major, self.minor, self.patch, self.release_type),
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if self.release_type != ReleaseType::Release {
+            write!(f, "-{}", self.release_type)?;
+            if let Some(number) = self.pre_release {
+                write!(f, ".{}", number)?;
+            }
+        }
+        if let Some(build) = self.build.as_ref() {
+            write!(f, "+{}", build)?;
         }
+        Ok(())
     }
 }
 
 // Implement Version for SemanticVersion.
 impl Version for SemanticVersion {
     fn lowest() -> Self {
-        Self::zero()
+        SemanticVersion::lowest()
     }
     fn bump(&self) -> Self {
-        self.bump_release_type()
+        self.next()
     }
 }
 
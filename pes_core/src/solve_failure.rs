@@ -0,0 +1,204 @@
+//! Structured explanations of failed solves
+//!
+//! When the solver is unable to find a dependency closure satisfying a request, it produces a
+//! derivation tree explaining why. The `SolveFailure` models that tree in terms of pes concepts -
+//! packages, version ranges, distributions, and the manifests which introduced each constraint - such
+//! that it may either be presented to the user (via its `Display` implementation, which produces an
+//! indented explanation) or serialized for consumption by other tools.
+use std::{
+    collections::HashSet,
+    fmt,
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// The relationship between a distribution and another package it places a constraint upon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Relation {
+    /// The package must be in the closure, within the range
+    Requires,
+    /// The package must be within the range, should it be in the closure
+    WeakRequires,
+    /// The package must not be in the closure within the range
+    Conflicts,
+}
+
+impl fmt::Display for Relation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Relation::Requires => write!(f, "requires"),
+            Relation::WeakRequires => write!(f, "weakly requires"),
+            Relation::Conflicts => write!(f, "conflicts with"),
+        }
+    }
+}
+
+/// A statement about a range of a package, which participates in an incompatibility
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailureTerm {
+    /// Name of the package. None refers to the request itself
+    pub package: Option<String>,
+    /// The range of the package
+    pub range: String,
+    /// Whether the term asserts that the package is within the range (true), or outside of it (false)
+    pub positive: bool,
+}
+
+impl FailureTerm {
+    // describe the package the term refers to
+    fn subject(&self) -> String {
+        match self.package.as_ref() {
+            Some(package) => format!("{} {}", package, self.range),
+            None => "the request".to_string(),
+        }
+    }
+}
+
+/// The reason for a failed solve, modeled as a tree of incompatibilities. The leaves of
+/// the tree are facts about the repositories, and each branch is derived from its two causes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FailureCause {
+    /// The package must be picked as the root of the solve
+    NotRoot {
+        package: String,
+        version: String,
+    },
+    /// There are no distributions of the package within the range
+    NoVersions {
+        package: String,
+        range: String,
+    },
+    /// The dependencies of distributions of the package within the range could not be retrieved
+    Unavailable {
+        package: String,
+        range: String,
+    },
+    /// A constraint declared by distributions of a package (or by the request, should `package` be None)
+    Dependency {
+        /// Name of the package declaring the constraint. None refers to the request itself
+        package: Option<String>,
+        /// The range of the package declaring the constraint
+        range: String,
+        /// The distributions within the range which declare the constraint
+        distributions: Vec<String>,
+        /// The manifests of those distributions
+        manifests: Vec<PathBuf>,
+        /// The kind of constraint
+        relation: Relation,
+        /// Name of the constrained package
+        dependency: String,
+        /// The range of the constrained package named by the constraint
+        dependency_range: String,
+    },
+    /// An incompatibility derived from two others. The `terms` may not all hold at once
+    Derived {
+        terms: Vec<FailureTerm>,
+        /// Identifies incompatibilities which appear more than once in the tree
+        shared_id: Option<usize>,
+        causes: Vec<FailureCause>,
+    },
+}
+
+/// A structured explanation of a failed solve
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SolveFailure {
+    /// The root of the explanation
+    pub cause: FailureCause,
+}
+
+impl SolveFailure {
+    /// Construct a new SolveFailure given the root of the explanation
+    pub fn new(cause: FailureCause) -> Self {
+        Self { cause }
+    }
+}
+
+impl fmt::Display for SolveFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut explained = HashSet::new();
+        self.cause.report(f, 0, &mut explained)
+    }
+}
+
+impl FailureCause {
+    /// Describe the incompatibility in a single sentence
+    pub fn summary(&self) -> String {
+        match self {
+            FailureCause::NotRoot { package, version } => format!("{} {} is not the root of the solve", package, version),
+            FailureCause::NoVersions { package, range } => format!("no distributions of {} match {}", package, range),
+            FailureCause::Unavailable { package, range } => {
+                format!("the dependencies of {} {} are unavailable", package, range)
+            }
+            FailureCause::Dependency { package, range, relation, dependency, dependency_range, .. } => {
+                let subject = match package {
+                    Some(package) => format!("{} {}", package, range),
+                    None => "the request".to_string(),
+                };
+                format!("{} {} {} {}", subject, relation, dependency, dependency_range)
+            }
+            FailureCause::Derived { terms, .. } => Self::terms_summary(terms),
+        }
+    }
+
+    // describe a set of terms which may not all hold at once
+    fn terms_summary(terms: &[FailureTerm]) -> String {
+        match terms {
+            [] => "version solving failed".to_string(),
+            [term] if term.package.is_none() && term.positive => "the request cannot be satisfied".to_string(),
+            [term] if term.positive => format!("{} cannot be selected", term.subject()),
+            [term] => format!("{} is required", term.subject()),
+            [first, second] if first.package.is_none() && first.positive && second.positive => {
+                format!("the request is incompatible with {}", second.subject())
+            }
+            [first, second] if first.positive != second.positive => {
+                let (positive, negative) = if first.positive { (first, second) } else { (second, first) };
+                format!("{} requires {}", positive.subject(), negative.subject())
+            }
+            terms => {
+                let terms = terms
+                    .iter()
+                    .map(|t| if t.positive { t.subject() } else { format!("not {}", t.subject()) })
+                    .collect::<Vec<_>>();
+                format!("{} are incompatible", terms.join(" and "))
+            }
+        }
+    }
+
+    // write the explanation at the provided depth, followed by its causes
+    fn report(&self, f: &mut fmt::Formatter<'_>, depth: usize, explained: &mut HashSet<usize>) -> fmt::Result {
+        let indent = "   ".repeat(depth);
+        match self {
+            FailureCause::Dependency { manifests, .. } => {
+                writeln!(f, "{}{}", indent, self.summary())?;
+                for manifest in manifests {
+                    writeln!(f, "{}   declared in {}", indent, manifest.display())?;
+                }
+                Ok(())
+            }
+            FailureCause::Derived { shared_id: Some(id), .. } if explained.contains(id) => {
+                writeln!(f, "{}{} (see [{}])", indent, self.summary(), id)
+            }
+            FailureCause::Derived { shared_id, causes, .. } => {
+                match shared_id {
+                    Some(id) => {
+                        explained.insert(*id);
+                        writeln!(f, "{}{} [{}] because:", indent, self.summary(), id)?
+                    }
+                    None => writeln!(f, "{}{} because:", indent, self.summary())?,
+                }
+                for cause in causes {
+                    cause.report(f, depth + 1, explained)?;
+                }
+                Ok(())
+            }
+            _ => writeln!(f, "{}{}", indent, self.summary()),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./unit_tests/solve_failure.rs"]
+mod unit_tests;
@@ -2,6 +2,8 @@ use std::path::PathBuf;
 use std::str::FromStr;
 use std::fmt::{Display, Debug};
 use std::hash::Hash;
+
+use crate::{BaseEnvPolicy, PesError};
 /// trait to find repositories in the job system
 pub trait RepoFinderService {
     /// Find repository paths
@@ -15,6 +17,13 @@ pub trait ManifestFinderService {
     fn find_manifest(&self, distribution: PathBuf) -> PathBuf;
 }
 
+/// trait to determine which variables of the calling environment make it into the base environment of
+/// a pes shell
+pub trait BaseEnvService {
+    /// Retrieve the policy applied to the calling environment
+    fn base_env_policy(&self) -> Result<BaseEnvPolicy, PesError>;
+}
+
 /// trait which must be implemented to satisfy releasetype. This trait is not currently used. 
 /// The plan is to use it and perhaps provide a plugin capability as well.
 pub trait ReleaseTypeProvider: Debug + Display + FromStr + Copy + Clone + Ord + PartialOrd + Eq + PartialEq + Hash {
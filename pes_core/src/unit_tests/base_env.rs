@@ -0,0 +1,53 @@
+#![allow(non_snake_case)]
+
+use super::*;
+
+fn environment() -> Vec<(String, String)> {
+    vec![
+        ("HOME".to_string(), "/home/jgerber".to_string()),
+        ("USER".to_string(), "jgerber".to_string()),
+        ("LD_PRELOAD".to_string(), "/lib/libfoo.so".to_string()),
+    ]
+}
+
+fn keys(environment: HashMap<String, String>) -> Vec<String> {
+    let mut keys = environment.into_iter().map(|(k, _)| k).collect::<Vec<_>>();
+    keys.sort();
+    keys
+}
+
+#[test]
+fn apply__given_whitelist__retains_listed_vars() {
+    let policy = BaseEnvPolicy::Whitelist { vars: vec!["HOME".into(), "SHELL".into()] };
+    assert_eq!(keys(policy.apply(environment())), vec!["HOME"]);
+}
+
+#[test]
+fn apply__given_blacklist__retains_unlisted_vars() {
+    let policy = BaseEnvPolicy::Blacklist { vars: vec!["LD_PRELOAD".into()] };
+    assert_eq!(keys(policy.apply(environment())), vec!["HOME", "USER"]);
+}
+
+#[test]
+fn apply__given_pass_through__retains_all_vars() {
+    assert_eq!(keys(BaseEnvPolicy::PassThrough.apply(environment())), vec!["HOME", "LD_PRELOAD", "USER"]);
+}
+
+#[test]
+fn deserialize__given_toml__succeeds() {
+    let policy: BaseEnvPolicy = toml::from_str("mode = \"blacklist\"\nvars = [\"LD_PRELOAD\"]").unwrap();
+    assert_eq!(policy, BaseEnvPolicy::Blacklist { vars: vec!["LD_PRELOAD".into()] });
+}
+
+#[test]
+fn deserialize__given_yaml__succeeds() {
+    let policy: BaseEnvPolicy = serde_yaml::from_str("mode: whitelist\nvars:\n  - HOME\n").unwrap();
+    assert_eq!(policy, BaseEnvPolicy::Whitelist { vars: vec!["HOME".into()] });
+    let policy: BaseEnvPolicy = serde_yaml::from_str("mode: pass-through").unwrap();
+    assert_eq!(policy, BaseEnvPolicy::PassThrough);
+}
+
+#[test]
+fn deserialize__given_unknown_mode__fails() {
+    assert!(serde_yaml::from_str::<BaseEnvPolicy>("mode: greylist").is_err());
+}
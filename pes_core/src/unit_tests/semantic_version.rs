@@ -3,33 +3,82 @@
 use super::*;
 
 #[test]
-fn bump__given_release__should_bump_patch() {
+fn bump__given_release__should_bump_patch_to_first_alpha() {
     let semver = SemanticVersion::new(1,2,3,ReleaseType::Release);
     let semver = semver.bump();
-    let expect = SemanticVersion::new(1,2,4,ReleaseType::Release);
+    let expect = SemanticVersion::new(1,2,4,ReleaseType::Alpha);
     assert_eq!(semver, expect);
 }
 
 #[test]
-fn bump__given_ReleaseCandidate__should_bump_release_type_to_Release() {
+fn bump__given_ReleaseCandidate__should_bump_to_first_numbered_ReleaseCandidate() {
     let semver = SemanticVersion::new(1,2,3,ReleaseType::ReleaseCandidate);
     let semver = semver.bump();
-    let expect = SemanticVersion::new(1,2,3,ReleaseType::Release);
+    let expect = SemanticVersion::new(1,2,3,ReleaseType::ReleaseCandidate).with_pre_release(0);
     assert_eq!(semver, expect);
 }
 
 #[test]
-fn bump__given_Beta__should_bump_release_type_to_ReleaseCandidate() {
-    let semver = SemanticVersion::new(1,2,3,ReleaseType::Beta);
+fn bump__given_numbered_Beta__should_bump_pre_release_number() {
+    let semver = SemanticVersion::new(1,2,3,ReleaseType::Beta).with_pre_release(2);
     let semver = semver.bump();
-    let expect = SemanticVersion::new(1,2,3,ReleaseType::ReleaseCandidate);
+    let expect = SemanticVersion::new(1,2,3,ReleaseType::Beta).with_pre_release(3);
     assert_eq!(semver, expect);
 }
 
 #[test]
-fn bump__given_Alpha__should_bump_release_type_to_Beta() {
+fn bump_release_type__given_Alpha__should_bump_release_type_to_Beta() {
     let semver = SemanticVersion::new(1,2,3,ReleaseType::Alpha);
-    let semver = semver.bump();
+    let semver = semver.bump_release_type();
     let expect = SemanticVersion::new(1,2,3,ReleaseType::Beta);
     assert_eq!(semver, expect);
 }
+
+#[test]
+fn from_str__given_numbered_pre_release__should_parse() {
+    let semver = SemanticVersion::from_str("1.2.3-beta.2").expect("unable to parse");
+    assert_eq!(semver, SemanticVersion::new(1,2,3,ReleaseType::Beta).with_pre_release(2));
+    assert_eq!(semver.to_string(), "1.2.3-beta.2");
+}
+
+#[test]
+fn from_str__given_build_metadata__should_preserve_build() {
+    let semver = SemanticVersion::from_str("1.2.3-rc.10+build.77").expect("unable to parse");
+    assert_eq!(semver.build.as_deref(), Some("build.77"));
+    assert_eq!(semver.to_string(), "1.2.3-rc.10+build.77");
+}
+
+#[test]
+fn from_str__given_invalid_build_metadata__should_fail() {
+    assert!(SemanticVersion::from_str("1.2.3+").is_err());
+    assert!(SemanticVersion::from_str("1.2.3+build..77").is_err());
+    assert!(SemanticVersion::from_str("1.2.3-beta.two").is_err());
+}
+
+#[test]
+fn cmp__given_numbered_pre_releases__should_order_numerically() {
+    let versions = ["1.2.3-alpha", "1.2.3-alpha.1", "1.2.3-beta", "1.2.3-beta.2", "1.2.3-beta.11", "1.2.3-rc.1", "1.2.3", "1.2.4-alpha"]
+        .iter()
+        .map(|v| SemanticVersion::from_str(v).unwrap())
+        .collect::<Vec<_>>();
+    let mut sorted = versions.clone();
+    sorted.reverse();
+    sorted.sort();
+    assert_eq!(sorted, versions);
+}
+
+#[test]
+fn eq__given_differing_build_metadata__should_be_equal() {
+    let first = SemanticVersion::from_str("1.2.3+build.1").unwrap();
+    let second = SemanticVersion::from_str("1.2.3+build.2").unwrap();
+    assert_eq!(first, second);
+    assert_eq!(first.cmp(&second), std::cmp::Ordering::Equal);
+}
+
+#[test]
+fn deserialize__given_build_metadata__should_round_trip() {
+    let semver: SemanticVersion = serde_yaml::from_str("1.2.3-beta.2+sha.5114f85").expect("unable to deserialize");
+    assert_eq!(semver.build.as_deref(), Some("sha.5114f85"));
+    let serialized = serde_yaml::to_string(&semver).expect("unable to serialize");
+    assert!(serialized.contains("1.2.3-beta.2+sha.5114f85"));
+}
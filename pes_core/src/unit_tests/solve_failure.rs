@@ -0,0 +1,72 @@
+#![allow(non_snake_case)]
+
+use super::*;
+
+fn dependency(package: &str, relation: Relation, dependency: &str) -> FailureCause {
+    FailureCause::Dependency {
+        package: Some(package.to_string()),
+        range: "1.0.0".to_string(),
+        distributions: vec![format!("{}-1.0.0", package)],
+        manifests: vec![PathBuf::from(format!("/repo/{}/1.0.0/manifest.yaml", package))],
+        relation,
+        dependency: dependency.to_string(),
+        dependency_range: "1.0.0".to_string(),
+    }
+}
+
+fn term(package: Option<&str>, positive: bool) -> FailureTerm {
+    FailureTerm { package: package.map(|p| p.to_string()), range: "1.0.0".to_string(), positive }
+}
+
+#[test]
+fn summary__given_dependency__describes_relation() {
+    let cause = dependency("foo", Relation::Conflicts, "bar");
+    assert_eq!(cause.summary(), "foo 1.0.0 conflicts with bar 1.0.0");
+}
+
+#[test]
+fn summary__given_positive_and_negative_terms__describes_requirement() {
+    let cause = FailureCause::Derived {
+        terms: vec![term(Some("foo"), true), term(Some("bar"), false)],
+        shared_id: None,
+        causes: Vec::new(),
+    };
+    assert_eq!(cause.summary(), "foo 1.0.0 requires bar 1.0.0");
+}
+
+#[test]
+fn to_string__given_derived_failure__indents_causes() {
+    let failure = SolveFailure::new(FailureCause::Derived {
+        terms: vec![term(None, true)],
+        shared_id: None,
+        causes: vec![
+            dependency("foo", Relation::Conflicts, "bar"),
+            dependency("baz", Relation::Requires, "bar"),
+        ],
+    });
+    let expected = "\
+the request cannot be satisfied because:
+   foo 1.0.0 conflicts with bar 1.0.0
+      declared in /repo/foo/1.0.0/manifest.yaml
+   baz 1.0.0 requires bar 1.0.0
+      declared in /repo/baz/1.0.0/manifest.yaml
+";
+    assert_eq!(failure.to_string(), expected);
+}
+
+#[test]
+fn to_string__given_shared_incompatibility__explains_once() {
+    let shared = FailureCause::Derived {
+        terms: vec![term(Some("foo"), true)],
+        shared_id: Some(3),
+        causes: vec![dependency("foo", Relation::Requires, "bar")],
+    };
+    let failure = SolveFailure::new(FailureCause::Derived {
+        terms: vec![term(None, true)],
+        shared_id: None,
+        causes: vec![shared.clone(), shared],
+    });
+    let report = failure.to_string();
+    assert_eq!(report.matches("declared in").count(), 1);
+    assert!(report.contains("(see [3])"));
+}
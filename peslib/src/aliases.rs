@@ -18,9 +18,12 @@ pub type DistPathMap = indexmap::IndexMap<String, String>;
 // a map of package to distribution
 pub type PackageDistMap = indexmap::IndexMap<String, String>;
 
+/// A Map whose key is a package and whose value is the name of the variant selected for it
+pub type VariantMap = indexmap::IndexMap<String, String>;
+
 /// 
 pub type SolveDistributions = SelectedDependencies<String, SemanticVersion>;
 
 /// Tuple returned by perform_solve function
-pub type SolveResult = (DistPathMap, SolveDistributions);
-pub type SolveRefResult<'a> = (&'a DistPathMap, &'a SolveDistributions);
+pub type SolveResult = (DistPathMap, SolveDistributions, VariantMap);
+pub type SolveRefResult<'a> = (&'a DistPathMap, &'a SolveDistributions, &'a VariantMap);
@@ -0,0 +1,657 @@
+//! Auditing of package manifests
+//!
+//! The audit engine inspects a manifest for problems, reporting every problem it finds at once, rather than
+//! stopping at the first. Each problem is reported as a `Diagnostic`, carrying a `Severity` and the location
+//! within the manifest file at which it was found. Errors are problems which prevent the manifest from being
+//! used (eg an invalid range, or an environment expression which cannot be evaluated), while warnings flag
+//! likely mistakes which pes otherwise tolerates (eg an unknown key, which is ignored).
+//!
+//! The `AuditReport` may either be presented to the user, via its `Display` implementation, or serialized.
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+
+use crate::{
+    env::{BasicVarProvider, PathToken, PACKAGE_ROOT_SUFFIX, PACKAGE_VARIABLES},
+    manifest::{migration, ManifestFormat, PackageManifest, MANIFEST_SCHEMA},
+    parser::{parse_consuming_all_paths_with_provider, parse_consuming_semver, parse_consuming_semver_range, referenced_variables},
+    PesError,
+};
+
+// the keys known at each level of the manifest
+const MANIFEST_KEYS: &[&str] = &["schema", "name", "version", "description", "targets", "environment", "variants"];
+const TARGET_KEYS: &[&str] = &["include", "requires", "conflicts", "weak_requires", "environment"];
+// keys of schema 1, which the migration to schema 2 renames
+const LEGACY_MANIFEST_KEYS: &[&str] = &["target"];
+const LEGACY_TARGET_KEYS: &[&str] = &["include-targets"];
+const VARIANT_KEYS: &[&str] = &["name", "requires", "environment"];
+
+/// The severity of a problem found by an audit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// A likely mistake, which pes tolerates
+    Warning,
+    /// A problem which prevents the manifest from being used
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found by an audit, along with its location
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// The manifest file
+    pub file: PathBuf,
+    /// The line (starting from 1) at which the problem was found, should it be known
+    pub line: Option<usize>,
+    /// The column (starting from 1) at which the problem was found, should it be known
+    pub column: Option<usize>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+        }
+        write!(f, ": {}: {}", self.severity, self.message)
+    }
+}
+
+/// The problems found by auditing a manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditReport {
+    /// The manifest file
+    pub file: PathBuf,
+    /// The problems found, in the order in which they appear in the manifest
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl AuditReport {
+    /// Determine whether the audit found any errors
+    pub fn has_errors(&self) -> bool {
+        self.error_count() > 0
+    }
+
+    /// The number of errors found
+    pub fn error_count(&self) -> usize {
+        self.diagnostics.iter().filter(|d| d.severity == Severity::Error).count()
+    }
+
+    /// The number of warnings found
+    pub fn warning_count(&self) -> usize {
+        self.diagnostics.iter().filter(|d| d.severity == Severity::Warning).count()
+    }
+}
+
+impl fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for diagnostic in &self.diagnostics {
+            writeln!(f, "{}", diagnostic)?;
+        }
+        write!(
+            f,
+            "{}: {} error(s), {} warning(s)",
+            self.file.display(),
+            self.error_count(),
+            self.warning_count()
+        )
+    }
+}
+
+/// Audit the manifest file at the supplied path. The format of the manifest is determined by its
+/// extension, falling back to its contents. An error is returned only should the file be unreadable.
+pub fn audit_manifest_file<P: AsRef<Path>>(path: P) -> Result<AuditReport, PesError> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path)?;
+    let format = ManifestFormat::for_file(path, &contents);
+    Ok(audit_manifest_str(&contents, path, format))
+}
+
+/// Audit the contents of a manifest in the supplied format. The `file` is used to report the location of
+/// problems, and to verify that the name and version of the package match the directories the manifest
+/// resides in.
+pub fn audit_manifest_str<P: Into<PathBuf>>(contents: &str, file: P, format: ManifestFormat) -> AuditReport {
+    let mut auditor = Auditor {
+        locator: SourceLocator::new(contents, format),
+        file: file.into(),
+        diagnostics: Vec::new(),
+    };
+    auditor.audit(contents, format);
+    let Auditor { file, mut diagnostics, .. } = auditor;
+    // stable, so problems at the same location retain the order in which they were found
+    diagnostics.sort_by_key(|d| (d.line.unwrap_or(0), d.column.unwrap_or(0)));
+    AuditReport { file, diagnostics }
+}
+
+// accumulates diagnostics for a single manifest
+struct Auditor<'a> {
+    locator: SourceLocator<'a>,
+    file: PathBuf,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Auditor<'a> {
+    // record a problem at the location of the supplied key path
+    fn report<M: Into<String>>(&mut self, severity: Severity, path: &[&str], message: M) {
+        let location = self.locator.locate(path);
+        self.report_at(severity, location, message);
+    }
+
+    // record a problem at the supplied location
+    fn report_at<M: Into<String>>(&mut self, severity: Severity, location: Option<(usize, usize)>, message: M) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            message: message.into(),
+            file: self.file.clone(),
+            line: location.map(|(line, _)| line),
+            column: location.map(|(_, column)| column),
+        });
+    }
+
+    fn audit(&mut self, contents: &str, format: ManifestFormat) {
+        // the manifest must be well formed before anything else may be checked
+        let document = match parse_document(contents, format) {
+            Ok(document) => document,
+            Err((message, location)) => return self.report_at(Severity::Error, location, message),
+        };
+        let schema = match migration::schema(contents, format) {
+            Ok(schema) => schema,
+            Err(e) => return self.report(Severity::Error, &["schema"], e.to_string()),
+        };
+        if schema < MANIFEST_SCHEMA {
+            self.report(
+                Severity::Warning,
+                &["schema"],
+                format!(
+                    "the manifest is written against schema {}. Run `pes manifest migrate` to upgrade it to schema {}",
+                    schema, MANIFEST_SCHEMA
+                ),
+            );
+        }
+        self.audit_keys(&document, schema);
+        let manifest = match PackageManifest::from_str_migrated(contents, format) {
+            Ok((manifest, _)) => manifest,
+            Err(e) => {
+                let (message, location) = error_details(&e);
+                return self.report_at(Severity::Error, location, message);
+            }
+        };
+        self.audit_location(&manifest);
+        self.audit_targets(&manifest);
+        self.audit_includes(&manifest);
+        self.audit_variants(&manifest);
+        self.audit_environment(&manifest, &["environment"], &manifest.environment, true);
+    }
+
+    // flag keys which pes does not know about, and will therefore ignore. The keys renamed by the migration
+    // from schema 1 are only known to manifests written against schema 1
+    fn audit_keys(&mut self, document: &Value, schema: u32) {
+        let legacy = schema == 1;
+        let is_manifest_key = |key: &str| MANIFEST_KEYS.contains(&key) || (legacy && LEGACY_MANIFEST_KEYS.contains(&key));
+        let is_target_key = |key: &str| TARGET_KEYS.contains(&key) || (legacy && LEGACY_TARGET_KEYS.contains(&key));
+        let mapping = match document.as_mapping() {
+            Some(mapping) => mapping,
+            None => return self.report(Severity::Error, &[], "the manifest must be a mapping"),
+        };
+        for (key, value) in mapping {
+            let key = key_str(key);
+            if !is_manifest_key(&key) {
+                self.report(Severity::Warning, &[&key], format!("unknown key '{}'", key));
+            } else if key == "targets" || key == "target" {
+                for (target, target_value) in value.as_mapping().into_iter().flatten() {
+                    let target = key_str(target);
+                    for (target_key, _) in target_value.as_mapping().into_iter().flatten() {
+                        let target_key = key_str(target_key);
+                        if !is_target_key(&target_key) {
+                            self.report(
+                                Severity::Warning,
+                                &[&key, &target, &target_key],
+                                format!("unknown key '{}' in target '{}'", target_key, target),
+                            );
+                        }
+                    }
+                }
+            } else if key == "variants" {
+                for variant in value.as_sequence().into_iter().flatten() {
+                    for (variant_key, _) in variant.as_mapping().into_iter().flatten() {
+                        let variant_key = key_str(variant_key);
+                        if !VARIANT_KEYS.contains(&variant_key.as_str()) {
+                            self.report(Severity::Warning, &["variants"], format!("unknown key '{}' in variant", variant_key));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // verify that the name and version of the package match the directories of a distribution
+    // (ie <repo>/<name>/<version>/<manifest>), should the manifest reside in one
+    fn audit_location(&mut self, manifest: &PackageManifest) {
+        let file = self.file.clone();
+        let version_dir = file.parent();
+        let version = version_dir
+            .and_then(|dir| dir.file_name())
+            .and_then(|dir| dir.to_str())
+            .and_then(|dir| parse_consuming_semver(dir).ok().map(|version| (dir.to_string(), version)));
+        let (dir, version) = match version {
+            Some(version) => version,
+            None => return,
+        };
+        if version != manifest.version {
+            self.report(
+                Severity::Error,
+                &["version"],
+                format!("version {} does not match the distribution directory '{}'", manifest.version, dir),
+            );
+        }
+        let name = version_dir
+            .and_then(|dir| dir.parent())
+            .and_then(|dir| dir.file_name())
+            .and_then(|dir| dir.to_str())
+            .map(|dir| dir.to_string());
+        if let Some(name) = name {
+            if name != manifest.name {
+                self.report(
+                    Severity::Error,
+                    &["name"],
+                    format!("name '{}' does not match the package directory '{}'", manifest.name, name),
+                );
+            }
+        }
+    }
+
+    // verify the ranges of each target, and that no package is constrained more than once, or by itself
+    fn audit_targets(&mut self, manifest: &PackageManifest) {
+        for (name, target) in manifest.targets.iter() {
+            let sections = [
+                ("requires", &target.requires),
+                ("conflicts", &target.conflicts),
+                ("weak_requires", &target.weak_requires),
+            ];
+            let mut seen = HashSet::new();
+            for (section, ranges) in sections.iter() {
+                for (package, range) in ranges.iter() {
+                    let path = ["targets", name.as_str(), *section, package.as_str()];
+                    self.audit_range(&path, package, range);
+                    if package == &manifest.name {
+                        self.report(
+                            Severity::Error,
+                            &path,
+                            format!("target '{}' {} its own package '{}'", name, describe_section(section), package),
+                        );
+                    }
+                    if !seen.insert(package.as_str()) {
+                        self.report(
+                            Severity::Error,
+                            &path,
+                            format!("package '{}' appears in more than one of the requires, conflicts and weak_requires of target '{}'", package, name),
+                        );
+                    }
+                }
+            }
+            self.audit_environment(manifest, &["targets", name.as_str(), "environment"], &target.environment, true);
+        }
+    }
+
+    // verify that each include names a target, that the includes do not form a cycle, and that the ranges they
+    // contribute are compatible
+    fn audit_includes(&mut self, manifest: &PackageManifest) {
+        for (name, target) in manifest.targets.iter() {
+            for include in target.get_includes() {
+                if !manifest.targets.contains_key(include) {
+                    self.report(
+                        Severity::Error,
+                        &["targets", name, "include"],
+                        format!("target '{}' includes '{}', which is not a target", name, include),
+                    );
+                }
+            }
+        }
+        let mut reported = HashSet::new();
+        for name in manifest.targets.keys() {
+            if let Some(cycle) = find_include_cycle(manifest, name) {
+                // each member of the cycle would otherwise report it
+                let mut members = cycle.clone();
+                members.sort_unstable();
+                members.dedup();
+                if reported.insert(members) {
+                    self.report(
+                        Severity::Error,
+                        &["targets", name, "include"],
+                        format!("circular target includes: {}", cycle.join(" -> ")),
+                    );
+                }
+            }
+        }
+        // a package constrained by more than one of the targets contributing to a target must have overlapping ranges
+        for name in manifest.targets.keys() {
+            let merged = manifest.get_requires(name).and(manifest.get_weak_requires(name));
+            if let Err(PesError::IncompatibleRanges { package, first, second, .. }) = merged {
+                self.report(
+                    Severity::Error,
+                    &["targets", name, "include"],
+                    format!(
+                        "target '{}' includes incompatible ranges of '{}': {} and {}",
+                        name, package, first, second
+                    ),
+                );
+            }
+        }
+    }
+
+    fn audit_variants(&mut self, manifest: &PackageManifest) {
+        for (idx, variant) in manifest.variants.iter().enumerate() {
+            if let Err(PesError::InvalidVariant(name)) = variant.validate() {
+                self.report(Severity::Error, &["variants"], format!("invalid variant name '{}'", name));
+            }
+            if manifest.variants[..idx].iter().any(|v| v.name == variant.name) {
+                self.report(Severity::Error, &["variants"], format!("duplicate variant '{}'", variant.name));
+            }
+            for (package, range) in variant.requires.iter() {
+                self.audit_range(&["variants"], package, range);
+                if package == &manifest.name {
+                    self.report(
+                        Severity::Error,
+                        &["variants"],
+                        format!("variant '{}' requires its own package '{}'", variant.name, package),
+                    );
+                }
+            }
+            self.audit_environment(manifest, &["variants"], &variant.environment, false);
+        }
+    }
+
+    fn audit_range(&mut self, path: &[&str], package: &str, range: &str) {
+        if parse_consuming_semver_range(range).is_err() {
+            self.report(Severity::Error, path, format!("invalid version range '{}' for package '{}'", range, package));
+        }
+    }
+
+    // verify that each environment expression references known variables, and may be evaluated. Should
+    // `keyed` be true, each variable is located by its key beneath the path. Otherwise, by the path alone.
+    fn audit_environment(&mut self, manifest: &PackageManifest, path: &[&str], environment: &IndexMap<String, String>, keyed: bool) {
+        let packages = required_packages(manifest);
+        for (key, value) in environment.iter() {
+            let mut var_path = path.to_vec();
+            if keyed {
+                var_path.push(key);
+            }
+            let variables = referenced_variables(value);
+            // evaluate the expression with every variable defined, so that only its syntax is checked
+            let mut provider = BasicVarProvider::new();
+            let mut env = HashMap::new();
+            for token in variables.iter() {
+                match token {
+                    PathToken::Variable(name) => {
+                        provider.insert_var(*name, *name);
+                        if PACKAGE_VARIABLES.contains(name) {
+                            continue;
+                        }
+                        match name.strip_suffix(PACKAGE_ROOT_SUFFIX) {
+                            Some(package) if packages.contains(package) => (),
+                            Some(package) => self.report(
+                                Severity::Warning,
+                                &var_path,
+                                format!("the value of {} references the root of '{}', which the package does not require", key, package),
+                            ),
+                            None => self.report(
+                                Severity::Error,
+                                &var_path,
+                                format!("unknown variable '{{{}}}' in the value of {}", name, key),
+                            ),
+                        }
+                    }
+                    // environment variables are only known once the environment is composed
+                    PathToken::EnvVariable(name) => {
+                        env.insert(name.to_string(), name.to_string());
+                    }
+                    _ => (),
+                }
+            }
+            provider.set_environment(env);
+            if parse_consuming_all_paths_with_provider(Rc::new(RefCell::new(provider)), value).is_err() {
+                self.report(
+                    Severity::Error,
+                    &var_path,
+                    format!("invalid environment expression '{}' for {}", value, key),
+                );
+            }
+        }
+    }
+}
+
+// describe the relationship a section of a target establishes
+fn describe_section(section: &str) -> &'static str {
+    match section {
+        "conflicts" => "conflicts with",
+        "weak_requires" => "weakly requires",
+        _ => "requires",
+    }
+}
+
+// parse the contents into a generic document, reporting the error message and location upon failure
+fn parse_document(contents: &str, format: ManifestFormat) -> Result<Value, (String, Option<(usize, usize)>)> {
+    match format {
+        ManifestFormat::Yaml => serde_yaml::from_str::<Value>(contents).map_err(|e| {
+            let location = e.location().map(|l| (l.line(), l.column()));
+            (e.to_string(), location)
+        }),
+        ManifestFormat::Toml => toml::from_str::<toml::Value>(contents)
+            .map_err(|e| {
+                let location = e.line_col().map(|(line, column)| (line + 1, column + 1));
+                (e.to_string(), location)
+            })
+            .and_then(|value| serde_yaml::to_value(value).map_err(|e| (e.to_string(), None))),
+    }
+}
+
+// retrieve the message of a deserialization error, along with its location, should it carry one
+fn error_details(error: &PesError) -> (String, Option<(usize, usize)>) {
+    match error {
+        PesError::SerdeYamlDeserializeError(e) => (e.to_string(), e.location().map(|l| (l.line(), l.column()))),
+        PesError::TomlDeserializeError(e) => (e.to_string(), e.line_col().map(|(line, column)| (line + 1, column + 1))),
+        e => (e.to_string(), None),
+    }
+}
+
+// render a key of a generic document as a string
+fn key_str(key: &Value) -> String {
+    match key {
+        Value::String(key) => key.clone(),
+        other => serde_yaml::to_string(other)
+            .map(|s| s.trim_start_matches("---").trim().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+// the packages required, weakly or otherwise, by any target or variant of the manifest
+fn required_packages(manifest: &PackageManifest) -> HashSet<&str> {
+    let targets = manifest.targets.values().flat_map(|t| t.requires.keys().chain(t.weak_requires.keys()));
+    let variants = manifest.variants.iter().flat_map(|v| v.requires.keys());
+    targets.chain(variants).map(String::as_str).collect()
+}
+
+// Find a cycle of target includes, starting from the supplied target, returning the targets which
+// form it, with the first repeated at the end (eg run -> build -> run)
+fn find_include_cycle<'a>(manifest: &'a PackageManifest, start: &'a str) -> Option<Vec<&'a str>> {
+    fn visit<'a>(manifest: &'a PackageManifest, target: &'a str, stack: &mut Vec<&'a str>) -> Option<Vec<&'a str>> {
+        if let Some(pos) = stack.iter().position(|t| *t == target) {
+            let mut cycle = stack[pos..].to_vec();
+            cycle.push(target);
+            return Some(cycle);
+        }
+        stack.push(target);
+        let includes = manifest.targets.get(target).map(|t| t.get_includes()).unwrap_or_default();
+        for include in includes {
+            if let Some(cycle) = visit(manifest, include, stack) {
+                return Some(cycle);
+            }
+        }
+        stack.pop();
+        None
+    }
+    visit(manifest, start, &mut Vec::new()).filter(|cycle| cycle[0] == start)
+}
+
+/// Locates keys within the source of a manifest, in order to report the location of problems. Location
+/// is best effort; should a key not be found, the location of the nearest enclosing key is used.
+struct SourceLocator<'a> {
+    lines: Vec<&'a str>,
+    format: ManifestFormat,
+}
+
+impl<'a> SourceLocator<'a> {
+    fn new(contents: &'a str, format: ManifestFormat) -> Self {
+        Self { lines: contents.lines().collect(), format }
+    }
+
+    /// Retrieve the line and column (each starting from 1) of the key path (eg ["targets", "run", "requires"])
+    fn locate(&self, path: &[&str]) -> Option<(usize, usize)> {
+        let location = match self.format {
+            ManifestFormat::Yaml => self.locate_yaml(path),
+            ManifestFormat::Toml => self.locate_toml(path),
+        };
+        // the targets may be keyed by their legacy name
+        match (location, path.split_first()) {
+            (None, Some((&"targets", rest))) => {
+                let mut legacy = vec!["target"];
+                legacy.extend(rest);
+                self.locate(&legacy)
+            }
+            (location, _) => location,
+        }
+    }
+
+    // find each key of the path amongst the children of its predecessor, by indentation
+    fn locate_yaml(&self, path: &[&str]) -> Option<(usize, usize)> {
+        let mut found = None;
+        let mut start = 0;
+        let mut parent: Option<usize> = None;
+        for key in path {
+            // the indentation of the children of the parent, set by the first child
+            let mut level = None;
+            let mut hit = None;
+            for (idx, line) in self.lines.iter().enumerate().skip(start) {
+                let trimmed = line.trim_start();
+                if trimmed.is_empty() || trimmed.starts_with('#') || trimmed == "---" {
+                    continue;
+                }
+                let content = trimmed.trim_start_matches("- ");
+                let indent = line.len() - content.len();
+                if parent.map(|p| indent <= p).unwrap_or(false) {
+                    break;
+                }
+                if *level.get_or_insert(indent) != indent {
+                    continue;
+                }
+                if key_matches(content, key, ':') {
+                    hit = Some((idx, indent));
+                    break;
+                }
+            }
+            match hit {
+                Some((idx, indent)) => {
+                    found = Some((idx + 1, indent + 1));
+                    start = idx + 1;
+                    parent = Some(indent);
+                }
+                None => break,
+            }
+        }
+        found
+    }
+
+    // find the longest prefix of the path which names a table, and the remainder of the path within the table
+    fn locate_toml(&self, path: &[&str]) -> Option<(usize, usize)> {
+        for split in (0..=path.len()).rev() {
+            let (table, keys) = path.split_at(split);
+            let region = match self.toml_table(table) {
+                Some(region) => region,
+                None => continue,
+            };
+            if keys.is_empty() {
+                // the root table has no header
+                return if table.is_empty() { None } else { Some((region.0, 1)) };
+            }
+            // the remainder may be a dotted key, or the start of an inline table
+            for candidate in &[keys.join("."), keys[0].to_string()] {
+                for idx in region.0..region.1 {
+                    let line = self.lines[idx];
+                    let content = line.trim_start();
+                    if key_matches(content, candidate, '=') {
+                        return Some((idx + 1, line.len() - content.len() + 1));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    // retrieve the line of the header of the table (starting from 1, or 0 for the root table),
+    // along with the index of the line following the table's last line
+    fn toml_table(&self, table: &[&str]) -> Option<(usize, usize)> {
+        let start = if table.is_empty() {
+            0
+        } else {
+            let header = table.join(".");
+            self.lines.iter().position(|line| {
+                let line = line.trim();
+                line.strip_prefix('[')
+                    .and_then(|line| line.strip_suffix(']'))
+                    .map(|line| line.trim_matches(|c| c == '[' || c == ']' || c == ' ') == header)
+                    .unwrap_or(false)
+            })? + 1
+        };
+        let end = self.lines[start..]
+            .iter()
+            .position(|line| line.trim_start().starts_with('['))
+            .map(|pos| pos + start)
+            .unwrap_or_else(|| self.lines.len());
+        Some((start, end))
+    }
+}
+
+// determine whether the content begins with the key (optionally quoted), followed by the separator
+fn key_matches(content: &str, key: &str, separator: char) -> bool {
+    let rest = if let Some(rest) = content.strip_prefix(key) {
+        rest
+    } else {
+        let quoted = ["\"", "'"].iter().find_map(|q| {
+            content
+                .strip_prefix(q)
+                .and_then(|c| c.strip_prefix(key))
+                .and_then(|c| c.strip_prefix(q))
+        });
+        match quoted {
+            Some(rest) => rest,
+            None => return false,
+        }
+    };
+    rest.trim_start().starts_with(separator)
+}
+
+#[cfg(test)]
+#[path = "./unit_tests/audit.rs"]
+mod unit_tests;
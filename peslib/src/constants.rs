@@ -5,8 +5,23 @@ pub const PACKAGE_REPO_PATH_VAR_NAME: &str = "PES_PACKAGE_REPO_PATH";
 /// The name of the manifest file
 pub const MANIFEST_NAME: &str = "manifest.yaml";
 
+/// The names a manifest file may have, in order of precedence
+pub const MANIFEST_NAMES: &[&str] = &[MANIFEST_NAME, "manifest.toml"];
+
 /// Name of the root package constructed to 
 pub const ROOT_REQUEST: &str  = "ROOT_REQUEST";
 
 pub const REPO_FINDER_VARNAME: &str = "REPO_FINDER_PLUGIN";
-pub const MANIFEST_FINDER_VARNAME: &str = "MANIFEST_FINDER_PLUGIN";
\ No newline at end of file
+pub const MANIFEST_FINDER_VARNAME: &str = "MANIFEST_FINDER_PLUGIN";
+
+/// The name of the environment variable naming the base environment config file
+pub use pes_core::base_env::BASE_ENV_VARNAME;
+
+/// The name of the environment variable that defines where repository indices are cached
+pub const CACHE_DIR_VARNAME: &str = "PES_CACHE_DIR";
+
+/// The target whose requires are used by default
+pub const DEFAULT_TARGET: &str = "run";
+
+/// Suffix appended to a distribution to form the name of the synthetic package used to select its variant
+pub const VARIANT_PACKAGE_SUFFIX: &str = "::variant";
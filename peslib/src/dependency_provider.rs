@@ -0,0 +1,525 @@
+//! A pubgrub `DependencyProvider` which lazily queries repositories for the packages
+//! visited by the solver.
+//!
+//! Rather than registering every distribution in every repository up front, as one does with
+//! pubgrub's `OfflineDependencyProvider`, the `RepositoryDependencyProvider` only lists versions and
+//! reads manifests for a package the first time the solver asks about it. The results are memoized
+//! for the duration of the solve, and backed by each repository's `RepositoryIndex`, so that solve time
+//! scales with the size of the dependency closure rather than with the size of the repositories.
+//!
+//! Package variants are modeled as a synthetic package per distribution (see `variant_package`), 
+//! whose versions correspond to the distribution's variants, in order of preference. A distribution 
+//! with variants depends upon its synthetic variant package, and each version of the variant package 
+//! carries the variant's requires. The solver thus picks the variant compatible with the rest of the closure.
+//!
+//! Conflicts require the ability to express that a package is absent from the closure, something pubgrub
+//! has no notion of, as it must select a version for every package it visits. We therefore reserve the lowest 
+//! version (see `absent_version`) to mean "absent". Every package has this version, which has no dependencies,
+//! and which is preferred whenever it is allowed. Requirements exclude it, whereas a conflict with a range `R` 
+//! of a package is expressed as a dependency upon the complement of `R`, which includes it. Similarly, a weak
+//! requirement upon a range `R` of a package is expressed as a dependency upon `R` plus the absent version.
+//! As a consequence, no distribution may use the absent version (0.0.0-alpha) itself; the `RepositoryIndex`
+//! rejects any which does.
+//!
+//! The provider may also be given preferred versions (and variants), typically those of a lockfile (see
+//! `Solver::with_lockfile_preferences`). Short of being absent, a package's preferred version is tried before
+//! any other, so that a solve changes as few of the preferred versions as the request allows.
+use std::{
+    borrow::Borrow,
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    path::PathBuf,
+    rc::Rc,
+};
+
+use indexmap::IndexMap;
+use log::{debug, warn};
+use pubgrub::{
+    range::Range,
+    solver::{choose_package_with_fewest_versions, Dependencies, DependencyConstraints, DependencyProvider},
+};
+
+use crate::{
+    aliases::VariantMap,
+    constants::{DEFAULT_TARGET, ROOT_REQUEST, VARIANT_PACKAGE_SUFFIX},
+    distribution_range::DistributionRange,
+    parser::parse_consuming_package_version,
+    repository_index::{cache_file_for, RepositoryIndex},
+    PesError,
+    Relation,
+    ReleaseType,
+    Repository,
+    SemanticVersion,
+    solver::SolveRequest,
+    TargetPolicy,
+};
+
+/// A distribution as seen by the solver
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProvidedDistribution {
+    /// Path to the root of the distribution
+    pub path: PathBuf,
+    /// Path to the manifest of the distribution
+    pub manifest: PathBuf,
+    /// The requirements of the distribution, for the target selected by the `TargetPolicy`
+    pub requires: Vec<(String, Range<SemanticVersion>)>,
+    /// The ranges of packages which the distribution conflicts with, for the target selected by the `TargetPolicy`
+    pub conflicts: Vec<(String, Range<SemanticVersion>)>,
+    /// The ranges which packages must fall within, should they be in the closure, for the target selected by the `TargetPolicy`
+    pub weak_requires: Vec<(String, Range<SemanticVersion>)>,
+    /// The variants of the distribution, in order of preference
+    pub variants: Vec<ProvidedVariant>,
+}
+
+impl ProvidedDistribution {
+    /// Retrieve the constraints which the solver must satisfy should this distribution be selected,
+    /// merging requirements, weak requirements and conflicts on the same package
+    pub fn dependencies(&self) -> DependencyConstraints<String, SemanticVersion> {
+        let mut dependencies = DependencyConstraints::default();
+        for (name, range) in &self.requires {
+            constrain(&mut dependencies, name, required(range));
+        }
+        for (name, range) in &self.conflicts {
+            constrain(&mut dependencies, name, required(range).negate());
+        }
+        for (name, range) in &self.weak_requires {
+            constrain(&mut dependencies, name, required(range).union(&Range::exact(absent_version())));
+        }
+        dependencies
+    }
+}
+
+/// A constraint upon another package, declared by distributions of a package. This is used to explain failed solves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeclaredConstraint {
+    /// The kind of constraint
+    pub relation: Relation,
+    /// The range of the other package named by the constraint
+    pub range: Range<SemanticVersion>,
+    /// The versions of the declaring package which declare the constraint, along with their manifests
+    pub distributions: Vec<(SemanticVersion, PathBuf)>,
+}
+
+/// A variant of a distribution as seen by the solver
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProvidedVariant {
+    /// Name of the variant
+    pub name: String,
+    /// The requirements of the variant
+    pub requires: Vec<(String, Range<SemanticVersion>)>,
+}
+
+/// Retrieve the version used to represent a package which is absent from the dependency closure. The version
+/// is reserved; distributions may not use it.
+pub fn absent_version() -> SemanticVersion {
+    SemanticVersion::lowest()
+}
+
+// restrict the range such that the package must be present in the closure
+fn required(range: &Range<SemanticVersion>) -> Range<SemanticVersion> {
+    range.intersection(&Range::exact(absent_version()).negate())
+}
+
+// intersect the range with any existing constraint on the package
+fn constrain(dependencies: &mut DependencyConstraints<String, SemanticVersion>, package: &str, range: Range<SemanticVersion>) {
+    match dependencies.get_mut(package) {
+        Some(existing) => *existing = existing.intersection(&range),
+        None => {
+            dependencies.insert(package.to_string(), range);
+        }
+    }
+}
+
+/// Retrieve the name of the synthetic package used to select a variant of the supplied distribution
+pub fn variant_package(package: &str, version: &SemanticVersion) -> String {
+    format!("{}-{}{}", package, version, VARIANT_PACKAGE_SUFFIX)
+}
+
+/// Given the name of a synthetic variant package, retrieve the package and version of the distribution 
+/// it belongs to. Returns None if `name` is not a variant package.
+pub fn parse_variant_package(name: &str) -> Option<(&str, SemanticVersion)> {
+    let distribution = name.strip_suffix(VARIANT_PACKAGE_SUFFIX)?;
+    parse_consuming_package_version(distribution).ok()
+}
+
+// convert a vector of DistributionRanges into (package, range) pairs
+fn to_constraints(ranges: Vec<DistributionRange>) -> Vec<(String, Range<SemanticVersion>)> {
+    ranges
+        .into_iter()
+        .map(|DistributionRange { name, range, .. }| (name.to_string(), range))
+        .collect()
+}
+
+/// Retrieve the version of the synthetic variant package which corresponds with the 
+/// variant at `index`
+pub fn variant_version(index: usize) -> SemanticVersion {
+    SemanticVersion::new(index as u32, 0, 0, ReleaseType::Release)
+}
+
+/// The distributions of a single package, gathered across all repositories. Should the same
+/// version appear in more than one repository, the last repository added wins.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PackageVersions {
+    distributions: BTreeMap<SemanticVersion, ProvidedDistribution>,
+}
+
+impl PackageVersions {
+    /// Retrieve an iterator over the versions of the package, in ascending order
+    pub fn versions(&self) -> impl DoubleEndedIterator<Item = &SemanticVersion> {
+        self.distributions.keys()
+    }
+
+    /// Retrieve the distribution for the supplied version, should it exist
+    pub fn distribution(&self, version: &SemanticVersion) -> Option<&ProvidedDistribution> {
+        self.distributions.get(version)
+    }
+}
+
+// a repository registered with the provider, along with the filtering to apply to it
+#[derive(Debug)]
+struct RepositoryEntry<R: Repository> {
+    repository: R,
+    index: RefCell<RepositoryIndex>,
+    cache_file: Option<PathBuf>,
+    min_release_type: ReleaseType,
+    distributions_override: Rc<Vec<(String, SemanticVersion)>>,
+}
+
+/// DependencyProvider which lists versions and reads manifests on demand
+#[derive(Debug)]
+pub struct RepositoryDependencyProvider<R: Repository> {
+    repositories: Vec<RepositoryEntry<R>>,
+    /// directory in which repository indices are cached. If None, indices are not persisted.
+    cache_dir: Option<PathBuf>,
+    /// determines the target used for each transitive dependency
+    target_policy: TargetPolicy,
+    /// the target requested for the root of the solve
+    root_target: String,
+    /// packages visited thus far, keyed by name
+    packages: RefCell<IndexMap<String, Rc<PackageVersions>>>,
+    /// the version of each package to try first, should it be allowed
+    preferred_versions: HashMap<String, SemanticVersion>,
+    /// the variant of each package to try first, should it be allowed
+    preferred_variants: VariantMap,
+}
+
+impl<R: Repository> Default for RepositoryDependencyProvider<R> {
+    fn default() -> Self {
+        Self {
+            repositories: Vec::new(),
+            cache_dir: None,
+            target_policy: TargetPolicy::default(),
+            root_target: DEFAULT_TARGET.to_string(),
+            packages: RefCell::new(IndexMap::new()),
+            preferred_versions: HashMap::new(),
+            preferred_variants: VariantMap::new(),
+        }
+    }
+}
+
+impl<R: Repository> RepositoryDependencyProvider<R> {
+    /// Construct a new provider without any repositories
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the directory in which repository indices are cached. Passing None disables the cache.
+    /// This applies to repositories added subsequently.
+    pub fn set_cache_dir(&mut self, cache_dir: Option<PathBuf>) {
+        self.cache_dir = cache_dir;
+    }
+
+    /// Set the policy used to determine which target of each dependency is consulted, given the
+    /// target requested for the root of the solve.
+    pub fn set_target_policy<T: Into<String>>(&mut self, target_policy: TargetPolicy, root_target: T) {
+        self.target_policy = target_policy;
+        self.root_target = root_target.into();
+        // requires depend upon the policy, so we must start over
+        self.packages.borrow_mut().clear();
+    }
+
+    /// Set the versions, and variants, of packages to try before any others. Preferences are soft; a package
+    /// whose preferred version is not allowed by the solve falls back to the newest version allowed.
+    pub fn set_preferences(&mut self, versions: HashMap<String, SemanticVersion>, variants: VariantMap) {
+        self.preferred_versions = versions;
+        self.preferred_variants = variants;
+    }
+
+    // retrieve the preferred version of the package, should there be one. For a synthetic variant package, this
+    // is the version corresponding to the preferred variant of the distribution.
+    fn preferred_version(&self, package: &str, available: &PackageVersions) -> Option<SemanticVersion> {
+        match parse_variant_package(package) {
+            Some((name, _)) => {
+                let variant = self.preferred_variants.get(name)?;
+                available
+                    .versions()
+                    .find(|version| self.variant_name(package, version).as_ref() == Some(variant))
+                    .cloned()
+            }
+            None => self.preferred_versions.get(package).filter(|version| available.distribution(version).is_some()).cloned(),
+        }
+    }
+
+    /// Register a repository with the provider. Only distributions whose release type is at least
+    /// `min_release_type`, or which appear in `distributions_override`, will be offered to the solver.
+    /// The repository is not read until the solver asks for one of its packages.
+    pub fn add_repository(
+        &mut self,
+        repository: R,
+        min_release_type: ReleaseType,
+        distributions_override: Rc<Vec<(String, SemanticVersion)>>,
+    ) {
+        let cache_file = self.cache_dir.as_ref().map(|dir| cache_file_for(dir, repository.root()));
+        let index = match cache_file {
+            Some(ref cache_file) => RepositoryIndex::load(repository.root(), cache_file),
+            None => RepositoryIndex::new(repository.root()),
+        };
+        self.repositories.push(RepositoryEntry {
+            repository,
+            index: RefCell::new(index),
+            cache_file,
+            min_release_type,
+            distributions_override,
+        });
+        // distributions from the new repository may shadow those we have already seen
+        self.packages.borrow_mut().clear();
+    }
+
+    /// Register the root request with the provider. The request is modeled as a distribution of a
+    /// synthetic package named `ROOT_REQUEST`, whose requirements, weak requirements and conflicts are those of the request.
+    pub fn set_request(&mut self, request: SolveRequest) {
+        let SolveRequest { requires, conflicts, weak_requires } = request;
+        let mut root = PackageVersions::default();
+        root.distributions.insert(
+            Self::root_version(),
+            ProvidedDistribution {
+                path: PathBuf::new(),
+                manifest: PathBuf::new(),
+                requires: to_constraints(requires),
+                conflicts: to_constraints(conflicts),
+                weak_requires: to_constraints(weak_requires),
+                variants: Vec::new(),
+            },
+        );
+        self.packages.borrow_mut().insert(ROOT_REQUEST.to_string(), Rc::new(root));
+    }
+
+    /// The version of the synthetic root request package
+    pub fn root_version() -> SemanticVersion {
+        SemanticVersion::new(1, 0, 0, ReleaseType::Release)
+    }
+
+    /// Retrieve the distributions of the supplied package, reading them from the repositories
+    /// should this be the first time that the package has been requested.
+    pub fn package(&self, package: &str) -> Result<Rc<PackageVersions>, PesError> {
+        if let Some(versions) = self.packages.borrow().get(package) {
+            return Ok(Rc::clone(versions));
+        }
+        debug!("retrieving versions of {}", package);
+        let mut versions = PackageVersions::default();
+        if let Some((base, version)) = parse_variant_package(package) {
+            if let Some(dist) = self.package(base)?.distribution(&version) {
+                for (idx, variant) in dist.variants.iter().enumerate() {
+                    versions.distributions.insert(
+                        variant_version(idx),
+                        ProvidedDistribution {
+                            path: dist.path.join(&variant.name),
+                            manifest: dist.manifest.clone(),
+                            requires: variant.requires.clone(),
+                            conflicts: Vec::new(),
+                            weak_requires: Vec::new(),
+                            variants: Vec::new(),
+                        },
+                    );
+                }
+            }
+            let versions = Rc::new(versions);
+            self.packages.borrow_mut().insert(package.to_string(), Rc::clone(&versions));
+            return Ok(versions);
+        }
+        for entry in &self.repositories {
+            let mut index = entry.index.borrow_mut();
+            let package_index = match index.package(&entry.repository, package)? {
+                Some(package_index) => package_index,
+                None => continue,
+            };
+            for dist in package_index.distributions().filter(|dist| {
+                dist.version.release_type >= entry.min_release_type
+                    || entry
+                        .distributions_override
+                        .iter()
+                        .any(|(name, version)| name == package && version == &dist.version)
+            }) {
+                // a manifest whose ranges or includes are invalid fails the solve, rather than being 
+                // treated as though it had no dependencies
+                let mut requires = to_constraints(
+                    dist.manifest.get_transitive_requires(&self.target_policy, &self.root_target)?,
+                );
+                let conflicts = to_constraints(
+                    dist.manifest.get_transitive_conflicts(&self.target_policy, &self.root_target)?,
+                );
+                let weak_requires = to_constraints(
+                    dist.manifest.get_transitive_weak_requires(&self.target_policy, &self.root_target)?,
+                );
+                let variants = dist
+                    .manifest
+                    .variants
+                    .iter()
+                    .map(|variant| {
+                        Ok(ProvidedVariant { name: variant.name.clone(), requires: to_constraints(variant.get_all_requires()?) })
+                    })
+                    .collect::<Result<Vec<_>, PesError>>()?;
+                if !variants.is_empty() {
+                    requires.push((variant_package(package, &dist.version), Range::any()));
+                }
+                versions
+                    .distributions
+                    .insert(dist.version.clone(), ProvidedDistribution {
+                        path: dist.path.clone(),
+                        manifest: dist.manifest_path.clone(),
+                        requires,
+                        conflicts,
+                        weak_requires,
+                        variants,
+                    });
+            }
+        }
+        let versions = Rc::new(versions);
+        self.packages.borrow_mut().insert(package.to_string(), Rc::clone(&versions));
+        Ok(versions)
+    }
+
+    /// Retrieve the names of the packages which have been visited thus far
+    pub fn packages(&self) -> Vec<String> {
+        self.packages
+            .borrow()
+            .keys()
+            .filter(|name| name.as_str() != ROOT_REQUEST)
+            .cloned()
+            .collect()
+    }
+
+    /// Retrieve the path to the supplied distribution, should it exist
+    pub fn dist_path(&self, package: &str, version: &SemanticVersion) -> Option<PathBuf> {
+        self.package(package)
+            .ok()?
+            .distribution(version)
+            .map(|dist| dist.path.clone())
+    }
+
+    /// Retrieve the name of the variant selected by choosing `version` of the synthetic `variant_package`, should it exist
+    pub fn variant_name(&self, variant_package: &str, version: &SemanticVersion) -> Option<String> {
+        let (package, dist_version) = parse_variant_package(variant_package)?;
+        let versions = self.package(package).ok()?;
+        let variant = versions
+            .distribution(&dist_version)?
+            .variants
+            .get(version.major as usize)?;
+        Some(variant.name.clone())
+    }
+
+    /// Retrieve the constraint upon `other` which distributions of `package` within `range` declare, should 
+    /// any of them do so. Should the distributions disagree, the constraint declared by the lowest version wins. 
+    /// This is used to explain failed solves.
+    pub fn declared_constraint(&self, package: &str, range: &Range<SemanticVersion>, other: &str) -> Option<DeclaredConstraint> {
+        let versions = self.package(package).ok()?;
+        let mut declared: Option<DeclaredConstraint> = None;
+        for (version, dist) in versions.distributions.iter().filter(|(version, _)| range.contains(version)) {
+            let constraint = [
+                (Relation::Requires, &dist.requires),
+                (Relation::WeakRequires, &dist.weak_requires),
+                (Relation::Conflicts, &dist.conflicts),
+            ]
+            .iter()
+            .find_map(|(relation, constraints)| {
+                constraints.iter().find(|(name, _)| name == other).map(|(_, range)| (*relation, range))
+            });
+            match (constraint, declared.as_mut()) {
+                (Some((relation, other_range)), Some(declared)) => {
+                    if declared.relation == relation && &declared.range == other_range {
+                        declared.distributions.push((version.clone(), dist.manifest.clone()));
+                    }
+                }
+                (Some((relation, other_range)), None) => {
+                    declared = Some(DeclaredConstraint {
+                        relation,
+                        range: other_range.clone(),
+                        distributions: vec![(version.clone(), dist.manifest.clone())],
+                    });
+                }
+                (None, _) => (),
+            }
+        }
+        declared
+    }
+
+    /// Write any repository indices which have changed to the cache directory. Failing to write
+    /// an index is not fatal, as it will simply be rebuilt next time.
+    pub fn save(&self) {
+        for entry in &self.repositories {
+            if let Some(cache_file) = entry.cache_file.as_ref() {
+                if let Err(e) = entry.index.borrow_mut().save(cache_file) {
+                    warn!("unable to write repository index {:?}: {}", cache_file, e);
+                }
+            }
+        }
+    }
+}
+
+impl<R: Repository> DependencyProvider<String, SemanticVersion> for RepositoryDependencyProvider<R> {
+    fn choose_package_version<T: Borrow<String>, U: Borrow<Range<SemanticVersion>>>(
+        &self,
+        potential_packages: impl Iterator<Item = (T, U)>,
+    ) -> Result<(T, Option<SemanticVersion>), Box<dyn Error>> {
+        // choose_package_with_fewest_versions cannot propagate errors, so we hold on to the
+        // first one encountered and report it once it returns
+        let failure = RefCell::new(None);
+        let list_available_versions = |package: &String| {
+            // we prefer that a package be absent whenever that is allowed
+            let mut versions = vec![absent_version()];
+            match self.package(package) {
+                Ok(available) => {
+                    // followed by the preferred version, should there be one
+                    let preferred = self.preferred_version(package, &available);
+                    versions.extend(preferred.clone());
+                    // variants are listed in order of preference, whereas we otherwise prefer the newest version
+                    let remaining = available.versions().filter(|version| Some(*version) != preferred.as_ref()).cloned();
+                    if parse_variant_package(package).is_some() {
+                        versions.extend(remaining);
+                    } else {
+                        versions.extend(remaining.rev());
+                    }
+                }
+                Err(e) => {
+                    failure.borrow_mut().get_or_insert(e);
+                }
+            };
+            versions.into_iter()
+        };
+        let choice = choose_package_with_fewest_versions(list_available_versions, potential_packages);
+        match failure.into_inner() {
+            Some(e) => Err(Box::new(e)),
+            None => Ok(choice),
+        }
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &String,
+        version: &SemanticVersion,
+    ) -> Result<Dependencies<String, SemanticVersion>, Box<dyn Error>> {
+        if version == &absent_version() {
+            return Ok(Dependencies::Known(DependencyConstraints::default()));
+        }
+        let versions = self.package(package)?;
+        Ok(match versions.distribution(version) {
+            Some(dist) => Dependencies::Known(dist.dependencies()),
+            None => Dependencies::Unknown,
+        })
+    }
+}
+
+#[cfg(test)]
+#[path = "./unit_tests/dependency_provider.rs"]
+mod unit_tests;
@@ -2,8 +2,10 @@
 //! - PathToken: enum modeling the tokens one may decompose a env var path string into
 //! - PathMode: enum describing the way that a path or paths are composed - eg by prepending, appending or replacing an existing variable
 //! - BasicVarProvider: struct used to store and provide path variables to a parser. This implements the `VarProvider` trait found in the `traits` module
+//!   It also provides the environment variables referenced by an expression (eg `$HOME`)
+//! - composition_order: function determining the order in which the environments of the packages in a solve are applied
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::collections::VecDeque;
 use std::path::{
     Path,
@@ -11,6 +13,7 @@ use std::path::{
 
 use crate::{
     PesError,
+    SemanticVersion,
     VarProvider,
 };
 
@@ -26,6 +29,8 @@ pub enum PathToken<'a> {
     RootVar,
     /// Variable, rendered as ```{<VAR NAME>}```
     Variable(&'a str),
+    /// Reference to an environment variable, rendered as ```$<VAR NAME>``` or ```${<VAR NAME>}```
+    EnvVariable(&'a str),
     /// OwnedVariable, making lifetime gymnastics simpler at the cost of an allocation.
     OwnedVariable(String),
     /// Error state. unknown variable
@@ -76,14 +81,25 @@ impl<'a> PathToken<'a> {
 }
 
 
-/// An environment variable setting may be tranformed into a Mode 
+/// The separator joining the entries of path lists (eg `PATH`), unless another is specified
+pub const PATH_SEPARATOR: &str = ":";
+
+/// An environment variable setting may be tranformed into a Mode
 /// wrapping a vector of PathBuf or PathBuf
 ///
 #[derive(Debug, PartialEq, Eq)]
 pub enum PathMode {
     Append(VecDeque<String>),
     Prepend(VecDeque<String>),
-    Exact(VecDeque<String>)
+    Exact(VecDeque<String>),
+    /// Set the variable to a value which is not treated as a list (eg a url, or flags separated by spaces)
+    Set(String),
+    /// Set the variable to the value, should it be unset (or empty)
+    Default(String),
+    /// Remove the variable from the environment
+    Unset,
+    /// Join the entries of the wrapped mode with a separator other than `:` (eg `;` or a space)
+    Separated(Box<PathMode>, String),
 }
 
 impl PathMode {
@@ -92,6 +108,60 @@ impl PathMode {
             Self::Append(me) | Self::Prepend(me) | Self::Exact(me) => {
                 me
             }
+            Self::Set(value) | Self::Default(value) => VecDeque::from(vec![value]),
+            Self::Unset => VecDeque::new(),
+            Self::Separated(mode, _) => mode.inner(),
+        }
+    }
+
+    /// Retrieve the separator joining the entries of the mode
+    pub fn separator(&self) -> &str {
+        match self {
+            Self::Separated(_, separator) => separator.as_str(),
+            _ => PATH_SEPARATOR,
+        }
+    }
+
+    /// Compose the mode with the existing value of a variable, returning the new value, or None should
+    /// the mode unset the variable. An empty existing value is treated as unset.
+    pub fn apply(&self, existing: Option<&str>) -> Option<String> {
+        let existing = existing.filter(|value| !value.is_empty());
+        self.apply_with(existing, PATH_SEPARATOR)
+    }
+
+    /// Apply the mode to the variable named `key` within the environment, removing the variable should
+    /// the mode unset it
+    pub fn apply_to(&self, env: &mut HashMap<String, String>, key: &str) {
+        match self.apply(env.get(key).map(String::as_str)) {
+            Some(value) => env.insert(key.to_string(), value),
+            None => env.remove(key),
+        };
+    }
+
+    fn apply_with(&self, existing: Option<&str>, separator: &str) -> Option<String> {
+        let join = |entries: &VecDeque<String>| entries.iter().map(String::as_str).collect::<Vec<_>>().join(separator);
+        match (self, existing) {
+            (Self::Prepend(entries), Some(existing)) => Some(format!("{}{}{}", join(entries), separator, existing)),
+            (Self::Append(entries), Some(existing)) => Some(format!("{}{}{}", existing, separator, join(entries))),
+            (Self::Prepend(entries), None) | (Self::Append(entries), None) | (Self::Exact(entries), _) => Some(join(entries)),
+            (Self::Set(value), _) | (Self::Default(value), None) => Some(value.clone()),
+            (Self::Default(_), Some(existing)) => Some(existing.to_string()),
+            (Self::Unset, _) => None,
+            (Self::Separated(mode, separator), _) => mode.apply_with(existing, separator),
+        }
+    }
+
+    // the mode which sets a variable to the value, or unsets it, retaining the separator
+    fn from_value(value: Option<String>, separator: &str) -> Self {
+        let value = match value {
+            Some(value) => value,
+            None => return Self::Unset,
+        };
+        let entries = value.split(separator).map(|x| x.to_string()).collect::<VecDeque<_>>();
+        if separator == PATH_SEPARATOR {
+            Self::Exact(entries)
+        } else {
+            Self::Separated(Box::new(Self::Exact(entries)), separator.to_string())
         }
     }
 }
@@ -99,67 +169,100 @@ use std::ops::{Add, AddAssign};
 impl Add for PathMode {
     type Output = Self;
 
-    fn add(self, other: Self) -> Self {
-        match self {
-            PathMode::Append(mut me) | PathMode::Prepend(mut me) | PathMode::Exact(mut me) =>  {
-                match other {
-                    PathMode::Prepend(you) => {
-                        for v in you {
-                            me.push_front(v);
-                        }
-                        PathMode::Prepend(me)
-                    },
-                    PathMode::Append(you) => {
-                        for v in you {
-                            me.push_back(v);
-                        }
-                        PathMode::Append(me)
-                    },
-                    PathMode::Exact(you) => PathMode::Exact(you)
-                }
-            }
-        }
+    fn add(mut self, other: Self) -> Self {
+        self += other;
+        self
     }
 }
 
 impl AddAssign for PathMode {
 
-    fn add_assign(&mut self, mut other: Self)  {
-         match self {
-            PathMode::Append(ref mut me) | PathMode::Prepend(ref mut me) | PathMode::Exact(ref mut me) =>  {
-                match other {
-                    PathMode::Prepend(you) => {
-                        for v in you {
-                            me.push_front(v);
-                        }
-                        
-                    },
-                    PathMode::Append(you) => {
-                        for v in you {
-                            me.push_back(v);
-                        }
-                    },
-                    PathMode::Exact(ref mut you) => {
-                        me.clear();
-                        me.append(you);
-                    }
+    fn add_assign(&mut self, other: Self)  {
+        let existing = match self {
+            Self::Unset => None,
+            _ => Some(self.apply_with(None, self.separator()).unwrap_or_default()),
+        };
+        let value = other.apply(existing.as_deref());
+        *self = Self::from_value(value, other.separator());
+    }
+}
+
+/// Determine the order in which the environments of the packages in a solve are applied, given each package
+/// along with the packages it depends upon. Dependents precede their dependencies. Packages which are free to go
+/// next are taken in the order in which they were requested, followed by any others in name order, such that a
+/// given solve always yields the same order. Dependencies upon packages absent from `dependencies` are ignored.
+/// Should the dependencies be circular, the cycle is broken at the earliest package by the same ordering.
+///
+/// # Example
+/// ```
+/// # use std::collections::HashMap;
+/// # use peslib::env::composition_order;
+/// let mut dependencies = HashMap::new();
+/// dependencies.insert("maya", vec!["python"]);
+/// dependencies.insert("python", vec![]);
+/// dependencies.insert("houdini", vec!["python"]);
+/// assert_eq!(composition_order(&dependencies, &["maya", "houdini"]), vec!["maya", "houdini", "python"]);
+/// ```
+pub fn composition_order<'a, S: AsRef<str>>(dependencies: &HashMap<&'a str, Vec<&'a str>>, requested: &[S]) -> Vec<&'a str> {
+    let rank = |package: &'a str| {
+        let position = requested.iter().position(|r| r.as_ref() == package).unwrap_or(requested.len());
+        (position, package)
+    };
+    // the number of dependents of each package which have yet to be applied
+    let mut dependents = dependencies.keys().map(|package| (*package, 0usize)).collect::<HashMap<_, _>>();
+    for package in dependencies.values().flatten() {
+        if let Some(count) = dependents.get_mut(package) {
+            *count += 1;
+        }
+    }
+    let mut ready = dependents
+        .iter()
+        .filter(|(_, count)| **count == 0)
+        .map(|(package, _)| rank(package))
+        .collect::<BTreeSet<_>>();
+    let mut order = Vec::with_capacity(dependencies.len());
+    while !dependents.is_empty() {
+        // should every remaining package have a dependent, we have a cycle
+        let next = match ready.iter().next().cloned() {
+            Some(next) => next,
+            None => dependents.keys().map(|package| rank(package)).min().expect("dependents is not empty"),
+        };
+        ready.remove(&next);
+        let package = next.1;
+        dependents.remove(package);
+        order.push(package);
+        for dependency in &dependencies[package] {
+            if let Some(count) = dependents.get_mut(dependency) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    ready.insert(rank(dependency));
                 }
             }
         }
     }
+    order
 }
 
+/// The variables describing the package whose environment is being composed
+pub const PACKAGE_VARIABLES: &[&str] = &["root", "name", "version", "major", "minor", "patch"];
+
+/// The suffix of the variables providing the roots of the other packages in the environment (eg `{maya.root}`)
+pub const PACKAGE_ROOT_SUFFIX: &str = ".root";
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 /// Provides variables to the parser. It is up to the user
 /// to insert variables prior to passing to the parser.
 pub struct BasicVarProvider {
-    inner: HashMap<String, String>
+    inner: HashMap<String, String>,
+    /// environment variables, referenced as `$NAME` or `${NAME}`
+    environment: HashMap<String, String>,
 }
 
 impl Default for BasicVarProvider {
     fn default() -> Self {
         Self {
-            inner: HashMap::new()
+            inner: HashMap::new(),
+            environment: HashMap::new(),
         }
     }
 }
@@ -183,6 +286,41 @@ impl BasicVarProvider {
     pub fn insert_var(&mut self, key: impl Into<String>, value: impl Into<String>)  {
         self.insert(key, value);
     }
+
+    /// Insert the variables describing the package whose environment is being composed. See `PACKAGE_VARIABLES`.
+    pub fn insert_package_vars(&mut self, name: &str, version: &SemanticVersion, root: &Path) {
+        self.insert("root", root.display().to_string());
+        self.insert("name", name);
+        self.insert("version", version.to_string());
+        self.insert("major", version.major.to_string());
+        self.insert("minor", version.minor.to_string());
+        self.insert("patch", version.patch.to_string());
+    }
+
+    /// Insert the root of a package in the environment, which is referenced as `{<name>.root}`
+    pub fn insert_package_root(&mut self, name: &str, root: &Path) {
+        self.insert(format!("{}{}", name, PACKAGE_ROOT_SUFFIX), root.display().to_string());
+    }
+
+    /// Replace the environment variables available to the parser
+    pub fn set_environment(&mut self, environment: HashMap<String, String>) {
+        self.environment = environment;
+    }
+
+    /// Retrieve the value of an environment variable, should it be set
+    pub fn env(&self, name: &str) -> Option<&str> {
+        self.environment.get(name).map(String::as_str)
+    }
+
+    /// Apply the mode to the named environment variable, updating the value available to the parser in place
+    pub fn apply_to_env(&mut self, mode: &PathMode, name: &str) {
+        mode.apply_to(&mut self.environment, name);
+    }
+
+    /// Remove the environment variables from the provider, returning them
+    pub fn take_environment(&mut self) -> HashMap<String, String> {
+        std::mem::take(&mut self.environment)
+    }
 }
 
 impl<'a> VarProvider<'a> for BasicVarProvider {
@@ -0,0 +1,234 @@
+//! Build the environment of a solve, without launching anything within it
+//!
+//! - EnvPackage: a package whose environment is applied, along with its root and selected variant
+//! - EnvBuilder: struct which composes the environments of a set of packages atop a base environment,
+//!   returning the resulting variables. This is what `pes shell` and `pes-run` exec into.
+//!
+//! # Example
+//! ```no_run
+//! # use peslib::{EnvBuilder, LockFile, PackageRepository, PluginMgr, PesError};
+//! # fn main() -> Result<(), PesError> {
+//! let plugin_mgr = PluginMgr::new()?;
+//! let repos = PackageRepository::from_plugin(&plugin_mgr)?;
+//! let lockfile = LockFile::from_file("foo.lock")?;
+//! let env = EnvBuilder::from_plugin(&plugin_mgr)?
+//!     .with_target("build")
+//!     .build_for_lockfile(&repos, &lockfile)?;
+//! # Ok(())
+//! # }
+//! ```
+use log::debug;
+use std::{cell::RefCell, collections::HashMap, path::PathBuf, rc::Rc};
+
+use crate::{
+    constants::{DEFAULT_TARGET, ROOT_REQUEST},
+    env::composition_order,
+    parser::parse_consuming_all_paths_with_provider,
+    traits::Repository,
+    BasicVarProvider, LockFile, Manifest, PackageRepository, PesError, PluginMgr, SelectedDependencies,
+    SemanticVersion, VariantMap,
+};
+
+/// A package whose environment is applied, along with the root its environment is evaluated against (the
+/// root of the distribution, or that of the selected variant) and the selected variant, if any
+#[derive(Debug, PartialEq, Eq)]
+pub struct EnvPackage {
+    pub root: PathBuf,
+    pub manifest: Manifest,
+    pub variant: Option<String>,
+}
+
+impl EnvPackage {
+    /// Construct a new EnvPackage
+    pub fn new<P: Into<PathBuf>>(root: P, manifest: Manifest, variant: Option<String>) -> Self {
+        Self { root: root.into(), manifest, variant }
+    }
+
+    /// Look up the manifest of each package in a solution within the supplied repositories, returning
+    /// an error listing every distribution whose manifest cannot be found
+    pub fn from_solution(
+        repos: &[PackageRepository],
+        solution: &SelectedDependencies<String, SemanticVersion>,
+        variants: &VariantMap,
+    ) -> Result<Vec<Self>, PesError> {
+        Self::collect(solution, variants, |package, version| search_repos(repos, package, version))
+    }
+
+    /// Retrieve the packages locked for the target. Packages are read from where the lockfile recorded
+    /// finding them, falling back to searching the supplied repositories should that no longer exist (or
+    /// the lockfile predate recording it).
+    pub fn from_lockfile(repos: &[PackageRepository], lockfile: &LockFile, target: &str) -> Result<Vec<Self>, PesError> {
+        let solution = lockfile.selected_dependencies_for(target)?;
+        let variants = lockfile.variants_for(target);
+        Self::collect(&solution, &variants, |package, version| {
+            lockfile
+                .distribution(package, version)
+                .map(|locked| locked.manifest.clone())
+                .filter(|manifest| manifest.is_file())
+                .or_else(|| search_repos(repos, package, version))
+        })
+    }
+
+    // construct the packages of a solution, given a means of finding the manifest of a package version
+    fn collect<F>(
+        solution: &SelectedDependencies<String, SemanticVersion>,
+        variants: &VariantMap,
+        find_manifest: F,
+    ) -> Result<Vec<Self>, PesError>
+    where
+        F: Fn(&str, &SemanticVersion) -> Option<PathBuf>,
+    {
+        let mut packages = Vec::new();
+        // define a var to hold a list of distributions for which we cannot find manifests
+        let mut missing_manifests = Vec::new();
+        // solution is a HashMap of (package,version) pairs
+        for (package, version) in solution.iter() {
+            let distribution = format!("{}-{}", package, version);
+            match find_manifest(package, version) {
+                Some(path) => {
+                    let manifest = Manifest::from_path(&path)?;
+                    let mut root = manifest.package_root().to_path_buf();
+                    // if a variant was selected, its root is nested within the distribution
+                    let variant = variants.get(package).cloned();
+                    if let Some(ref variant) = variant {
+                        let variant = manifest
+                            .variant(variant)
+                            .ok_or_else(|| PesError::InvalidVariant(format!("{}[{}]", distribution, variant)))?;
+                        root = variant.root(root);
+                    }
+                    packages.push(Self::new(root, manifest, variant));
+                }
+                // if we were unable to find the manifest, add it to the list of missing manifests
+                None if package.as_str() != ROOT_REQUEST => missing_manifests.push(distribution),
+                None => (),
+            }
+        }
+        if missing_manifests.is_empty() {
+            Ok(packages)
+        } else {
+            Err(PesError::MissingManifests(missing_manifests))
+        }
+    }
+}
+
+// search through repositories for the manifest of the package version. Should more than one repository
+// provide it, the last wins.
+fn search_repos(repos: &[PackageRepository], package: &str, version: &SemanticVersion) -> Option<PathBuf> {
+    let version = version.to_string();
+    repos.iter().rev().find_map(|repo| repo.manifest(package, &version).ok())
+}
+
+/// Composes the environments of the packages in a solve, for a target, atop a base environment. The
+/// environments are applied in composition order (see `env::composition_order`), such that the same
+/// solve always yields the same environment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvBuilder {
+    base_env: HashMap<String, String>,
+    target: String,
+    requested: Vec<String>,
+}
+
+impl EnvBuilder {
+    /// Construct a builder which composes environments atop the supplied base environment, for the
+    /// `run` target
+    pub fn new(base_env: HashMap<String, String>) -> Self {
+        Self { base_env, target: DEFAULT_TARGET.to_string(), requested: Vec::new() }
+    }
+
+    /// Construct a builder whose base environment is provided by the base env plugin
+    pub fn from_plugin(plugin_mgr: &PluginMgr) -> Result<Self, PesError> {
+        Ok(Self::new(plugin_mgr.base_env()?))
+    }
+
+    /// Set the target whose environment is built for each package
+    pub fn with_target<T: Into<String>>(mut self, target: T) -> Self {
+        self.target = target.into();
+        self
+    }
+
+    /// Set the names of the packages requested, in the order requested. This breaks ties in the
+    /// composition order.
+    pub fn with_requested<I, S>(mut self, requested: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.requested = requested.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Retrieve the target whose environment is built
+    pub fn target(&self) -> &str {
+        self.target.as_str()
+    }
+
+    /// Compose the environments of the supplied packages, returning the resulting variables
+    pub fn build(&self, packages: &[EnvPackage]) -> Result<HashMap<String, String>, PesError> {
+        // the environment is composed within the provider, as that composed thus far may be referenced (eg $HOME)
+        let provider = Rc::new(RefCell::new(BasicVarProvider::new()));
+        provider.borrow_mut().set_environment(self.base_env.clone());
+        let packages = packages
+            .iter()
+            .map(|package| (package.manifest.name(), package))
+            .collect::<HashMap<_, _>>();
+
+        // the roots of every package in the solve may be referenced (eg {maya.root})
+        for package in packages.values() {
+            provider.borrow_mut().insert_package_root(package.manifest.name(), &package.root);
+        }
+
+        // packages are applied in composition order, dependents before their dependencies
+        let dependencies = packages
+            .iter()
+            .map(|(name, package)| Ok((*name, package.manifest.environment_dependencies_for(&self.target, package.variant.as_deref())?)))
+            .collect::<Result<HashMap<_, _>, PesError>>()?;
+        let order = composition_order(&dependencies, &self.requested);
+        debug!("composition order: {:?}", &order);
+
+        for name in order {
+            let EnvPackage { root, manifest, variant } = packages[name];
+            provider.borrow_mut().insert_package_vars(manifest.name(), manifest.version(), root);
+            // the target's environment is applied after the package-wide environment, and the variant's after both
+            let variant_env = variant
+                .as_ref()
+                .and_then(|v| manifest.variant(v))
+                .map(|v| v.environment.iter().collect::<Vec<_>>())
+                .unwrap_or_default();
+            for (key, value) in manifest.environment_for(&self.target)?.into_iter().chain(variant_env) {
+                debug!("{} {}", &key, value);
+                let result = parse_consuming_all_paths_with_provider(Rc::clone(&provider), value)?;
+                debug!("{:?}", result);
+                provider.borrow_mut().apply_to_env(&result, key);
+            }
+        }
+        let env_vars = provider.borrow_mut().take_environment();
+        Ok(env_vars)
+    }
+
+    /// Compose the environment of a solution, looking up the manifests of its packages within the
+    /// supplied repositories
+    pub fn build_for_solution(
+        &self,
+        repos: &[PackageRepository],
+        solution: &SelectedDependencies<String, SemanticVersion>,
+        variants: &VariantMap,
+    ) -> Result<HashMap<String, String>, PesError> {
+        self.build(&EnvPackage::from_solution(repos, solution, variants)?)
+    }
+
+    /// Compose the environment of the builder's target within a lockfile, reading each distribution from
+    /// where the lockfile recorded finding it (see `EnvPackage::from_lockfile`). Unless set via `with_requested`,
+    /// the packages are taken to have been requested in the order in which they are locked.
+    pub fn build_for_lockfile(&self, repos: &[PackageRepository], lockfile: &LockFile) -> Result<HashMap<String, String>, PesError> {
+        let packages = EnvPackage::from_lockfile(repos, lockfile, &self.target)?;
+        if self.requested.is_empty() {
+            self.clone().with_requested(lockfile.packages_for(&self.target)).build(&packages)
+        } else {
+            self.build(&packages)
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./unit_tests/env_builder.rs"]
+mod unit_tests;
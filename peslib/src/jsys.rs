@@ -9,13 +9,16 @@ use crate::{
     BaseEnv,
     PesError,
     ManifestLocationProvider,
-    constants::MANIFEST_NAME,
+    constants::{MANIFEST_NAME, MANIFEST_NAMES},
     Manifest,
     env::PathMode,
 };
+use pes_core::base_env::DEFAULT_WHITELIST;
 
 #[derive(Debug)]
-/// Provides a base environment for the Jsys system
+/// Provides a base environment for the Jsys system. pes shells now take their base environment
+/// from the base env plugin (see `PluginMgr::base_env`), whose default policy whitelists the same
+/// variables (see `pes_core::base_env::DEFAULT_WHITELIST`).
 pub struct JsysCleanEnv {
     vars: &'static[&'static str]
 }
@@ -23,74 +26,7 @@ pub struct JsysCleanEnv {
 impl Default for JsysCleanEnv {
     fn default() -> Self {
         Self {
-            vars: &[
-                "JSYS_PROJECT", 
-                "JSYS_SEQUENCE", 
-                "JSYS_SHOT", 
-                "JSYS_LEVEL", 
-                "JSYS_ROOT",
-                "_",
-                "COLORTERM",
-                "DBUS_SESSION_BUS_ADDRESS",
-                "DEFAULTS_PATH",
-                "DESKTOP_SESSION",
-                "DISPLAY",
-                "GDMSESSION",
-                "G_ENABLE_DIAGNOSTIC",
-                "GNOME_DESKTOP_SESSION_ID",
-                "GNOME_SHELL_SESSION_MODE",
-                "GNOME_TERMINAL_SCREEN",
-                "GNOME_TERMINAL_SERVICE",
-                "GPG_AGENT_INFO",
-                "GTK_IM_MODULE",
-                "GTK_MODULES",
-                "HOME",
-                "INVOCATION_ID",
-                "JOURNAL_STREAM",
-                "LANG",
-                "LANGUAGE",
-                "LC_ADDRESS",
-                "LC_IDENTIFICATION",
-                "LC_MEASUREMENT",
-                "LC_MONETARY",
-                "LC_NAME",
-                "LC_NUMERIC",
-                "LC_PAPER",
-                "LC_TELEPHONE",
-                "LC_TIME",
-                "LESSCLOSE",
-                "LESSOPEN",
-                "LOGNAME",
-                "LS_COLORS",
-                "MANAGERPID",
-                "MANDATORY_PATH",
-                "PAPERSIZE",
-                "PATH",
-                "PWD",
-                "QT_ACCESSIBILITY",
-                "QT_IM_MODULE",
-                "SESSION_MANAGER",
-                "SHELL",
-                "SHLVL",
-                "SSH_AGENT_PID",
-                "SSH_AUTH_SOCK",
-                "TERM",
-                "USER",
-                "USERNAME",
-                "VTE_VERSION",
-                "WINDOWPATH",
-                "XAUTHORITY",
-                "XDG_CONFIG_DIRS",
-                "XDG_CURRENT_DESKTOP",
-                "XDG_DATA_DIRS",
-                "XDG_MENU_PREFIX",
-                "XDG_RUNTIME_DIR",
-                "XDG_SESSION_CLASS",
-                "XDG_SESSION_DESKTOP",
-                "XDG_SESSION_TYPE",
-                "XMODIFIERS"
-                
-            ]
+            vars: DEFAULT_WHITELIST,
         }
     }
 }
@@ -144,9 +80,12 @@ pub struct ManifestFactory;
 
 impl ManifestLocationProvider for ManifestFactory {
     fn find<P: Into<PathBuf>>(&self, distribution: P) -> PathBuf {
-        let mut path = distribution.into();
-        path.push(MANIFEST_NAME);
-        path
+        let distribution = distribution.into();
+        MANIFEST_NAMES
+            .iter()
+            .map(|name| distribution.join(name))
+            .find(|path| path.exists())
+            .unwrap_or_else(|| distribution.join(MANIFEST_NAME))
     }
 
     fn manifest<P: Into<PathBuf>>(&self, distribution: P) -> Result<Manifest, PesError> {
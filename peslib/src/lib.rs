@@ -23,9 +23,12 @@ extern crate generator;
 pub use pes_core::{PesError, PNResult, PesNomError, PNCompleteResult};
 
 pub mod aliases;
+pub mod audit;
 pub mod constants;
+pub mod dependency_provider;
 pub mod distribution;
 pub mod env;
+pub mod env_builder;
 pub mod jsys;
 pub mod lock;
 pub mod manifest;
@@ -34,23 +37,38 @@ pub mod parser;
 pub mod plugin_mgr;
 pub mod range;
 pub mod repository;
+pub mod repository_index;
 pub mod solver;
+pub mod target_policy;
 pub mod traits;
 pub mod utils;
 pub mod distribution_range;
 
 pub use pes_core::{SemanticVersion, ReleaseType, parser_atoms};
+pub use pes_core::solve_failure::{self, FailureCause, FailureTerm, Relation, SolveFailure};
 
 pub use aliases::*;
+pub use dependency_provider::RepositoryDependencyProvider;
 pub use distribution::Distribution;
 pub use env::BasicVarProvider;
+pub use env_builder::{EnvBuilder, EnvPackage};
 pub use lock::LockFile;
 pub use manifest::Manifest;
 pub use plugin_mgr::PluginMgr;
 pub use range::*;
 pub use repository::PackageRepository;
+pub use repository_index::RepositoryIndex;
 pub use solver::SelectedDependencies;
-pub use solver::{Solver, perform_solve, perform_solve_for_distribution_and_target};
+pub use solver::{
+    Solver, 
+    SolveRequest, 
+    perform_solve, 
+    perform_solve_for_target, 
+    perform_pinned_solve_for_target, 
+    perform_solve_for_distribution_and_target, 
+    perform_pinned_solve_for_distribution_and_target
+};
+pub use target_policy::{TargetPolicy, TransitiveTarget};
 pub use traits::{BaseEnv, ManifestLocationProvider, Repository, VarProvider};
 pub use distribution_range::DistributionRange;
 
@@ -61,6 +79,7 @@ pub mod prelude {
         BasicVarProvider, 
         Distribution, 
         DistributionRange, 
+        EnvBuilder, 
         LockFile, 
         Manifest,
         ManifestLocationProvider, 
@@ -71,8 +90,12 @@ pub mod prelude {
         Repository, 
         SemanticVersion, 
         Solver,
+        TargetPolicy,
         VarProvider, 
         perform_solve, 
-        perform_solve_for_distribution_and_target
+        perform_solve_for_target, 
+        perform_pinned_solve_for_target, 
+        perform_solve_for_distribution_and_target,
+        perform_pinned_solve_for_distribution_and_target
     };
 }
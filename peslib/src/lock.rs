@@ -1,7 +1,19 @@
 //! This module implements the lockfile. A lockfile is a serialization of a solve
+//!
+//! # Schemas
+//! - 1 - the original schema, recording the version of each package per target
+//! - 2 - adds the `distributions` table, recording where each distribution was found, along with a hash of
+//!   its manifest, and the optional `env` table, recording the environment composed for each target. These
+//!   allow the lockfile to be verified against the state of the repositories (see `LockFile::verify`).
+//!   Schema 2 lockfiles also record their provenance: when, where, and by which version of pes they were
+//!   written, the repositories searched, and the solve requested, as structured data.
 use std::{
+    collections::HashMap,
+    fmt,
     io::{Read, Write},
-    path::Path,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
 };
 use indexmap::IndexMap;
 use indexmap::map::{Keys, Iter};
@@ -10,44 +22,352 @@ use serde::{Serialize, Deserialize};
 use toml;
 
 use crate::{
+    aliases::VariantMap,
+    manifest::migration::SchemaProbe,
+    PackageRepository,
     PesError,
-    parser::parse_consuming_package_version,
+    parser::{parse_consuming_constraint, parse_consuming_package_version},
     SelectedDependencies,
     SemanticVersion,
+    TargetPolicy,
+    traits::Repository,
 };
 
+/// The latest lockfile schema
+pub const LOCKFILE_SCHEMA: u32 = 2;
+
+/// The version of pes recorded in the lockfiles it writes
+pub const PES_VERSION: &str = env!("CARGO_PKG_VERSION");
+
 pub type VersionMap = IndexMap<String, SemanticVersion>;
 pub type LockMap = IndexMap<String, VersionMap>;
+/// Maps a target to the variants selected for its packages
+pub type LockVariantMap = IndexMap<String, VariantMap>;
+/// Maps a distribution (eg maya-2019.0.0) to where it was found
+pub type LockDistributionMap = IndexMap<String, LockedDistribution>;
+/// Maps a target to the environment composed for it, sorted by variable name
+pub type LockEnvMap = IndexMap<String, IndexMap<String, String>>;
+
+/// Maps a target to the changes made to its packages between two lockfiles
+pub type LockDiff = IndexMap<String, Vec<LockChange>>;
+
+/// Hash the supplied bytes using 64 bit FNV-1a, returning the hash as 16 hex digits
+pub fn fnv1a_hash(bytes: &[u8]) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let hash = bytes
+        .iter()
+        .fold(OFFSET_BASIS, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(PRIME));
+    format!("{:016x}", hash)
+}
+
+/// Where a locked distribution was found when the lockfile was written
+#[derive(PartialEq, Eq, Serialize, Deserialize, Debug, Clone)]
+pub struct LockedDistribution {
+    /// The root of the distribution
+    pub path: PathBuf,
+    /// The root of the repository the distribution was found in
+    pub repo: PathBuf,
+    /// The path to the distribution's manifest
+    pub manifest: PathBuf,
+    /// The FNV-1a hash of the manifest's contents
+    pub manifest_hash: String,
+}
+
+impl LockedDistribution {
+    /// Construct a LockedDistribution given the root of the repository and the path to the distribution's
+    /// manifest, which we expect to be found at the root of the distribution
+    pub fn from_manifest<R: Into<PathBuf>, M: Into<PathBuf>>(repo: R, manifest: M) -> Result<Self, PesError> {
+        let manifest = manifest.into();
+        let manifest_hash = fnv1a_hash(&std::fs::read(&manifest)?);
+        let mut path = manifest.clone();
+        path.pop();
+        Ok(Self { path, repo: repo.into(), manifest, manifest_hash })
+    }
+}
+
+/// A discrepancy between a lockfile and the current state of the repositories or environment
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum LockMismatch {
+    /// The distribution no longer exists where it was found
+    MissingDistribution { distribution: String, path: PathBuf },
+    /// The manifest of the distribution has changed since the lockfile was written
+    ManifestChanged { distribution: String, manifest: PathBuf },
+    /// The environment composed for the target differs from that recorded, in the listed variables
+    EnvironmentChanged { target: String, vars: Vec<String> },
+}
+
+impl fmt::Display for LockMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingDistribution { distribution, path } => {
+                write!(f, "distribution {} is no longer found at {}", distribution, path.display())
+            }
+            Self::ManifestChanged { distribution, manifest } => {
+                write!(f, "the manifest of {} ({}) has changed since it was locked", distribution, manifest.display())
+            }
+            Self::EnvironmentChanged { target, vars } => {
+                write!(f, "the environment of target '{}' differs from that locked in: {}", target, vars.join(", "))
+            }
+        }
+    }
+}
+
+/// A change to a locked package between two lockfiles (see `LockFile::diff`)
+#[derive(PartialEq, Eq, Serialize, Debug, Clone)]
+#[serde(tag = "change", rename_all = "lowercase")]
+pub enum LockChange {
+    /// The package is locked by the newer lockfile alone
+    Added { package: String, version: SemanticVersion },
+    /// The package is locked by the older lockfile alone
+    Removed { package: String, version: SemanticVersion },
+    /// The newer lockfile locks a greater version of the package
+    Upgraded { package: String, from: SemanticVersion, to: SemanticVersion },
+    /// The newer lockfile locks a lesser version of the package
+    Downgraded { package: String, from: SemanticVersion, to: SemanticVersion },
+    /// The newer lockfile locks the same version of the package, with a different variant
+    Variant { package: String, version: SemanticVersion, from: String, to: String },
+}
+
+impl LockChange {
+    /// Retrieve the name of the package which changed
+    pub fn package(&self) -> &str {
+        match self {
+            Self::Added { package, .. }
+            | Self::Removed { package, .. }
+            | Self::Upgraded { package, .. }
+            | Self::Downgraded { package, .. }
+            | Self::Variant { package, .. } => package.as_str(),
+        }
+    }
+}
+
+impl fmt::Display for LockChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Added { package, version } => write!(f, "+ {}-{}", package, version),
+            Self::Removed { package, version } => write!(f, "- {}-{}", package, version),
+            Self::Upgraded { package, from, to } => write!(f, "^ {} {} -> {}", package, from, to),
+            Self::Downgraded { package, from, to } => write!(f, "v {} {} -> {}", package, from, to),
+            Self::Variant { package, version, from, to } => write!(f, "~ {}-{} {} -> {}", package, version, from, to),
+        }
+    }
+}
 
+/// A constraint of the solve recorded by a lockfile (eg maya-2019, or the weak ~python-3)
+#[derive(PartialEq, Eq, Serialize, Deserialize, Debug, Clone)]
+pub struct LockConstraint {
+    /// The name of the package
+    pub package: String,
+    /// The version range, as written. None if any version satisfies the constraint
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<String>,
+    /// Whether the constraint is a weak requirement
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub weak: bool,
+}
+
+// parses a constraint as supplied to a solve (eg maya-2019 or ~python-3)
+impl FromStr for LockConstraint {
+    type Err = PesError;
+
+    fn from_str(constraint: &str) -> Result<Self, Self::Err> {
+        let (weak, package, range) = parse_consuming_constraint(constraint)?;
+        Ok(Self { package: package.to_string(), range: range.map(str::to_string), weak })
+    }
+}
+
+impl fmt::Display for LockConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.weak {
+            write!(f, "~")?;
+        }
+        match &self.range {
+            Some(range) => write!(f, "{}-{}", self.package, range),
+            None => write!(f, "{}", self.package),
+        }
+    }
+}
+
+// format a point in time as an RFC3339 timestamp in UTC (eg 2021-03-04T05:06:07Z)
+fn rfc3339(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (days, secs) = ((secs / 86_400) as i64, secs % 86_400);
+    // convert days since the epoch to a civil date (see http://howardhinnant.github.io/date_algorithms.html)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, secs / 3600, (secs % 3600) / 60, secs % 60
+    )
+}
 
 /// The lockfile stores resolved dependency closures for targets
-#[derive(PartialEq, Serialize, Deserialize, Debug)]
+#[derive(PartialEq, Serialize, Deserialize, Debug, Clone)]
 pub struct LockFile {
     schema: u32,
     request: String,
-    // timestamp: ?
     author: String,
-    lock: LockMap
+    /// When the lockfile was written, as an RFC3339 timestamp
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hostname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pes_version: Option<String>,
+    /// The repositories searched by the solve, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    repos: Vec<PathBuf>,
+    #[serde(default)]
+    include_pre: bool,
+    /// The distribution whose targets were solved, should the solve be for a distribution
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    root_distribution: Option<String>,
+    /// The target policy of a distribution solve
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    transitive: Option<String>,
+    lock: LockMap,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    variants: LockVariantMap,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    distributions: LockDistributionMap,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    env: LockEnvMap,
+    /// The constraints solved, should the solve be for a set of constraints
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    constraints: Vec<LockConstraint>,
 }
 
 impl LockFile {
 
-    /// Construct a new, empty LockFile
+    /// Construct a new, empty LockFile, stamped with the current time and version of pes
     pub fn new<R: Into<String>, A: Into<String>>(request:R, author: A) -> Self {
         Self {
-            schema: 1,
+            schema: LOCKFILE_SCHEMA,
             request: request.into(),
             author: author.into(),
-            lock: LockMap::new()
+            timestamp: Some(rfc3339(SystemTime::now())),
+            hostname: None,
+            pes_version: Some(PES_VERSION.to_string()),
+            repos: Vec::new(),
+            include_pre: false,
+            root_distribution: None,
+            transitive: None,
+            lock: LockMap::new(),
+            variants: LockVariantMap::new(),
+            distributions: LockDistributionMap::new(),
+            env: LockEnvMap::new(),
+            constraints: Vec::new(),
         }
     }
 
-    /// Construct a LockFile from a str
+    /// Record the host the lockfile is written on
+    pub fn with_hostname<H: Into<String>>(mut self, hostname: H) -> Self {
+        self.hostname = Some(hostname.into());
+        self
+    }
+
+    /// Record the repositories searched by the solve, in order
+    pub fn with_repos(mut self, repos: Vec<PathBuf>) -> Self {
+        self.repos = repos;
+        self
+    }
+
+    /// Record whether prereleases were included in the solve
+    pub fn with_include_pre(mut self, include_pre: bool) -> Self {
+        self.include_pre = include_pre;
+        self
+    }
+
+    /// Record that the solve was for a set of constraints (eg maya-2019 ~python-3)
+    pub fn with_constraints<S: AsRef<str>>(mut self, constraints: &[S]) -> Result<Self, PesError> {
+        self.constraints = constraints
+            .iter()
+            .map(|c| c.as_ref().parse::<LockConstraint>())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(self)
+    }
+
+    /// Record that the solve was for the targets of a distribution (eg foo-1.0.0), using the supplied policy
+    /// to determine the targets of its dependencies
+    pub fn with_root_distribution<D: Into<String>>(mut self, distribution: D, transitive: &TargetPolicy) -> Self {
+        self.root_distribution = Some(distribution.into());
+        self.transitive = Some(transitive.to_string());
+        self
+    }
+
+    /// Retrieve the command line which requested the solve
+    pub fn request(&self) -> &str {
+        self.request.as_str()
+    }
+
+    /// Retrieve the author of the lockfile
+    pub fn author(&self) -> &str {
+        self.author.as_str()
+    }
+
+    /// Retrieve when the lockfile was written. Schema 1 lockfiles may not record this.
+    pub fn timestamp(&self) -> Option<&str> {
+        self.timestamp.as_deref()
+    }
+
+    /// Retrieve the host the lockfile was written on, should it have been recorded
+    pub fn hostname(&self) -> Option<&str> {
+        self.hostname.as_deref()
+    }
+
+    /// Retrieve the version of pes which wrote the lockfile, should it have been recorded
+    pub fn pes_version(&self) -> Option<&str> {
+        self.pes_version.as_deref()
+    }
+
+    /// Retrieve the repositories searched by the solve, in order
+    pub fn repos(&self) -> &[PathBuf] {
+        &self.repos
+    }
+
+    /// Were prereleases included in the solve?
+    pub fn include_pre(&self) -> bool {
+        self.include_pre
+    }
+
+    /// Retrieve the constraints solved, should the solve have been for a set of constraints
+    pub fn constraints(&self) -> &[LockConstraint] {
+        &self.constraints
+    }
+
+    /// Retrieve the distribution whose targets were solved, should the solve have been for a distribution
+    pub fn root_distribution(&self) -> Option<&str> {
+        self.root_distribution.as_deref()
+    }
+
+    /// Retrieve the target policy of a distribution solve
+    pub fn transitive(&self) -> Option<&str> {
+        self.transitive.as_deref()
+    }
+
+    /// Construct a LockFile from a str. Lockfiles written against any supported schema may be read.
     pub fn from_str(input: &str) -> Result<Self, PesError> {
+        let SchemaProbe { schema } = toml::from_str(input)?;
+        if !(1..=LOCKFILE_SCHEMA as u64).contains(&schema) {
+            return Err(PesError::UnsupportedLockFileSchema { schema, latest: LOCKFILE_SCHEMA as u64 });
+        }
         let lf: LockFile = toml::from_str(input)?;
         Ok(lf)
     }
 
+    /// Retrieve the schema the lockfile was written against
+    pub fn schema(&self) -> u32 {
+        self.schema
+    }
+
     /// Read a lockfile from a path
     pub fn from_file<I: AsRef<Path>>(input: I) -> Result<Self, PesError> {
         let file = std::fs::read_to_string(input)?;
@@ -92,6 +412,128 @@ impl LockFile {
     }
 
 
+    /// Record the variant selected for a package in the target
+    pub fn add_variant(&mut self, target: &str, package: &str, variant: &str) {
+        self.variants
+            .entry(target.to_string())
+            .or_default()
+            .insert(package.to_string(), variant.to_string());
+    }
+
+    /// Retrieve the variant selected for the package in the target, should it exist
+    pub fn variant(&self, target: &str, package: &str) -> Option<&str> {
+        self.variants
+            .get(target)
+            .and_then(|map| map.get(package))
+            .map(|v| v.as_str())
+    }
+
+    /// Retrieve the variants selected for the packages in the target, keyed by package. 
+    /// This is empty if none of the packages have variants.
+    pub fn variants_for(&self, target: &str) -> VariantMap {
+        self.variants.get(target).cloned().unwrap_or_default()
+    }
+
+    /// Record where a distribution (eg maya-2019.0.0) was found
+    pub fn add_distribution<D: Into<String>>(&mut self, distribution: D, locked: LockedDistribution) {
+        self.distributions.insert(distribution.into(), locked);
+    }
+
+    /// Retrieve where the package version was found, should it have been recorded
+    pub fn distribution(&self, package: &str, version: &SemanticVersion) -> Option<&LockedDistribution> {
+        self.distributions.get(&format!("{}-{}", package, version))
+    }
+
+    /// Record where each distribution locked by any target is found within the supplied repositories, returning
+    /// an error listing every distribution which cannot be found. Should a distribution be found in more than one
+    /// repository, the last wins, matching the search performed when building an environment.
+    pub fn record_distributions(&mut self, repos: &[PackageRepository]) -> Result<(), PesError> {
+        let mut missing = Vec::new();
+        let dists = self
+            .lock
+            .values()
+            .flat_map(|map| map.iter())
+            .map(|(package, version)| (package.clone(), version.clone()))
+            .collect::<Vec<_>>();
+        for (package, version) in dists {
+            let distribution = format!("{}-{}", package, version);
+            if self.distributions.contains_key(&distribution) {
+                continue;
+            }
+            let version_str = version.to_string();
+            let found = repos
+                .iter()
+                .rev()
+                .find_map(|repo| repo.manifest(&package, &version_str).ok().map(|manifest| (repo.root(), manifest)));
+            match found {
+                Some((repo, manifest)) => {
+                    let locked = LockedDistribution::from_manifest(repo, manifest)?;
+                    self.distributions.insert(distribution, locked);
+                }
+                None => missing.push(distribution),
+            }
+        }
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(PesError::MissingManifests(missing))
+        }
+    }
+
+    /// Record the environment composed for the target
+    pub fn set_env(&mut self, target: &str, env: HashMap<String, String>) {
+        let mut env = env.into_iter().collect::<Vec<_>>();
+        env.sort();
+        self.env.insert(target.to_string(), env.into_iter().collect());
+    }
+
+    /// Retrieve the environment recorded for the target, should it exist
+    pub fn env_for(&self, target: &str) -> Option<&IndexMap<String, String>> {
+        self.env.get(target)
+    }
+
+    /// Verify that the recorded distributions are still found where they were, with unchanged manifests,
+    /// returning the mismatches. Distributions recorded by schema 1 lockfiles are not verified.
+    pub fn verify(&self) -> Vec<LockMismatch> {
+        let mut mismatches = Vec::new();
+        for (distribution, locked) in &self.distributions {
+            if !locked.path.is_dir() {
+                mismatches.push(LockMismatch::MissingDistribution {
+                    distribution: distribution.clone(),
+                    path: locked.path.clone(),
+                });
+                continue;
+            }
+            let unchanged = std::fs::read(&locked.manifest)
+                .map(|contents| fnv1a_hash(&contents) == locked.manifest_hash)
+                .unwrap_or(false);
+            if !unchanged {
+                mismatches.push(LockMismatch::ManifestChanged {
+                    distribution: distribution.clone(),
+                    manifest: locked.manifest.clone(),
+                });
+            }
+        }
+        mismatches
+    }
+
+    /// Verify that the supplied environment, composed for the target, matches that recorded, should one have
+    /// been recorded
+    pub fn verify_env(&self, target: &str, env: &HashMap<String, String>) -> Option<LockMismatch> {
+        let recorded = self.env_for(target)?;
+        let mut vars = recorded
+            .iter()
+            .filter(|(var, value)| env.get(*var) != Some(*value))
+            .map(|(var, _)| var.clone())
+            .chain(env.keys().filter(|var| !recorded.contains_key(*var)).cloned())
+            .collect::<Vec<_>>();
+        if vars.is_empty() {
+            return None;
+        }
+        vars.sort();
+        Some(LockMismatch::EnvironmentChanged { target: target.to_string(), vars })
+    }
+
     /// Does the Lockfile contain a target?
     pub fn has_target(&self, target: &str) -> bool {
         self.lock.contains_key(target)
@@ -120,6 +562,84 @@ impl LockFile {
         }
     }
 
+    /// Retrieve the names of the packages locked for the target, in the order in which they were added
+    pub fn packages_for(&self, target: &str) -> Vec<&str> {
+        self.dists_for(target)
+            .map(|dists| dists.map(|(package, _)| package.as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Retrieve the locked version of every package in the target, other than the `unpinned` packages. Re-solving
+    /// the lockfile's request with these pinned changes as little as possible (see `perform_pinned_solve_for_target`).
+    pub fn pins_for<S: AsRef<str>>(&self, target: &str, unpinned: &[S]) -> Vec<(String, SemanticVersion)> {
+        self.dists_for(target)
+            .map(|dists| {
+                dists
+                    .filter(|(package, _)| !unpinned.iter().any(|p| p.as_ref() == package.as_str()))
+                    .map(|(package, version)| (package.clone(), version.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Retrieve a copy of the lockfile in which none of the `packages` are locked, nor have a variant, by any
+    /// target. Preferring the result while re-solving leaves the `packages` free to change.
+    pub fn without_packages<S: AsRef<str>>(&self, packages: &[S]) -> LockFile {
+        let mut lockfile = self.clone();
+        for package in packages {
+            for versions in lockfile.lock.values_mut() {
+                versions.shift_remove(package.as_ref());
+            }
+            for variants in lockfile.variants.values_mut() {
+                variants.shift_remove(package.as_ref());
+            }
+        }
+        lockfile
+    }
+
+    /// Calculate the changes made to the packages of each target, going from this lockfile to the `other`. Targets
+    /// without changes are omitted, and the changes to each target are ordered by package name. A package locked
+    /// at the same version with a different variant is reported as a `LockChange::Variant`.
+    pub fn diff(&self, other: &LockFile) -> LockDiff {
+        let empty = VersionMap::new();
+        let mut diff = LockDiff::new();
+        for target in self.targets().chain(other.targets().filter(|t| !self.has_target(t))) {
+            let from = self.lock.get(target).unwrap_or(&empty);
+            let to = other.lock.get(target).unwrap_or(&empty);
+            let mut changes = from
+                .iter()
+                .filter_map(|(package, version)| match to.get(package) {
+                    None => Some(LockChange::Removed { package: package.clone(), version: version.clone() }),
+                    Some(new) if new > version => {
+                        Some(LockChange::Upgraded { package: package.clone(), from: version.clone(), to: new.clone() })
+                    }
+                    Some(new) if new < version => {
+                        Some(LockChange::Downgraded { package: package.clone(), from: version.clone(), to: new.clone() })
+                    }
+                    Some(_) => match (self.variant(target, package), other.variant(target, package)) {
+                        (Some(old), Some(new)) if old != new => Some(LockChange::Variant {
+                            package: package.clone(),
+                            version: version.clone(),
+                            from: old.to_string(),
+                            to: new.to_string(),
+                        }),
+                        _ => None,
+                    },
+                })
+                .chain(
+                    to.iter()
+                        .filter(|(package, _)| !from.contains_key(package.as_str()))
+                        .map(|(package, version)| LockChange::Added { package: package.clone(), version: version.clone() }),
+                )
+                .collect::<Vec<_>>();
+            if !changes.is_empty() {
+                changes.sort_by(|a, b| a.package().cmp(b.package()));
+                diff.insert(target.clone(), changes);
+            }
+        }
+        diff
+    }
+
     pub fn selected_dependencies_for(&self, target: &str) -> Result<SelectedDependencies<String, SemanticVersion>, PesError> {
         let mut selected_deps: SelectedDependencies<String, SemanticVersion> = SelectedDependencies::default();
         match self.dists_for(target) {
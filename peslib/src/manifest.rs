@@ -4,13 +4,20 @@
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
-use crate::PesError;
+use crate::{PesError, SemanticVersion};
 use crate::distribution_range::DistributionRange;
+use crate::TargetPolicy;
 
+pub mod manifest_format;
+pub mod migration;
 pub mod package_manifest;
 pub(crate) mod package_target;
+pub mod package_variant;
 
+pub use manifest_format::ManifestFormat;
+pub use migration::MANIFEST_SCHEMA;
 pub use package_manifest::PackageManifest;
+pub use package_variant::PackageVariant;
 
 // manifest wraps inner manifest with metadata
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -46,6 +53,16 @@ impl Manifest {
         Ok(Self::new(root, package_manifest))
     }
 
+    /// Retrieve the name of the package
+    pub fn name(&self) -> &str {
+        self.inner.name.as_str()
+    }
+
+    /// Retrieve the version of the package
+    pub fn version(&self) -> &SemanticVersion {
+        &self.inner.version
+    }
+
     pub fn distribution(&self) -> String {
         self.inner.distribution()
     }
@@ -55,10 +72,52 @@ impl Manifest {
         self.inner.get_requires(target)
     }
 
+    /// retrieve a list of package ranges which the supplied target conflicts with
+    pub fn get_conflicts(&self, target: &str) -> Result<Vec<DistributionRange<'_>>, PesError> {
+        self.inner.get_conflicts(target)
+    }
+
+    /// retrieve a list of weak requires for the supplied target
+    pub fn get_weak_requires(&self, target: &str) -> Result<Vec<DistributionRange<'_>>, PesError> {
+        self.inner.get_weak_requires(target)
+    }
+
+    /// retrieve a list of requires for this package as a transitive dependency of a solve for `root_target`
+    pub fn get_transitive_requires(&self, target_policy: &TargetPolicy, root_target: &str) -> Result<Vec<DistributionRange<'_>>, PesError> {
+        self.inner.get_transitive_requires(target_policy, root_target)
+    }
+
+    /// determine whether the manifest defines the provided target
+    pub fn has_target(&self, target: &str) -> bool {
+        self.inner.has_target(target)
+    }
+
     pub fn environment(&self) -> indexmap::map::Iter<String, String> {
         self.inner.environment.iter()
     }
 
+    /// retrieve the environment for the supplied target, including the package-wide environment, in the order
+    /// in which it is applied
+    pub fn environment_for(&self, target: &str) -> Result<Vec<(&String, &String)>, PesError> {
+        self.inner.get_environment(target)
+    }
+
+    /// retrieve the names of the packages the package depends upon when its environment is built for the
+    /// supplied target and selected variant
+    pub fn environment_dependencies_for(&self, target: &str, variant: Option<&str>) -> Result<Vec<&str>, PesError> {
+        self.inner.get_environment_dependencies(target, variant)
+    }
+
+    /// Retrieve the variants of the package. This is empty if the package does not have variants
+    pub fn variants(&self) -> &[PackageVariant] {
+        self.inner.variants.as_slice()
+    }
+
+    /// Retrieve the variant with the supplied name, should it exist
+    pub fn variant(&self, name: &str) -> Option<&PackageVariant> {
+        self.inner.variant(name)
+    }
+
     /// Retrive the path to the package root
     pub fn package_root(&self) -> &Path {
         self.root.as_path()
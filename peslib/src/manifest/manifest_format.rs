@@ -0,0 +1,73 @@
+//! Component modeling the serialization format of a package's manifest
+//!
+//! Manifests may be written in either yaml (`manifest.yaml`) or toml (`manifest.toml`). The
+//! format of a manifest file is determined by its extension, falling back to its contents
+//! should the extension be unrecognized.
+use std::path::Path;
+
+use crate::PesError;
+
+/// The serialization format of a manifest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestFormat {
+    Yaml,
+    Toml,
+}
+
+impl ManifestFormat {
+    /// Determine the format of a manifest from the extension of its path, should it be recognized
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "yaml" | "yml" => Some(ManifestFormat::Yaml),
+            "toml" => Some(ManifestFormat::Toml),
+            _ => None,
+        }
+    }
+
+    /// Determine the format of a manifest from its contents. The first line which is neither
+    /// blank nor a comment decides: a toml table header, or a key followed by `=` rather than `:`,
+    /// indicates toml. Anything else is assumed to be yaml.
+    pub fn detect(contents: &str) -> Self {
+        let line = contents
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#') && line != &"---");
+        match line {
+            Some(line) if line.starts_with('[') => ManifestFormat::Toml,
+            Some(line) => match (line.find('='), line.find(':')) {
+                (Some(eq), Some(colon)) if eq < colon => ManifestFormat::Toml,
+                (Some(_), None) => ManifestFormat::Toml,
+                _ => ManifestFormat::Yaml,
+            },
+            None => ManifestFormat::Yaml,
+        }
+    }
+
+    /// Determine the format of the manifest file at `path`, with the supplied contents. The
+    /// extension takes precedence over the contents.
+    pub fn for_file<P: AsRef<Path>>(path: P, contents: &str) -> Self {
+        Self::from_path(path).unwrap_or_else(|| Self::detect(contents))
+    }
+
+    /// Deserialize an instance of `T` from a str in this format
+    pub fn deserialize<T>(&self, value: &str) -> Result<T, PesError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        match self {
+            ManifestFormat::Yaml => Ok(serde_yaml::from_str(value)?),
+            ManifestFormat::Toml => Ok(toml::from_str(value)?),
+        }
+    }
+
+    /// Serialize the value to a String in this format
+    pub fn serialize<T>(&self, value: &T) -> Result<String, PesError>
+    where
+        T: serde::Serialize,
+    {
+        match self {
+            ManifestFormat::Yaml => Ok(serde_yaml::to_string(value)?),
+            ManifestFormat::Toml => Ok(toml::to_string(value)?),
+        }
+    }
+}
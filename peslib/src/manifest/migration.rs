@@ -0,0 +1,117 @@
+//! Migration of manifests written against older schemas
+//!
+//! Manifests declare the schema they are written against via their `schema` key. When a
+//! manifest is loaded, its schema is checked first, such that schemas newer than the latest,
+//! which this version of pes knows nothing about, are rejected before any attempt is made to
+//! interpret the rest of the manifest. Manifests written against older schemas are then
+//! upgraded, one schema at a time, to the latest schema. Migrations operate on the untyped
+//! document, before it is deserialized against the latest schema, such that they may rename or
+//! remove keys which the latest schema no longer knows about.
+//!
+//! Documents in either format are represented as a `yaml_rust::Yaml`, rather than a `serde_yaml::Value`,
+//! as the former retains the text of scalars. A range such as `3.10` would otherwise be read as a float,
+//! and deserialized as `3.1`.
+//!
+//! # Schemas
+//! - 1 - the original schema
+//! - 2 - adds `conflicts`, `weak_requires`, `variants`, target environments, and the extended range grammar. The
+//!   `target` and `include-targets` keys of the original toml design are renamed to `targets`
+//!   and `include`, matching the yaml keys.
+use serde::Deserialize;
+use yaml_rust::{yaml::Hash, Yaml, YamlEmitter, YamlLoader};
+
+use crate::{manifest::{ManifestFormat, PackageManifest}, PesError};
+
+/// The latest manifest schema
+pub const MANIFEST_SCHEMA: u32 = 2;
+
+// A migration upgrades a manifest document from a schema to its successor
+type Migration = fn(Yaml) -> Result<Yaml, PesError>;
+
+// MIGRATIONS[n - 1] upgrades a manifest document from schema n to schema n + 1
+const MIGRATIONS: &[Migration] = &[migrate_1_to_2];
+
+// The only key shared by every schema, of both manifests and lockfiles
+#[derive(Deserialize)]
+pub(crate) struct SchemaProbe {
+    pub(crate) schema: u64,
+}
+
+/// Retrieve the schema declared by a manifest str in the supplied format, returning an error should the
+/// schema be unsupported
+pub fn schema(value: &str, format: ManifestFormat) -> Result<u32, PesError> {
+    let SchemaProbe { schema } = format.deserialize(value)?;
+    if (1..=MANIFEST_SCHEMA as u64).contains(&schema) {
+        Ok(schema as u32)
+    } else {
+        Err(PesError::UnsupportedSchema { schema, latest: MANIFEST_SCHEMA as u64 })
+    }
+}
+
+/// Upgrade a manifest str in the supplied format, written against the supplied schema, to the latest schema
+pub fn migrate(value: &str, format: ManifestFormat, schema: u32) -> Result<PackageManifest, PesError> {
+    let mut document = parse_document(value, format)?;
+    for migration in &MIGRATIONS[(schema - 1) as usize..] {
+        document = migration(document)?;
+    }
+    let mut migrated = String::new();
+    YamlEmitter::new(&mut migrated)
+        .dump(&document)
+        .map_err(|e| PesError::ParsingFailure(format!("unable to emit migrated manifest: {:?}", e)))?;
+    ManifestFormat::Yaml.deserialize(&migrated)
+}
+
+// parse a manifest str in the supplied format as an untyped document
+fn parse_document(value: &str, format: ManifestFormat) -> Result<Yaml, PesError> {
+    match format {
+        ManifestFormat::Yaml => YamlLoader::load_from_str(value)
+            .map_err(|e| PesError::ParsingFailure(e.to_string()))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| PesError::ParsingFailure("the manifest is empty".to_string())),
+        ManifestFormat::Toml => Ok(from_toml(format.deserialize(value)?)),
+    }
+}
+
+// convert a toml value into the equivalent yaml
+fn from_toml(value: toml::Value) -> Yaml {
+    match value {
+        toml::Value::String(value) => Yaml::String(value),
+        toml::Value::Integer(value) => Yaml::Integer(value),
+        toml::Value::Float(value) => Yaml::Real(value.to_string()),
+        toml::Value::Boolean(value) => Yaml::Boolean(value),
+        toml::Value::Datetime(value) => Yaml::String(value.to_string()),
+        toml::Value::Array(values) => Yaml::Array(values.into_iter().map(from_toml).collect()),
+        toml::Value::Table(table) => {
+            Yaml::Hash(table.into_iter().map(|(key, value)| (Yaml::String(key), from_toml(value))).collect())
+        }
+    }
+}
+
+// rename a key of the map, should it be present, returning an error should the new key be present as well
+fn rename_key(map: &mut Hash, from: &str, to: &str) -> Result<(), PesError> {
+    if let Some(value) = map.remove(&Yaml::String(from.to_string())) {
+        if map.insert(Yaml::String(to.to_string()), value).is_some() {
+            return Err(PesError::DuplicateKey(to.to_string()));
+        }
+    }
+    Ok(())
+}
+
+// Schema 2 is a superset of schema 1, bar the renaming of the toml keys `target` and `include-targets`
+fn migrate_1_to_2(mut document: Yaml) -> Result<Yaml, PesError> {
+    let manifest = match &mut document {
+        Yaml::Hash(manifest) => manifest,
+        _ => return Err(PesError::InvalidSchema("the manifest is not a map".to_string())),
+    };
+    rename_key(manifest, "target", "targets")?;
+    if let Some(Yaml::Hash(targets)) = manifest.get_mut(&Yaml::String("targets".to_string())) {
+        for (_, target) in targets.iter_mut() {
+            if let Yaml::Hash(target) = target {
+                rename_key(target, "include-targets", "include")?;
+            }
+        }
+    }
+    manifest.insert(Yaml::String("schema".to_string()), Yaml::Integer(2));
+    Ok(document)
+}
@@ -3,17 +3,31 @@ use crate::{EnvMap, TargetMap};
 use std::path::Path;
 //use indexmap::IndexMap;
 //use pubgrub::version::SemanticVersion;
+use indexmap::IndexMap;
+use log::debug;
+use pubgrub::range::Range;
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    constants::DEFAULT_TARGET,
     PesError,
     //manifest::PackageTarget,
     SemanticVersion,
     DistributionRange,
+    TargetPolicy,
+    manifest::{migration, package_target::PackageTarget, ManifestFormat, PackageVariant},
+    range::display_range,
 };
 
+// How the ranges of a package, appearing in more than one target, are combined
+#[derive(Debug, Clone, Copy)]
+enum Combine {
+    Intersection,
+    Union,
+}
+
 /// Models a manifest for package
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct PackageManifest {
     /// schema version of the manifest
     pub schema: u32,
@@ -29,57 +43,265 @@ pub struct PackageManifest {
     /// environment vars (doesnt really have to be an index map)
     #[serde(default)]
     pub environment: EnvMap, //IndexMap<String, String>
+    /// Variants of the package, in order of preference
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<PackageVariant>,
 }
 
 impl PackageManifest {
-    /// Construct a PackageManifest from a str
+    /// Construct a PackageManifest from a str, in either yaml or toml. The format is detected from the contents,
+    /// and manifests written against older schemas are migrated to the latest schema.
     pub fn from_str_unchecked(value: &str) -> Result<Self, PesError> {
-        Ok(serde_yaml::from_str(value)?)
+        let (manifest, _) = Self::from_str_migrated(value, ManifestFormat::detect(value))?;
+        Ok(manifest)
+    }
+
+    /// Construct a PackageManifest from a str in the supplied format, migrating it to the latest schema. The
+    /// schema the manifest was written against is returned alongside it.
+    pub fn from_str_migrated(value: &str, format: ManifestFormat) -> Result<(Self, u32), PesError> {
+        let schema = migration::schema(value, format)?;
+        let manifest = if schema == migration::MANIFEST_SCHEMA {
+            format.deserialize(value)?
+        } else {
+            migration::migrate(value, format, schema)?
+        };
+        Ok((manifest, schema))
     }
 
-    /// Construct a PackageManifest from a readable file
+    /// Construct a PackageManifest from a readable file. The format of the file is determined by its
+    /// extension, falling back to its contents.
     pub fn from_file_unchecked<F>(value: F) -> Result<Self, PesError>
     where
         F: AsRef<Path>,
     {
         let manifest = std::fs::read_to_string(value.as_ref())?;
-        Ok(Self::from_str_unchecked(&manifest)?)
+        let (manifest, _) = Self::from_str_migrated(&manifest, ManifestFormat::for_file(value, &manifest))?;
+        Ok(manifest)
     }
 
-    /// Construct a PackageManifest from a str
+    /// Construct a PackageManifest from a str, in either yaml or toml
     pub fn from_str(value: &str) -> Result<Self, PesError> {
-        let manifest: PackageManifest = serde_yaml::from_str(value)?;
+        let manifest = Self::from_str_unchecked(value)?;
         manifest.validate()?;
         Ok(manifest)
     }
 
-    /// Construct a PackageManifest from a readable file
+    /// Construct a PackageManifest from a readable file, in either yaml or toml
     pub fn from_file<F>(value: F) -> Result<Self, PesError>
     where
         F: AsRef<Path>,
     {
-        let manifest = std::fs::read_to_string(value.as_ref())?;
-        Ok(serde_yaml::from_str(&manifest)?)
+        let manifest = Self::from_file_unchecked(value)?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// Serialize the PackageManifest in the supplied format
+    pub fn to_string_as(&self, format: ManifestFormat) -> Result<String, PesError> {
+        format.serialize(self)
     }
 
-    /// Retrieve a vector of SemanticVersion Ranges associated with the provided target
-    pub fn get_requires(&self, target: &str) -> Result<Vec<DistributionRange>, PesError> {
-        let rtarget = self
+    /// Write the PackageManifest to a file. The format is determined by the file's extension, defaulting to yaml.
+    pub fn to_file<F>(&self, value: F) -> Result<(), PesError>
+    where
+        F: AsRef<Path>,
+    {
+        let format = ManifestFormat::from_path(value.as_ref()).unwrap_or(ManifestFormat::Yaml);
+        std::fs::write(value.as_ref(), self.to_string_as(format)?)?;
+        Ok(())
+    }
+
+    /// Retrieve the targets which contribute to the provided target - that is the target itself, along with every
+    /// target it includes, directly or indirectly. Included targets precede the targets which include them, and each
+    /// target appears once, regardless of how many times it is included. Should the includes form a cycle, a
+    /// `PesError::CircularInclude` is returned.
+    pub fn resolve_includes(&self, target: &str) -> Result<Vec<&str>, PesError> {
+        let (target, _) = self
             .targets
-            .get(target)
+            .get_key_value(target)
             .ok_or_else(|| PesError::MissingKey(target.into()))?;
+        let mut resolved = Vec::new();
+        self.visit_includes(target, &mut Vec::new(), &mut resolved)?;
+        Ok(resolved)
+    }
+
+    // depth first traversal of the includes of the target, tracking the path from the root in `stack`
+    fn visit_includes<'a>(&'a self, target: &'a str, stack: &mut Vec<&'a str>, resolved: &mut Vec<&'a str>) -> Result<(), PesError> {
+        if let Some(pos) = stack.iter().position(|t| *t == target) {
+            let mut cycle = stack[pos..].iter().map(|t| t.to_string()).collect::<Vec<_>>();
+            cycle.push(target.to_string());
+            return Err(PesError::CircularInclude(cycle));
+        }
+        if resolved.contains(&target) {
+            return Ok(());
+        }
+        stack.push(target);
+        for include in self.targets[target].get_includes() {
+            if !self.targets.contains_key(include) {
+                return Err(PesError::MissingInclude {
+                    target: target.into(),
+                    include: include.into(),
+                });
+            }
+            self.visit_includes(include, stack, resolved)?;
+        }
+        stack.pop();
+        resolved.push(target);
+        Ok(())
+    }
+
+    // Gather the ranges, retrieved from each contributing target by `ranges`, for the provided target. A package
+    // appearing in more than one contributing target has its ranges combined.
+    fn merge_ranges<'a, F>(&'a self, target: &str, ranges: F, combine: Combine) -> Result<Vec<DistributionRange<'a>>, PesError>
+    where
+        F: Fn(&'a PackageTarget) -> Result<Vec<DistributionRange<'a>>, PesError>,
+    {
+        let mut merged: IndexMap<&str, DistributionRange> = IndexMap::new();
+        for contributor in self.resolve_includes(target)? {
+            for dist_range in ranges(&self.targets[contributor])? {
+                let existing = match merged.get_mut(dist_range.name) {
+                    Some(existing) => existing,
+                    None => {
+                        merged.insert(dist_range.name, dist_range);
+                        continue;
+                    }
+                };
+                existing.range = match combine {
+                    Combine::Union => existing.range.union(&dist_range.range),
+                    Combine::Intersection => {
+                        let range = existing.range.intersection(&dist_range.range);
+                        if range == Range::none() {
+                            return Err(PesError::IncompatibleRanges {
+                                target: target.into(),
+                                package: dist_range.name.into(),
+                                first: display_range(&existing.range).to_string(),
+                                second: display_range(&dist_range.range).to_string(),
+                            });
+                        }
+                        range
+                    }
+                };
+            }
+        }
+        Ok(merged.into_iter().map(|(_, dist_range)| dist_range).collect())
+    }
 
-        let mut results = Vec::new();
+    /// Retrieve a vector of SemanticVersion Ranges associated with the provided target, including those of
+    /// the targets it includes, recursively. Should a package be required by more than one of those targets,
+    /// the intersection of the ranges is required, and a `PesError::IncompatibleRanges` returned should
+    /// the ranges not intersect.
+    pub fn get_requires(&self, target: &str) -> Result<Vec<DistributionRange<'_>>, PesError> {
+        self.merge_ranges(target, PackageTarget::get_all_requires, Combine::Intersection)
+    }
+
+    /// Retrieve a vector of the package ranges which the provided target conflicts with, including those
+    /// of the targets it includes, recursively. Should a package be conflicted with by more than one of
+    /// those targets, the target conflicts with the union of the ranges.
+    pub fn get_conflicts(&self, target: &str) -> Result<Vec<DistributionRange<'_>>, PesError> {
+        self.merge_ranges(target, PackageTarget::get_all_conflicts, Combine::Union)
+    }
+
+    /// Retrieve a vector of the weak package ranges for the provided target, including those of the targets it
+    /// includes, recursively. Ranges of the same package are intersected, as per `get_requires`.
+    pub fn get_weak_requires(&self, target: &str) -> Result<Vec<DistributionRange<'_>>, PesError> {
+        self.merge_ranges(target, PackageTarget::get_all_weak_requires, Combine::Intersection)
+    }
 
-        // incorporate any included targets package ranges
-        for include in rtarget.get_includes() {
-            let inc_target = self.targets.get(include);
-            if let Some(inc_target) = inc_target {
-                results.append(&mut inc_target.get_all_requires()?);
+    /// Retrieve the environment of the package for the provided target, as (name, value) pairs, in the order in
+    /// which they are applied: the package-wide environment, followed by the environments of the targets
+    /// contributing to the target (see `resolve_includes`). Should the manifest not define the target, we fall
+    /// back to the `run` target, and failing that, to the package-wide environment alone.
+    pub fn get_environment(&self, target: &str) -> Result<Vec<(&String, &String)>, PesError> {
+        let mut environment = self.environment.iter().collect::<Vec<_>>();
+        if let Some(target) = self.target_or_default(target) {
+            for contributor in self.resolve_includes(target)? {
+                environment.extend(self.targets[contributor].environment.iter());
             }
         }
-        results.append(&mut rtarget.get_all_requires()?);
-        Ok(results)
+        Ok(environment)
+    }
+
+    /// Retrieve the names of the packages which the package depends upon when its environment is built for the
+    /// provided target: those it requires, strongly or weakly, for the target `get_environment` falls back to,
+    /// followed by those required by the selected variant, if any.
+    pub fn get_environment_dependencies(&self, target: &str, variant: Option<&str>) -> Result<Vec<&str>, PesError> {
+        let mut dependencies = match self.target_or_default(target) {
+            Some(target) => self
+                .get_requires(target)?
+                .into_iter()
+                .chain(self.get_weak_requires(target)?)
+                .map(|range| range.name)
+                .collect(),
+            None => Vec::new(),
+        };
+        if let Some(name) = variant {
+            let variant = self
+                .variant(name)
+                .ok_or_else(|| PesError::InvalidVariant(format!("{}-{}[{}]", self.name, self.version, name)))?;
+            dependencies.extend(variant.get_all_requires()?.into_iter().map(|range| range.name));
+        }
+        Ok(dependencies)
+    }
+
+    /// Determine whether the package has variants
+    pub fn has_variants(&self) -> bool {
+        !self.variants.is_empty()
+    }
+
+    /// Retrieve the variant with the supplied name, should it exist
+    pub fn variant(&self, name: &str) -> Option<&PackageVariant> {
+        self.variants.iter().find(|v| v.name == name)
+    }
+
+    /// Determine whether the manifest defines the provided target
+    pub fn has_target(&self, target: &str) -> bool {
+        self.targets.contains_key(target)
+    }
+
+    /// Retrieve the target used when this package is a transitive dependency of a solve for `root_target`,
+    /// as determined by the `target_policy`. Should the manifest lack the selected target, we fall back
+    /// to the `run` target, and failing that, to None.
+    pub fn get_transitive_target<'a>(&'a self, target_policy: &'a TargetPolicy, root_target: &'a str) -> Option<&'a str> {
+        self.target_or_default(target_policy.target_for(&self.name, root_target))
+    }
+
+    // Retrieve the target, should the manifest define it, falling back to the `run` target, and failing that, to None.
+    fn target_or_default<'a>(&self, target: &'a str) -> Option<&'a str> {
+        if self.has_target(target) {
+            Some(target)
+        } else if self.has_target(DEFAULT_TARGET) {
+            debug!("{} does not define target '{}'. Falling back to '{}'", self.distribution(), target, DEFAULT_TARGET);
+            Some(DEFAULT_TARGET)
+        } else {
+            None
+        }
+    }
+
+    /// Retrieve the requires used when this package is a transitive dependency of a solve for `root_target`.
+    /// See `get_transitive_target`.
+    pub fn get_transitive_requires(&self, target_policy: &TargetPolicy, root_target: &str) -> Result<Vec<DistributionRange<'_>>, PesError> {
+        match self.get_transitive_target(target_policy, root_target) {
+            Some(target) => self.get_requires(target),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Retrieve the conflicts used when this package is a transitive dependency of a solve for `root_target`.
+    /// See `get_transitive_target`.
+    pub fn get_transitive_conflicts(&self, target_policy: &TargetPolicy, root_target: &str) -> Result<Vec<DistributionRange<'_>>, PesError> {
+        match self.get_transitive_target(target_policy, root_target) {
+            Some(target) => self.get_conflicts(target),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Retrieve the weak requires used when this package is a transitive dependency of a solve for `root_target`.
+    /// See `get_transitive_target`.
+    pub fn get_transitive_weak_requires(&self, target_policy: &TargetPolicy, root_target: &str) -> Result<Vec<DistributionRange<'_>>, PesError> {
+        match self.get_transitive_target(target_policy, root_target) {
+            Some(target) => self.get_weak_requires(target),
+            None => Ok(Vec::new()),
+        }
     }
 
     // looks like version is already a SemanticVersion
@@ -102,6 +324,19 @@ impl PackageManifest {
                 }
             }
             target.validate_requires()?;
+            target.validate_conflicts()?;
+            target.validate_weak_requires()?;
+        }
+        // with each target valid in isolation, verify that they may be combined
+        for key in self.targets.keys() {
+            self.get_requires(key)?;
+            self.get_weak_requires(key)?;
+        }
+        for (idx, variant) in self.variants.iter().enumerate() {
+            variant.validate()?;
+            if self.variants[..idx].iter().any(|v| v.name == variant.name) {
+                return Err(PesError::DuplicateKey(variant.name.clone()));
+            }
         }
 
         Ok(())
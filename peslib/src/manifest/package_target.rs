@@ -4,13 +4,23 @@ use indexmap::IndexMap;
 use pubgrub::{range::Range};
 use serde::{Deserialize, Serialize};
 
-use crate::{PesError, parser::parse_consuming_semver_range, DistributionRange, SemanticVersion};
+use crate::{PesError, parser::parse_consuming_semver_range, range::display_range, DistributionRange, EnvMap, SemanticVersion};
 
 /// Struct used to simplify serialization & deserialization of manifest
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct PackageTarget {
     pub include: Option<Vec<String>>,
     pub requires: IndexMap<String, String>,
+    /// Ranges of packages which the target is incompatible with
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub conflicts: IndexMap<String, String>,
+    /// Ranges which packages must fall within, should they be in the dependency closure. Unlike
+    /// requires, weak requires do not pull packages into the closure.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub weak_requires: IndexMap<String, String>,
+    /// Environment applied after the package-wide environment when the target is requested
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub environment: EnvMap,
 }
 
 impl Default for PackageTarget {
@@ -18,6 +28,9 @@ impl Default for PackageTarget {
         Self {
             include: None,
             requires: IndexMap::new(),
+            conflicts: IndexMap::new(),
+            weak_requires: IndexMap::new(),
+            environment: EnvMap::new(),
         }
     }
 }
@@ -60,6 +73,37 @@ impl PackageTarget {
         self.requires.insert(key.into(), value.into())
     }
 
+    /// Given a key and a SemanticVersion Range, insert the range, in canonical form, into the requires map.
+    /// If the key already exists in the map, return the old value wrapped in an Option. Otherwise return None.
+    pub fn requires_range<K>(&mut self, key: K, value: &Range<SemanticVersion>) -> Option<String>
+    where
+        K: Into<String>,
+    {
+        self.requires(key, display_range(value).to_string())
+    }
+
+    /// Given a key and a value, declare that the target conflicts with the range of versions
+    /// of the package. If the key already exists in the map, return the old value wrapped in an
+    /// Option. Otherwise return None.
+    pub fn conflicts<K, V>(&mut self, key: K, value: V) -> Option<String>
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.conflicts.insert(key.into(), value.into())
+    }
+
+    /// Given a key and a value, declare that the package, should it be in the dependency closure, must fall within
+    /// the range, without requiring the package. If the key already exists in the map, return the old value wrapped
+    /// in an Option. Otherwise return None.
+    pub fn weak_requires<K, V>(&mut self, key: K, value: V) -> Option<String>
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.weak_requires.insert(key.into(), value.into())
+    }
+
     /// Retrieve the SemanticVersion Range associated with the provided key
     pub fn get_requires(&self, key: &str) -> Result<Range<SemanticVersion>, PesError> {
         let result = self.requires.get(key);
@@ -80,6 +124,26 @@ impl PackageTarget {
         Ok(retval)
     }
 
+    /// Retrieve all the conflicts
+    pub fn get_all_conflicts(&self) -> Result<Vec<DistributionRange<'_>>, PesError> {
+        let mut retval = Vec::with_capacity(self.conflicts.len());
+        for (k, v) in self.conflicts.iter() {
+            retval.push(DistributionRange::from_strs(k.as_str(), v)?);
+        }
+
+        Ok(retval)
+    }
+
+    /// Retrieve all the weak requires
+    pub fn get_all_weak_requires(&self) -> Result<Vec<DistributionRange<'_>>, PesError> {
+        let mut retval = Vec::with_capacity(self.weak_requires.len());
+        for (k, v) in self.weak_requires.iter() {
+            retval.push(DistributionRange::from_strs(k.as_str(), v)?);
+        }
+
+        Ok(retval)
+    }
+
     /// Retrieve a vector of included targets
     pub fn get_includes(&self) -> Vec<&str> {
         if let Some(ref includes) = self.include {
@@ -89,6 +153,24 @@ impl PackageTarget {
         }
     }
 
+    /// Rewrite the ranges of the requires, conflicts, and weak requires in canonical form, such that equivalent
+    /// ranges are written identically (eg `^1` and `1+<2` both become `1.*`). An error is returned, and the target 
+    /// left unchanged, should any of the ranges be invalid.
+    pub fn normalize(&mut self) -> Result<(), PesError> {
+        let normalize = |map: &IndexMap<String, String>| -> Result<IndexMap<String, String>, PesError> {
+            map.iter()
+                .map(|(k, v)| Ok((k.clone(), display_range(&parse_consuming_semver_range(v)?).to_string())))
+                .collect()
+        };
+        let requires = normalize(&self.requires)?;
+        let conflicts = normalize(&self.conflicts)?;
+        let weak_requires = normalize(&self.weak_requires)?;
+        self.requires = requires;
+        self.conflicts = conflicts;
+        self.weak_requires = weak_requires;
+        Ok(())
+    }
+
     /// Validate that all of the requires are valid semver ranges
     pub fn validate_requires(&self) -> Result<(), PesError> {
         for v in self.requires.values() {
@@ -96,4 +178,28 @@ impl PackageTarget {
         }
         Ok(())
     }
+
+    /// Validate that all of the conflicts are valid semver ranges, and that the target does not
+    /// both require and conflict with a package
+    pub fn validate_conflicts(&self) -> Result<(), PesError> {
+        for (k, v) in self.conflicts.iter() {
+            let _ = parse_consuming_semver_range(v)?;
+            if self.requires.contains_key(k) {
+                return Err(PesError::DuplicateKey(k.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate that all of the weak requires are valid semver ranges, and that the target does not also
+    /// require or conflict with the package
+    pub fn validate_weak_requires(&self) -> Result<(), PesError> {
+        for (k, v) in self.weak_requires.iter() {
+            let _ = parse_consuming_semver_range(v)?;
+            if self.requires.contains_key(k) || self.conflicts.contains_key(k) {
+                return Err(PesError::DuplicateKey(k.clone()));
+            }
+        }
+        Ok(())
+    }
 }
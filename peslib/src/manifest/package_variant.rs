@@ -0,0 +1,77 @@
+//! Component modeling a variant of a package version
+//!
+//! A package version may be built several ways (eg against python2 and python3, or
+//! against maya2018 and maya2019). Each variant carries its own requires, which apply
+//! to every target, and its own environment, which is applied after the package-wide
+//! environment. Variants are stored beneath the distribution, in a directory named after
+//! the variant:
+//! ```ignore
+//! <root>/<package>/<version>/<variant>/
+//! ```
+use std::path::{Path, PathBuf};
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{parser::parse_consuming_semver_range, DistributionRange, EnvMap, PesError};
+
+/// Models a single variant of a package version
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PackageVariant {
+    /// Name of the variant. This doubles as the name of the variant's directory within the distribution
+    pub name: String,
+    /// Requires which apply to all targets when this variant is selected
+    #[serde(default)]
+    pub requires: IndexMap<String, String>,
+    /// Environment applied after the package-wide environment when this variant is selected
+    #[serde(default)]
+    pub environment: EnvMap,
+}
+
+impl PackageVariant {
+    /// Construct a new, empty PackageVariant
+    pub fn new<N: Into<String>>(name: N) -> Self {
+        Self {
+            name: name.into(),
+            requires: IndexMap::new(),
+            environment: EnvMap::new(),
+        }
+    }
+
+    /// Given a key and a value, insert the value into the requires map. If the key already 
+    /// exists in the map, return the old value wrapped in an Option. Otherwise return None.
+    pub fn requires<K, V>(&mut self, key: K, value: V) -> Option<String>
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.requires.insert(key.into(), value.into())
+    }
+
+    /// Retrieve all the requires
+    pub fn get_all_requires(&self) -> Result<Vec<DistributionRange<'_>>, PesError> {
+        let mut retval = Vec::with_capacity(self.requires.len());
+        for (k, v) in self.requires.iter() {
+            retval.push(DistributionRange::from_strs(k.as_str(), v)?);
+        }
+
+        Ok(retval)
+    }
+
+    /// Retrieve the root of the variant, given the root of the distribution
+    pub fn root<P: AsRef<Path>>(&self, distribution_root: P) -> PathBuf {
+        distribution_root.as_ref().join(&self.name)
+    }
+
+    /// Validate that the name may be used as a directory name and that all of the
+    /// requires are valid semver ranges
+    pub fn validate(&self) -> Result<(), PesError> {
+        if self.name.is_empty() || self.name == "." || self.name == ".." || self.name.contains('/') {
+            return Err(PesError::InvalidVariant(self.name.clone()));
+        }
+        for v in self.requires.values() {
+            let _ = parse_consuming_semver_range(v)?;
+        }
+        Ok(())
+    }
+}
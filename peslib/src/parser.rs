@@ -20,27 +20,29 @@ use pubgrub::{
 
 use nom::{
     branch::alt,
-    bytes::complete::tag,
-    character::complete::digit1,
+    bytes::complete::{is_not, tag, take_while1},
+    character::complete::{alpha1, alphanumeric1, anychar, char, digit1, multispace0},
     combinator::{
         all_consuming, 
+        map,
+        opt,
         recognize
     },
     multi::{
         many_m_n, 
-        many0, many1, separated_list0
+        many0, many1, separated_list0, separated_list1
     },
     sequence::{
         delimited,
         pair,
         preceded,
         separated_pair,
-        //terminated,
+        terminated,
         tuple,
     },
 };
 
-use crate::{PNResult, PesNomError, PNCompleteResult, PesError, SemanticVersion, ReleaseType};
+use crate::{PNResult, PesNomError, PesError, SemanticVersion, ReleaseType};
 use crate::env::{PathToken, PathMode};
 use crate::parser_atoms::{alphaword_many0_underscore_word, ws};
 pub use crate::traits::VarProvider;
@@ -54,6 +56,18 @@ pub use crate::env::BasicVarProvider;
 
 /// Given an Rc wrapped provider, return a parser which parses the paths from a string
 ///
+/// # Grammar
+/// - `/foo/{root}/bar:/bla` - set the variable to the list of paths, joined by `:`
+/// - `prepend(/foo:/bar)`, `append(/foo:/bar)` - prepend or append the list of paths to the variable
+/// - `append(-O2 -g, sep=" ")`, `prepend(c:\foo;c:\bar, sep=";")` - prepend or append entries joined by a
+///   single character separator other than `:`. The entries need not be paths.
+/// - `set(http://proxy:8080)` - set the variable to the value, which is not treated as a list
+/// - `default(vim)` - set the variable to the value, should it be unset (or empty)
+/// - `unset()` - remove the variable from the environment
+///
+/// Variables (eg `{root}`) are replaced by their values. Within `set`, `default` and lists with a separator,
+/// a backslash escapes the following character, such that it is taken literally (eg `\)`, `\,`, `\{`, `\\`).
+///
 /// # Example
 ///
 /// ```
@@ -83,6 +97,11 @@ pub fn parse_all_paths_with_provider<'a>(provider: Rc<RefCell<BasicVarProvider>>
     //let provider = provider.clone();
     move |s: &'a str| {
         alt((
+            parse_unset,
+            parse_set_with_provider(Rc::clone(&provider)),
+            parse_default_with_provider(Rc::clone(&provider)),
+            parse_separated_with_provider(Rc::clone(&provider), "append", PathMode::Append),
+            parse_separated_with_provider(Rc::clone(&provider), "prepend", PathMode::Prepend),
             parse_append_paths_with_provider(Rc::clone(&provider)), 
             parse_prepend_paths_with_provider(Rc::clone(&provider)),
             parse_exact_paths_with_provider(Rc::clone(&provider))
@@ -119,9 +138,29 @@ pub fn parse_all_paths_with_provider<'a>(provider: Rc<RefCell<BasicVarProvider>>
 /// ])));
 /// # }
 pub fn parse_consuming_all_paths_with_provider(provider: Rc<RefCell<BasicVarProvider>>, s: &str) 
-    //-> PNResult<&'a str, PathMode> 
-    -> PNCompleteResult<&str, PathMode>
+    -> Result<PathMode, PesError>
 {
+    // report every unknown variable up front, rather than the first the parser happens upon
+    let mut unknown = Vec::new();
+    {
+        let provider = provider.borrow();
+        for token in referenced_variables(s) {
+            let variable = match token {
+                PathToken::Variable(name) if provider.get(name).is_none() => format!("{{{}}}", name),
+                PathToken::EnvVariable(name) if provider.env(name).is_none() => format!("${}", name),
+                _ => continue,
+            };
+            if !unknown.contains(&variable) {
+                unknown.push(variable);
+            }
+        }
+    }
+    if !unknown.is_empty() {
+        return Err(PesError::UnknownVariables {
+            expression: s.trim().to_string(),
+            variables: unknown,
+        });
+    }
     let (_, result) = all_consuming(
         ws( // drop surrounding whitespace
             parse_all_paths_with_provider(provider)
@@ -132,6 +171,27 @@ pub fn parse_consuming_all_paths_with_provider(provider: Rc<RefCell<BasicVarProv
 }
 
 
+/// Retrieve the variables referenced by an environment expression, in order of appearance, as
+/// `PathToken::Variable` (eg `{root}`, `{maya.root}`) and `PathToken::EnvVariable` (eg `$HOME`, `${HOME}`).
+/// Escaped references (eg `\{root\}`, `\$HOME`) are skipped.
+///
+/// # Example
+/// ```
+/// # use peslib::parser::referenced_variables;
+/// # use peslib::env::PathToken;
+/// let variables = referenced_variables("prepend({root}/bin:${HOME}/bin:$PATH)");
+/// assert_eq!(variables, vec![PathToken::Variable("root"), PathToken::EnvVariable("HOME"), PathToken::EnvVariable("PATH")]);
+/// ```
+pub fn referenced_variables(s: &str) -> Vec<PathToken<'_>> {
+    let result: PNResult<&str, Vec<Option<PathToken>>> = many0(alt((
+        map(parse_escaped_char, |_| None),
+        map(parse_var, Some),
+        map(parse_env_var, Some),
+        map(anychar, |_| None),
+    )))(s);
+    result.map(|(_, tokens)| tokens.into_iter().flatten().collect()).unwrap_or_default()
+}
+
 /// Given a string representing a semantic version range - return a Range of SemanticVersion
 /// 
 /// # Example
@@ -148,9 +208,30 @@ pub fn parse_consuming_all_paths_with_provider(provider: Rc<RefCell<BasicVarProv
 /// );
 /// # }
 /// ```
+///
+/// # Grammar
+/// A range is made up of one or more terms, separated by `|`, and matches a version matched by any of them.
+/// Each term is a constraint, optionally followed by exclusions, each of which is a `!` followed by 
+/// a constraint (eg `1+<2!1.4.3`). A term consisting solely of exclusions matches any version
+/// outside of them (eg `!1.4.3`). The following constraints are supported:
+///
+/// - `1.2.3` - exactly the version
+/// - `1.2.3+<2` - from the first version, up to but excluding the second
+/// - `^1.2` - any version matching the supplied precision (eg `>=1.2.0, <1.3.0`)
+/// - `~1.2.3` - from the version, up to but excluding the next minor release (eg `~1.2.3` is `>=1.2.3, <1.3.0`, and `~1` is `>=1.0.0, <2.0.0`)
+/// - `1.2.*` - any version whose leading components match (`*` matches any version)
+/// - `>=1.2`, `>1.2`, `<1.2`, `<=1.2` - comparisons, which honor the precision of the version. (eg `>1.2` is `>=1.3.0`, and `<=1.2` is `<1.3.0`)
+///
+/// As pre-releases precede their release, an exclusive upper bound admits the pre-releases of the bound. `<1.2.3`
+/// matches `1.2.3-alpha.1`, and `<=1.2` (`<1.3.0`) and `1+<2` match `1.3.0-beta` and `2.0.0-rc` respectively.
+/// Pre-releases are only considered by a solve which includes them (eg `pes env -p`), however.
 pub fn parse_semver_range(s: &str) -> PNResult<&str, Range<SemanticVersion>> {
-        alt((parse_semver_carrot, parse_semver_between, parse_semver_exact)) //,
-    (s)
+    let (leftover, terms) = separated_list1(
+        delimited(many0(tag(" ")), tag("|"), many0(tag(" "))),
+        parse_semver_term
+    )(s)?;
+    let range = terms.iter().fold(Range::none(), |acc, term| acc.union(term));
+    Ok((leftover, range))
 }
 
 /// Given a str representing a semantic version range, return a `Range<SemanticVersion>` or an error
@@ -255,6 +336,68 @@ pub fn parse_consuming_package_range(input: &str) -> Result<(&str, Range<Semanti
     Ok(result)
 }
 
+/// Given an input str representing a weak requirement upon a package, parse and return the package name and
+/// semantic version range. A weak requirement is a package range prefixed with a tilde (eg ~maya-2019). It 
+/// constrains the version of the package, should the package be in the dependency closure, without pulling 
+/// the package into the closure.
+///
+/// # Example
+/// ```
+/// # use peslib::parser::parse_weak_package_range;
+/// # use pubgrub::{range::Range};
+/// # use peslib::{SemanticVersion, ReleaseType};
+/// # fn main()  {
+/// let range = parse_weak_package_range("~maya-1.2.3");
+/// assert_eq!(range, Ok(("",("maya", Range::exact(SemanticVersion::new(1,2,3,ReleaseType::Release))))));
+/// # }
+/// ```
+pub fn parse_weak_package_range(input: &str) -> PNResult<&str, (&str, Range<SemanticVersion>)> {
+    preceded(tag("~"), parse_package_range)(input)
+}
+
+/// Wraps ```parse_weak_package_range```, ensuring that the wrapped parser completely consumes the input
+pub fn parse_consuming_weak_package_range(input: &str) -> Result<(&str, Range<SemanticVersion>), PesError> {
+    let (_,result) = 
+        all_consuming(
+            ws(
+                parse_weak_package_range
+            )
+        )(input).map_err(|e| PesError::ParsingFailure(format!("{:?}", e)) )?;
+    Ok(result)
+}
+
+/// Given an input str representing a constraint supplied to a solve - a package range, which is weak should it
+/// be prefixed with a tilde (eg maya-2019+<2020 or ~python-3) - parse and return whether the constraint is weak,
+/// the package name, and the version range as written, should one be supplied. The range is validated, but
+/// returned as text so that it may be recorded verbatim (see `LockConstraint`).
+///
+/// # Example
+/// ```
+/// # use peslib::parser::parse_consuming_constraint;
+/// # fn main()  {
+/// assert_eq!(parse_consuming_constraint("~maya-2019+<2020").unwrap(), (true, "maya", Some("2019+<2020")));
+/// assert_eq!(parse_consuming_constraint("maya").unwrap(), (false, "maya", None));
+/// # }
+/// ```
+pub fn parse_consuming_constraint(input: &str) -> Result<(bool, &str, Option<&str>), PesError> {
+    let (_, (weak, (name, range))) = all_consuming(delimited(
+        multispace0,
+        pair(
+            map(opt(tag("~")), |tilde: Option<&str>| tilde.is_some()),
+            alt((
+                map(
+                    separated_pair(alphaword_many0_underscore_word, tag("-"), recognize(parse_semver_range)),
+                    |(name, range)| (name, Some(range)),
+                ),
+                map(alphaword_many0_underscore_word, |name| (name, None)),
+            )),
+        ),
+        multispace0,
+    ))(input)
+    .map_err(|e| PesError::ParsingFailure(format!("parse_consuming_constraint {:?}", e)))?;
+    Ok((weak, name, range))
+}
+
 /// Wraps ```parse_semver```, ensuring that it completely consumes the input, and simplifies the 
 /// return signature. Failure to consume the input results in an error.
 pub fn parse_consuming_semver(input: &str) -> Result<SemanticVersion, PesError> {
@@ -281,8 +424,20 @@ fn parse_prerelease(s: &str) -> PNResult<&str, ReleaseType> {
 }
 
 fn parse_semver(s: &str) -> PNResult<&str, SemanticVersion> {
-    let results = alt((  parse_semver_prerelease, parse_semver_release))(s)?;
-    Ok(results)
+    let (leftover, (semver, build)) = pair(alt((  parse_semver_prerelease, parse_semver_release)), opt(parse_build_metadata))(s)?;
+    match build {
+        Some(build) => Ok((leftover, semver.with_build(build))),
+        None => Ok((leftover, semver)),
+    }
+}
+
+// Given a string starting with a '+' followed by period delimited alphanumeric identifiers (eg +build.77), 
+// return the identifiers. Note that this does not match the '+<' of a range.
+fn parse_build_metadata(s: &str) -> PNResult<&str, &str> {
+    preceded(
+        tag("+"), 
+        recognize(separated_list1(tag("."), take_while1(|c: char| c.is_ascii_alphanumeric() || c == '-')))
+    )(s)
 }
 
 // Given a string that represents a semantic version, that is an unsigned int,  followed by 
@@ -299,18 +454,132 @@ fn parse_semver_release(s: &str) -> PNResult<&str, SemanticVersion> {
     Ok((leftover,semver))
 }
 
+// Given a string that represents a pre-release semantic version, that is a release, followed by a '-', a 
+// prerelease type, and optionally a period delimited pre-release number (eg 1.2.3-beta.2), return a 
+// SemanticVersion instance
 fn parse_semver_prerelease(s: &str) -> PNResult<&str, SemanticVersion> {
-    let (leftover,(first, rest, release_type)) = tuple((digit1, many_m_n(0, 2, preceded(tag("."), digit1)),preceded(tag("-"), parse_prerelease) ))(s)?;
+    let (leftover,(first, rest, release_type, number)) = tuple((
+        digit1, 
+        many_m_n(0, 2, preceded(tag("."), digit1)),
+        preceded(tag("-"), parse_prerelease), 
+        opt(preceded(tag("."), digit1)),
+    ))(s)?;
     let semver = SemanticVersion::new(
         first.parse::<u32>().unwrap(),
         rest.get(0).unwrap_or(&"0").parse::<u32>().unwrap(),
         rest.get(1).unwrap_or(&"0").parse::<u32>().unwrap(),
        release_type
     );
+    let semver = match number {
+        Some(number) => semver.with_pre_release(number.parse::<u32>().map_err(|e| PesError::InvalidVersion(e.to_string()))?),
+        None => semver,
+    };
 
     Ok((leftover,semver))
 }
 
+// Given a str representing a term of a range - that is a constraint followed by zero or more exclusions, or 
+// one or more exclusions on their own - return the constraint less the exclusions
+fn parse_semver_term(s: &str) -> PNResult<&str, Range<SemanticVersion>> {
+    let (leftover, (range, exclusions)) = alt((
+        pair(parse_semver_constraint, many0(parse_semver_exclusion)),
+        map(many1(parse_semver_exclusion), |exclusions| (Range::any(), exclusions))
+    ))(s)?;
+    let range = exclusions.iter().fold(range, |acc, exclusion| acc.intersection(&exclusion.negate()));
+    Ok((leftover, range))
+}
+
+// Given a str representing a '!' followed by a constraint, return the constraint
+fn parse_semver_exclusion(s: &str) -> PNResult<&str, Range<SemanticVersion>> {
+    preceded(tag("!"), parse_semver_constraint)(s)
+}
+
+// Given a str representing a single constraint, return its range. Note that the ordering is significant, as 
+// the wildcard and between parsers must be attempted before the exact parser, which would otherwise 
+// match their leading version
+fn parse_semver_constraint(s: &str) -> PNResult<&str, Range<SemanticVersion>> {
+    alt((
+        parse_semver_carrot, 
+        parse_semver_tilde, 
+        parse_semver_comparison, 
+        parse_semver_wildcard, 
+        parse_semver_between, 
+        parse_semver_exact
+    ))(s)
+}
+
+// Given a str representing a semantic version, return the SemanticVersion, along with its precision. That is, the 
+// number of components of the version which were supplied. A pre-release version is considered to be fully precise.
+fn parse_semver_with_precision(s: &str) -> PNResult<&str, (SemanticVersion, usize)> {
+    let (_, (_, rest)) = tuple((digit1, many_m_n(0, 2, preceded(tag("."), digit1))))(s)?;
+    let (leftover, semver) = parse_semver(s)?;
+    let precision = if semver.release_type == ReleaseType::Release { rest.len() + 1 } else { 3 };
+    Ok((leftover, (semver, precision)))
+}
+
+// Given a SemanticVersion and its precision, return the lowest version which does not match the 
+// version at that precision. (eg 1.2 -> 1.3.0)
+fn next_at_precision(semver: &SemanticVersion, precision: usize) -> SemanticVersion {
+    match precision {
+        1 => SemanticVersion::new(semver.major + 1, 0, 0, ReleaseType::Release),
+        2 => SemanticVersion::new(semver.major, semver.minor + 1, 0, ReleaseType::Release),
+        _ => semver.next(),
+    }
+}
+
+// Given a str representing a comparison operator followed by a semantic version (eg >=1.2.3), return the
+// corresponding Range
+fn parse_semver_comparison(s: &str) -> PNResult<&str, Range<SemanticVersion>> {
+    let (leftover, (op, (semver, precision))) = pair(
+        alt((tag(">="), tag("<="), tag(">"), tag("<"))), 
+        preceded(many0(tag(" ")), parse_semver_with_precision)
+    )(s)?;
+    let range = match op {
+        ">=" => Range::higher_than(semver),
+        ">" => Range::higher_than(next_at_precision(&semver, precision)),
+        "<" => Range::strictly_lower_than(semver),
+        _ => Range::strictly_lower_than(next_at_precision(&semver, precision)),
+    };
+    Ok((leftover, range))
+}
+
+// Given a str reference starting with a '~' followed by a semantic version, return a Range from the version up to,
+// but excluding, the next minor release. Should only a major version be supplied, the range extends to the next 
+// major release.
+fn parse_semver_tilde(s: &str) -> PNResult<&str, Range<SemanticVersion>> {
+    let (leftover, (semver, precision)) = preceded(tag("~"), parse_semver_with_precision)(s)?;
+    let upper = next_at_precision(&semver, precision.min(2));
+    Ok((leftover, Range::between(semver, upper)))
+}
+
+// Given a str reference representing a wildcard version (eg 1.2.*, 1.*, or *), return a Range matching any 
+// version whose leading components match those supplied
+fn parse_semver_wildcard(s: &str) -> PNResult<&str, Range<SemanticVersion>> {
+    let (leftover, components) = alt((
+        map(tag("*"), |_| Vec::new()),
+        map(
+            tuple((digit1, many_m_n(0, 1, preceded(tag("."), digit1)), tag(".*"))), 
+            |(first, rest, _)| { let mut components = vec![first]; components.extend(rest); components }
+        )
+    ))(s)?;
+    let components = components
+        .iter()
+        .map(|c| c.parse::<u32>().map_err(|e| PesError::InvalidVersion(e.to_string())))
+        .collect::<Result<Vec<u32>, PesError>>()?;
+    let range = match components.as_slice() {
+        [] => Range::any(),
+        [major] => Range::between(
+            SemanticVersion::new(*major, 0, 0, ReleaseType::Release), 
+            SemanticVersion::new(major + 1, 0, 0, ReleaseType::Release)
+        ),
+        [major, minor, ..] => Range::between(
+            SemanticVersion::new(*major, *minor, 0, ReleaseType::Release), 
+            SemanticVersion::new(*major, minor + 1, 0, ReleaseType::Release)
+        ),
+    };
+    Ok((leftover, range))
+}
+
 // Given a string representing two semantic versions separated by '+<', return a Range::between the first and second
 // SemanticVersion instances
 fn parse_semver_between(s: &str) -> PNResult<&str, Range<SemanticVersion>> {
@@ -353,12 +622,23 @@ fn parse_semver_exact(s: &str) -> PNResult<&str, Range<SemanticVersion>> {
 //   ENV PARSING   //
 //-----------------//
 
-#[allow(dead_code)] // not really dead code. it is used in a subparser
+// the name of a variable, optionally qualified by a package (eg `maya.root`)
+fn parse_var_name(s: &str) -> PNResult<&str, &str> {
+    recognize(pair(alphaword_many0_underscore_word, opt(pair(tag("."), alphaword_many0_underscore_word))))(s)
+}
+
 fn parse_var<'a>(s: &'a str) -> PNResult<&str, PathToken<'a>> {
-    let (leftover, variable) = delimited(tag("{"),alphaword_many0_underscore_word, tag("}"))(s)?;
+    let (leftover, variable) = delimited(tag("{"), parse_var_name, tag("}"))(s)?;
     Ok((leftover, PathToken::Variable(variable)))
 }
 
+// a reference to an environment variable, either `$NAME` or `${NAME}`
+fn parse_env_var<'a>(s: &'a str) -> PNResult<&'a str, PathToken<'a>> {
+    let name = |s| recognize(pair(alt((alpha1, tag("_"))), many0(alt((alphanumeric1, tag("_"))))))(s);
+    let (leftover, variable) = alt((delimited(tag("${"), name, tag("}")), preceded(tag("$"), name)))(s)?;
+    Ok((leftover, PathToken::EnvVariable(variable)))
+}
+
 fn parse_relpath<'a>(s: &'a str) -> PNResult<&str, PathToken<'a>> {
     let (leftover, relpath) = 
     recognize(
@@ -390,7 +670,7 @@ fn parse_var_with_provider<'a>(provider: Rc<RefCell<BasicVarProvider>>)
         let (leftover, variable) = 
             delimited(
                 tag("{"),
-                alphaword_many0_underscore_word, 
+                parse_var_name, 
                 // we eat a slash if it follows the closing brace, as it interferes with subsequent path parsing
                 alt((
                     tag("}/"),
@@ -403,11 +683,32 @@ fn parse_var_with_provider<'a>(provider: Rc<RefCell<BasicVarProvider>>)
     }
 }
 
+// given a provider to resolve environment variables, parse a reference to one into its value
+fn parse_env_var_with_provider<'a>(provider: Rc<RefCell<BasicVarProvider>>) 
+-> impl Fn(&'a str) -> PNResult<&'a str, PathToken<'a>> {
+    move |s: &'a str| {
+        // as with variables, we eat a slash following the reference
+        let (leftover, token) = terminated(parse_env_var, opt(tag("/")))(s)?;
+        let variable = match token {
+            PathToken::EnvVariable(variable) => variable,
+            _ => unreachable!(),
+        };
+        let provider = provider.borrow();
+        let result = provider.env(variable).ok_or_else(|| PesNomError::<&str>::InvalidKey(format!("${}", variable)))?;
+        Ok((leftover, PathToken::OwnedVariable(result.to_string())))
+    }
+}
+
 // given a provider to resolve path variables, 
 fn parse_path_with_provider<'a>(provider: Rc<RefCell<BasicVarProvider>>) -> impl Fn(&'a str) -> PNResult<&'a str, PathBuf> {
     //let provider = provider.clone();
     move |s: &'a str| {
-        let (leftover, path_tokens) = many1(alt((parse_abspath, parse_relpath, parse_var_with_provider(Rc::clone(&provider) ))))(s)?;
+        let (leftover, path_tokens) = many1(alt((
+            parse_abspath, 
+            parse_relpath, 
+            parse_var_with_provider(Rc::clone(&provider)), 
+            parse_env_var_with_provider(Rc::clone(&provider))
+        )))(s)?;
         let mut retpath = PathBuf::new();
         
         for token in path_tokens {
@@ -454,6 +755,123 @@ fn parse_prepend_paths_with_provider<'a>(provider: Rc<RefCell<BasicVarProvider>>
     }
 }
 
+// A fragment of the text of an environment expression. Only the unescaped text written in the expression
+// is split into entries. Escaped characters and the values of variables are taken literally.
+#[derive(Debug)]
+enum TextFragment {
+    Text(String),
+    Literal(String),
+}
+
+// a character escaped by a backslash
+fn parse_escaped_char(s: &str) -> PNResult<&str, char> {
+    preceded(char('\\'), anychar)(s)
+}
+
+// given a provider to resolve variables, parse a variable or a reference to an environment variable into its
+// value. Unlike parse_var_with_provider, any trailing slash is retained
+fn parse_text_var_with_provider<'a>(provider: Rc<RefCell<BasicVarProvider>>) -> impl Fn(&'a str) -> PNResult<&'a str, String> {
+    move |s: &'a str| {
+        let (leftover, token) = alt((parse_var, parse_env_var))(s)?;
+        let provider = provider.borrow();
+        let result = match token {
+            PathToken::Variable(variable) => provider.get(variable).ok_or_else(|| PesNomError::<&str>::InvalidKey(variable.to_string()))?,
+            PathToken::EnvVariable(variable) => provider.env(variable).ok_or_else(|| PesNomError::<&str>::InvalidKey(format!("${}", variable)))?,
+            _ => unreachable!(),
+        };
+        Ok((leftover, result.to_string()))
+    }
+}
+
+// given a provider to resolve variables, parse text up to, but excluding, the first unescaped character
+// in `terminators`, which must include the backslash, the opening brace and the dollar sign
+fn parse_text_with_provider<'a>(provider: Rc<RefCell<BasicVarProvider>>, terminators: &'static str) 
+    -> impl Fn(&'a str) -> PNResult<&'a str, Vec<TextFragment>> 
+{
+    move |s: &'a str| {
+        many0(alt((
+            map(parse_escaped_char, |c| TextFragment::Literal(c.to_string())),
+            map(parse_text_var_with_provider(Rc::clone(&provider)), TextFragment::Literal),
+            map(is_not(terminators), |text: &str| TextFragment::Text(text.to_string())),
+            // a `$` which does not reference an environment variable is taken literally
+            map(tag("$"), |text: &str| TextFragment::Text(text.to_string())),
+        )))(s)
+    }
+}
+
+// concatenate text fragments into a single value
+fn join_fragments(fragments: Vec<TextFragment>) -> String {
+    fragments.into_iter().map(|fragment| match fragment {
+        TextFragment::Text(text) | TextFragment::Literal(text) => text,
+    }).collect()
+}
+
+// split text fragments into entries at each unescaped separator, dropping empty entries
+fn split_fragments(fragments: Vec<TextFragment>, separator: char) -> VecDeque<String> {
+    let mut entries = vec![String::new()];
+    for fragment in fragments {
+        match fragment {
+            TextFragment::Literal(text) => entries.last_mut().unwrap().push_str(&text),
+            TextFragment::Text(text) => {
+                let mut pieces = text.split(separator);
+                if let Some(piece) = pieces.next() {
+                    entries.last_mut().unwrap().push_str(piece);
+                }
+                entries.extend(pieces.map(str::to_string));
+            }
+        }
+    }
+    entries.into_iter().filter(|entry| !entry.is_empty()).collect()
+}
+
+// parse the separator clause of a list, eg `sep=";"`
+fn parse_separator(s: &str) -> PNResult<&str, char> {
+    preceded(pair(tag("sep"), ws(tag("="))), delimited(char('"'), anychar, char('"')))(s)
+}
+
+fn parse_unset(s: &str) -> PNResult<&str, PathMode> {
+    let (leftover, _) = tuple((tag("unset("), multispace0, tag(")")))(s)?;
+    Ok((leftover, PathMode::Unset))
+}
+
+// given a provider to resolve variables, 
+fn parse_set_with_provider<'a>(provider: Rc<RefCell<BasicVarProvider>>) -> impl Fn(&'a str) -> PNResult<&'a str, PathMode> {
+    move |s: &'a str| {
+        let (leftover, fragments) = delimited(tag("set("), parse_text_with_provider(Rc::clone(&provider), ")\\{$"), tag(")"))(s)?;
+        Ok((leftover, PathMode::Set(join_fragments(fragments))))
+    }
+}
+
+// given a provider to resolve variables, 
+fn parse_default_with_provider<'a>(provider: Rc<RefCell<BasicVarProvider>>) -> impl Fn(&'a str) -> PNResult<&'a str, PathMode> {
+    move |s: &'a str| {
+        let (leftover, fragments) = delimited(tag("default("), parse_text_with_provider(Rc::clone(&provider), ")\\{$"), tag(")"))(s)?;
+        Ok((leftover, PathMode::Default(join_fragments(fragments))))
+    }
+}
+
+// given a provider to resolve variables, parse a list whose entries are joined by a custom separator, 
+// eg `append(-O2 -g, sep=" ")`, constructing the PathMode via `mode`
+fn parse_separated_with_provider<'a>(
+    provider: Rc<RefCell<BasicVarProvider>>, 
+    keyword: &'static str, 
+    mode: fn(VecDeque<String>) -> PathMode
+) -> impl Fn(&'a str) -> PNResult<&'a str, PathMode> {
+    move |s: &'a str| {
+        let (leftover, (fragments, separator)) = delimited(
+            pair(tag(keyword), tag("(")),
+            separated_pair(
+                parse_text_with_provider(Rc::clone(&provider), ",)\\{$"), 
+                tag(","), 
+                ws(parse_separator)
+            ),
+            tag(")")
+        )(s)?;
+        let entries = split_fragments(fragments, separator);
+        Ok((leftover, PathMode::Separated(Box::new(mode(entries)), separator.to_string())))
+    }
+}
+
 fn parse_exact_paths_with_provider<'a>(provider: Rc<RefCell<BasicVarProvider>>) -> impl Fn(&'a str) -> PNResult<&'a str, PathMode> {
     move |s: &'a str| {
         let (leftover, result) =  parse_paths_with_provider(Rc::clone(&provider) )(s)?;
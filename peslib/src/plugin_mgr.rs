@@ -2,8 +2,9 @@ use crate::{aliases::DistPathMap, PesError, PackageRepository, traits::Repositor
 
 use libloading::Library;
 use log::info;
-use pes_core::{ RepoFinderService, ManifestFinderService };
+use pes_core::{ BaseEnvPolicy, BaseEnvService, RepoFinderService, ManifestFinderService };
 use std::{
+    collections::HashMap,
     path::PathBuf,
     rc::Rc,
 };
@@ -14,7 +15,9 @@ use crate::{SemanticVersion, ReleaseType};
 #[derive(Debug)]
 pub struct PluginMgr {
     repo_finder: Library,
-    manifest_finder: Library
+    manifest_finder: Library,
+    /// The base_env plugin is optional. See `base_env_policy`
+    base_env: Option<Library>,
  }
 
 
@@ -33,7 +36,8 @@ impl PluginMgr {
         info!("building pluginmgr");
         let repo_finder = Self::new_repo_finder_service()?;
         let manifest_finder = Self::new_manifest_finder_service()?;
-        Ok(Self { repo_finder, manifest_finder })
+        let base_env = Self::new_base_env_service()?;
+        Ok(Self { repo_finder, manifest_finder, base_env })
     }
 
     // in the test version, we build the path to the plugins using target/<debug|release>/
@@ -89,6 +93,24 @@ impl PluginMgr {
         Ok(lib)
     }
 
+    // the base_env plugin postdates the finder plugins, and so may not be installed
+    fn new_base_env_service() -> Result<Option<Library>, PesError> {
+        #[cfg(target_os = "macos")]
+        let path = Self::plugin_dir("libbase_env.dylib")?;
+
+        #[cfg(target_os = "linux")]
+        let path = Self::plugin_dir("libbase_env.so")?;
+
+        if !path.exists() {
+            info!("BaseEnv Library not found at {:?}", &path);
+            return Ok(None);
+        }
+        info!("Loading BaseEnv Library: {:?}", &path);
+        let lib = unsafe { libloading::Library::new(path)? };
+
+        Ok(Some(lib))
+    }
+
     /// retrieve a manifest given a distribution
     pub fn manifest_path_from_distribution<D: Into<PathBuf>>(&self, distribution: D) -> PathBuf {
         let new_service: libloading::Symbol<extern "Rust" fn() -> Box<dyn ManifestFinderService>> =
@@ -107,6 +129,26 @@ impl PluginMgr {
         repo
     }
 
+    /// retrieve the policy determining which variables of the calling environment are retained in
+    /// the base environment. The policy is provided by the base_env plugin. Should the plugin not be
+    /// installed, the default policy of `BaseEnvPolicy::from_env` is used.
+    pub fn base_env_policy(&self) -> Result<BaseEnvPolicy, PesError> {
+        let library = match &self.base_env {
+            Some(library) => library,
+            None => return BaseEnvPolicy::from_env(),
+        };
+        let new_service: libloading::Symbol<extern "Rust" fn() -> Box<dyn BaseEnvService>> =
+            unsafe { library.get(b"new_base_env_service").expect("unable to get base env service from plugin") };
+        let base_env = new_service();
+        base_env.base_env_policy()
+    }
+
+    /// retrieve the base environment, composed of the variables of the calling environment retained
+    /// by the base env policy
+    pub fn base_env(&self) -> Result<HashMap<String, String>, PesError> {
+        Ok(self.base_env_policy()?.apply(std::env::vars()))
+    }
+
     /// retrieve a map that maps distributions to paths
     pub fn get_distpathmap(&self, min_release_type: ReleaseType, distributions_override: Vec<(String, SemanticVersion)>) -> Result<DistPathMap, PesError> {
         let repos = PackageRepository::from_plugin(self)?;
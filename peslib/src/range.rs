@@ -1,8 +1,11 @@
-//! Provides an implementation of the `FrmStr` trait for `Range<SematicVersion>`
+//! Provides an implementation of the `FrmStr` trait for `Range<SematicVersion>`, along with
+//! a means of displaying a `Range<SemanticVersion>` using the pes range syntax
+use std::fmt;
+
 use crate::PesError;
 use crate::parser::parse_consuming_semver_range;
 use crate::traits::FrmStr;
-use crate::SemanticVersion;
+use crate::{ReleaseType, SemanticVersion};
 
 use pubgrub::range::Range;
 //use pubgrub::version::SemanticVersion;
@@ -17,7 +20,125 @@ impl FrmStr for Range<SemanticVersion> {
     }
 }
 
+/// Return the intervals which make up the range, in ascending order. Each interval is a tuple
+/// of its inclusive lower bound and its exclusive upper bound, with None indicating that the
+/// interval is unbounded. Returns None should the intervals be unavailable.
+pub fn range_intervals(range: &SemVerRange) -> Option<Vec<(SemanticVersion, Option<SemanticVersion>)>> {
+    // pubgrub does not expose the segments of a range, however it serializes them transparently
+    // as a sequence of intervals, so we take the long way around. As that layout is not part of
+    // pubgrub's api, callers must cope with its absence
+    serde_yaml::to_value(range)
+        .and_then(serde_yaml::from_value)
+        .ok()
+}
+
+/// Displays a `Range<SemanticVersion>` in canonical pes range syntax, such that the
+/// displayed range may be parsed back into an identical range. Construct via `display_range`.
+///
+/// A given range is always displayed identically, regardless of how it was constructed.
+/// Each interval of the range is displayed using the most concise of
+/// - `1.2.3` - an interval containing a single version
+/// - `1.*` or `1.2.*` - an interval spanning a major or minor release
+/// - `~1.2.3` - an interval from a version up to the next minor release
+/// - `>=1.2.3` - an interval without an upper bound
+/// - `<1.2.3` - an interval without a lower bound
+/// - `1.2.3+<2.0.0` - an interval with both bounds
+///
+/// Intervals separated by a single version are displayed as a single interval with an
+/// exclusion (eg `1.*!1.4.3`, or `!1.4.3` for any version other than 1.4.3), and the
+/// remaining intervals are separated by `|`. A range matching any version is displayed as
+/// `*`, while an empty range is displayed as `!*`. Should the intervals of the range be unavailable
+/// (see `range_intervals`), the range's `Debug` representation is displayed instead.
+#[derive(Debug, Clone, Copy)]
+pub struct RangeDisplay<'a>(&'a SemVerRange);
+
+/// Return a wrapper around the range which displays it in canonical pes range syntax
+///
+/// # Example
+/// ```
+/// # use peslib::{display_range, SemVerRange, traits::FrmStr};
+/// let range = SemVerRange::frm_str(">=1.2|1+<1.2!1.1.3").unwrap();
+/// assert_eq!(display_range(&range).to_string(), ">=1.0.0!1.1.3");
+/// ```
+pub fn display_range(range: &SemVerRange) -> RangeDisplay<'_> {
+    RangeDisplay(range)
+}
+
+// a contiguous span of versions, less zero or more single versions within it
+struct Term {
+    start: SemanticVersion,
+    end: Option<SemanticVersion>,
+    exclusions: Vec<SemanticVersion>,
+}
+
+impl Term {
+    // write the span of the term, ignoring its exclusions
+    fn fmt_span(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let start = &self.start;
+        let lowest = SemanticVersion::lowest();
+        let end = match &self.end {
+            None if start == &lowest => return write!(f, "*"),
+            None => return write!(f, ">={}", start),
+            Some(end) => end,
+        };
+        let next_major = SemanticVersion::new(start.major + 1, 0, 0, ReleaseType::Release);
+        let next_minor = SemanticVersion::new(start.major, start.minor + 1, 0, ReleaseType::Release);
+        let is_release = start.release_type == ReleaseType::Release;
+        if end == &start.next() {
+            write!(f, "{}", start)
+        } else if start == &lowest {
+            write!(f, "<{}", end)
+        } else if is_release && start.minor == 0 && start.patch == 0 && end == &next_major {
+            write!(f, "{}.*", start.major)
+        } else if is_release && start.patch == 0 && end == &next_minor {
+            write!(f, "{}.{}.*", start.major, start.minor)
+        } else if is_release && end == &next_minor {
+            write!(f, "~{}", start)
+        } else {
+            write!(f, "{}+<{}", start, end)
+        }
+    }
+}
 
+impl<'a> fmt::Display for RangeDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let intervals = match range_intervals(self.0) {
+            Some(intervals) => intervals,
+            None => return write!(f, "{:?}", self.0),
+        };
+        let mut terms: Vec<Term> = Vec::new();
+        // build metadata does not participate in comparisons, so it is dropped from the bounds
+        let strip = |mut version: SemanticVersion| { version.build = None; version };
+        for (start, end) in intervals {
+            let (start, end) = (strip(start), end.map(strip));
+            match terms.last_mut() {
+                // the previous interval ends a single version short of this one
+                Some(term) if term.end.as_ref().map(|e| e.next()) == Some(start.clone()) => {
+                    let excluded = term.end.take().unwrap();
+                    term.exclusions.push(excluded);
+                    term.end = end;
+                }
+                _ => terms.push(Term { start, end, exclusions: Vec::new() }),
+            }
+        }
+        if terms.is_empty() {
+            return write!(f, "!*");
+        }
+        for (idx, term) in terms.iter().enumerate() {
+            if idx > 0 {
+                write!(f, "|")?;
+            }
+            let is_any = term.end.is_none() && term.start == SemanticVersion::lowest();
+            if !is_any || term.exclusions.is_empty() {
+                term.fmt_span(f)?;
+            }
+            for exclusion in &term.exclusions {
+                write!(f, "!{}", exclusion)?;
+            }
+        }
+        Ok(())
+    }
+}
 
 #[cfg(test)]
 #[path = "./unit_tests/range.rs"]
@@ -0,0 +1,375 @@
+//! A persistent, on-disk index of the distributions within a package repository.
+//!
+//! Walking every package directory and parsing every manifest in a repository is expensive,
+//! particularly when repositories are served over NFS. The `RepositoryIndex` records, for each
+//! distribution, its version, the path to the distribution, and the parsed manifest (and thus
+//! its requires). The index is invalidated by directory and manifest mtimes:
+//!
+//! - when a package directory's mtime changes (eg a version was added or removed), the package's
+//!   version directories are re-listed
+//! - when a version directory's mtime changes, that distribution's manifest is re-read
+//! - when the mtime or size of a distribution's manifest changes (eg it was edited in place, which leaves
+//!   the directory's mtime untouched), the manifest is re-read
+//!
+//! Indices are stored as yaml, one per repository, within a cache directory. See `default_cache_dir`.
+//! As the index stores parsed manifests, an index written by any other version of peslib is discarded
+//! and rebuilt.
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+    str::FromStr,
+    time::SystemTime,
+};
+
+use indexmap::IndexMap;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constants::CACHE_DIR_VARNAME,
+    dependency_provider::absent_version,
+    lock::fnv1a_hash,
+    manifest::PackageManifest,
+    PesError,
+    ReleaseType,
+    Repository,
+    SemanticVersion,
+};
+
+/// The version of peslib which wrote the index. Indices written by a different version are discarded and
+/// rebuilt, as the format of the manifests they store may differ.
+pub const INDEX_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// A single distribution recorded in the index
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IndexedDistribution {
+    /// Version of the distribution
+    pub version: SemanticVersion,
+    /// Path to the root of the distribution
+    pub path: PathBuf,
+    /// Path to the distribution's manifest
+    pub manifest_path: PathBuf,
+    /// mtime of the distribution's directory when the manifest was read
+    modified: Option<SystemTime>,
+    /// mtime and size of the manifest when it was read
+    manifest_stamp: Option<(SystemTime, u64)>,
+    /// The parsed manifest
+    pub manifest: PackageManifest,
+}
+
+impl IndexedDistribution {
+    // determine whether the distribution is up to date, given the current mtime of its directory. Should
+    // either mtime be unavailable, the distribution is considered stale.
+    fn is_fresh(&self, modified: Option<SystemTime>) -> bool {
+        self.modified.is_some()
+            && self.modified == modified
+            && self.manifest_stamp.is_some()
+            && self.manifest_stamp == stamp(&self.manifest_path)
+    }
+}
+
+/// The distributions of a single package, sorted by version
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct PackageIndex {
+    /// mtime of the package directory when it was last listed
+    modified: Option<SystemTime>,
+    distributions: Vec<IndexedDistribution>,
+}
+
+impl PackageIndex {
+    /// Retrieve an iterator over the indexed distributions, in ascending version order
+    pub fn distributions(&self) -> impl Iterator<Item = &IndexedDistribution> {
+        self.distributions.iter()
+    }
+
+    /// Retrieve the indexed distribution for the supplied version, should it exist
+    pub fn distribution(&self, version: &SemanticVersion) -> Option<&IndexedDistribution> {
+        self.distributions.iter().find(|d| &d.version == version)
+    }
+}
+
+/// Index of the distributions within a repository
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RepositoryIndex {
+    /// indices which predate recording the version are read with an empty version, and thus discarded
+    #[serde(default)]
+    pes_version: String,
+    root: PathBuf,
+    packages: IndexMap<String, PackageIndex>,
+    /// tracks whether the index has changed since it was loaded
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl RepositoryIndex {
+    /// Construct a new, empty index for the repository rooted at `root`
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            pes_version: INDEX_VERSION.to_string(),
+            root: root.into(),
+            packages: IndexMap::new(),
+            dirty: true,
+        }
+    }
+
+    /// Load the index for the repository rooted at `root` from `cache_file`. If the cache file does not exist,
+    /// cannot be read, was written by a different version of peslib, or belongs to a different repository, an
+    /// empty index is returned instead.
+    pub fn load<P: Into<PathBuf>, C: AsRef<Path>>(root: P, cache_file: C) -> Self {
+        let root = root.into();
+        let cache_file = cache_file.as_ref();
+        if !cache_file.exists() {
+            return Self::new(root);
+        }
+        let index = std::fs::read_to_string(cache_file)
+            .map_err(PesError::from)
+            .and_then(|contents| Ok(serde_yaml::from_str::<Self>(&contents)?));
+        match index {
+            Ok(index) if index.pes_version == INDEX_VERSION && index.root == root => {
+                debug!("loaded repository index {:?}", cache_file);
+                index
+            }
+            Ok(_) => {
+                debug!("discarding stale repository index {:?}", cache_file);
+                Self::new(root)
+            }
+            Err(e) => {
+                warn!("unable to read repository index {:?}: {}. Rebuilding", cache_file, e);
+                Self::new(root)
+            }
+        }
+    }
+
+    /// Write the index to `cache_file`, assuming it has changed since it was loaded. The index is
+    /// written to a temporary file first and then renamed into place, so that concurrent readers
+    /// never see a partially written index.
+    pub fn save<C: AsRef<Path>>(&mut self, cache_file: C) -> Result<(), PesError> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let cache_file = cache_file.as_ref();
+        if let Some(parent) = cache_file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut tmp_file = cache_file.to_path_buf();
+        tmp_file.set_extension(format!("{}.tmp", std::process::id()));
+        std::fs::write(&tmp_file, serde_yaml::to_string(&self)?)?;
+        std::fs::rename(&tmp_file, cache_file)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Retrieve the root of the indexed repository
+    pub fn root(&self) -> &Path {
+        self.root.as_path()
+    }
+
+    /// Has the index changed since it was loaded or saved?
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Bring the entire index up to date with the repository, adding new packages, refreshing
+    /// stale ones, and dropping packages which no longer exist.
+    pub fn refresh<R: Repository>(&mut self, repository: &R) -> Result<(), PesError> {
+        let mut packages = Vec::new();
+        for entry in self.root.read_dir()? {
+            let path = entry?.path();
+            if path.is_dir() {
+                let name = path
+                    .file_name()
+                    .ok_or_else(|| PesError::InvalidPath(path.clone()))?
+                    .to_string_lossy()
+                    .to_string();
+                packages.push(name);
+            }
+        }
+        let before = self.packages.len();
+        self.packages.retain(|name, _| packages.contains(name));
+        if self.packages.len() != before {
+            self.dirty = true;
+        }
+        for package in packages {
+            self.package(repository, &package)?;
+        }
+        Ok(())
+    }
+
+    /// Retrieve the index for the supplied package, refreshing it first if it is stale. Returns
+    /// None if the package does not exist in the repository.
+    pub fn package<R: Repository>(&mut self, repository: &R, package: &str) -> Result<Option<&PackageIndex>, PesError> {
+        let package_dir = self.root.join(package);
+        if !package_dir.is_dir() {
+            if self.packages.shift_remove(package).is_some() {
+                self.dirty = true;
+            }
+            return Ok(None);
+        }
+        let modified = mtime(&package_dir);
+        // take the cached entry, leaving its slot in place so that the package retains its position
+        let cached = self.packages.get_mut(package).map(std::mem::take).unwrap_or_default();
+        let (refreshed, changed) = if cached.modified.is_some() && cached.modified == modified {
+            Self::refresh_distributions(repository, package, cached)?
+        } else {
+            debug!("package directory {:?} changed. Relisting versions", &package_dir);
+            let mut refreshed = Self::list_distributions(repository, package, &package_dir, cached)?;
+            refreshed.modified = modified;
+            (refreshed, true)
+        };
+        self.dirty |= changed;
+        self.packages.insert(package.to_string(), refreshed);
+        Ok(self.packages.get(package))
+    }
+
+    /// Retrieve an iterator over the indexed (package, distribution) pairs whose release type is at least `min_release_type`,
+    /// or which appear in `distributions_override`. This mirrors the filtering performed by `Repository::manifests`.
+    /// Note that this does not refresh the index. Call `refresh` first.
+    pub fn distributions<'a>(
+        &'a self,
+        min_release_type: ReleaseType,
+        distributions_override: Rc<Vec<(String, SemanticVersion)>>,
+    ) -> impl Iterator<Item = (&'a str, &'a IndexedDistribution)> + 'a {
+        self.packages.iter().flat_map(move |(name, package)| {
+            let distributions_override = Rc::clone(&distributions_override);
+            package
+                .distributions
+                .iter()
+                .filter(move |dist| {
+                    dist.version.release_type >= min_release_type
+                        || distributions_override.iter().any(|(n, v)| n == name && v == &dist.version)
+                })
+                .map(move |dist| (name.as_str(), dist))
+        })
+    }
+
+    // list the version directories for the package, reusing cached distributions whose
+    // directories and manifests have not changed
+    fn list_distributions<R: Repository>(
+        repository: &R,
+        package: &str,
+        package_dir: &Path,
+        cached: PackageIndex,
+    ) -> Result<PackageIndex, PesError> {
+        let mut cached = cached.distributions;
+        let mut distributions = Vec::new();
+        for entry in package_dir.read_dir()? {
+            let path = entry?.path();
+            if !path.is_dir() {
+                continue;
+            }
+            let version_str = path
+                .file_name()
+                .ok_or_else(|| PesError::InvalidPath(path.clone()))?
+                .to_string_lossy()
+                .to_string();
+            let version = SemanticVersion::from_str(version_str.as_str())
+                .map_err(|e| PesError::InvalidVersion(format!("{:?} {}", &path, e)))?;
+            if version == absent_version() {
+                return Err(PesError::ReservedVersion(path));
+            }
+            let modified = mtime(&path);
+            match cached.iter().position(|d| d.version == version) {
+                Some(idx) if cached[idx].is_fresh(modified) => {
+                    distributions.push(cached.swap_remove(idx))
+                }
+                _ => distributions.push(Self::read_distribution(repository, package, version, path, modified)?),
+            }
+        }
+        distributions.sort_by(|a, b| a.version.cmp(&b.version));
+        // build metadata does not participate in precedence, so versions such as 1.0.0 and 1.0.0+build.3
+        // cannot be told apart by the solver
+        if let Some(pair) = distributions.windows(2).find(|pair| pair[0].version == pair[1].version) {
+            return Err(PesError::DuplicateDistribution {
+                package: package.to_string(),
+                first: pair[0].path.clone(),
+                second: pair[1].path.clone(),
+            });
+        }
+        Ok(PackageIndex { modified: None, distributions })
+    }
+
+    // re-read the manifests of any distributions whose directories or manifests have changed
+    fn refresh_distributions<R: Repository>(
+        repository: &R,
+        package: &str,
+        cached: PackageIndex,
+    ) -> Result<(PackageIndex, bool), PesError> {
+        let PackageIndex { modified, distributions } = cached;
+        let mut changed = false;
+        let mut refreshed = Vec::with_capacity(distributions.len());
+        for dist in distributions {
+            let dist_modified = mtime(&dist.path);
+            if dist_modified.is_none() {
+                // the distribution has been removed out from under us
+                changed = true;
+            } else if dist.is_fresh(dist_modified) {
+                refreshed.push(dist);
+            } else {
+                changed = true;
+                refreshed.push(Self::read_distribution(repository, package, dist.version, dist.path, dist_modified)?);
+            }
+        }
+        Ok((PackageIndex { modified, distributions: refreshed }, changed))
+    }
+
+    fn read_distribution<R: Repository>(
+        repository: &R,
+        package: &str,
+        version: SemanticVersion,
+        path: PathBuf,
+        modified: Option<SystemTime>,
+    ) -> Result<IndexedDistribution, PesError> {
+        debug!("indexing {}-{}", package, &version);
+        let manifest_path = repository
+            .manifest(package, version.to_string())
+            .map_err(|e| PesError::PesError(e.to_string()))?;
+        let manifest_path = manifest_path.as_ref().to_path_buf();
+        // stamp the manifest before reading it, so that an edit made while reading is picked up next time
+        let manifest_stamp = stamp(&manifest_path);
+        let manifest = PackageManifest::from_file(&manifest_path)?;
+        Ok(IndexedDistribution { version, path, manifest_path, modified, manifest_stamp, manifest })
+    }
+}
+
+/// Retrieve the directory in which repository indices are stored. This is the value of the
+/// `PES_CACHE_DIR` environment variable, if set, otherwise `$XDG_CACHE_HOME/pes` or `$HOME/.cache/pes`.
+/// Returns None if none of these variables are set, or if `PES_CACHE_DIR` is set to an empty string,
+/// which disables the persistent cache.
+pub fn default_cache_dir() -> Option<PathBuf> {
+    if let Ok(dir) = std::env::var(CACHE_DIR_VARNAME) {
+        return if dir.is_empty() { None } else { Some(PathBuf::from(dir)) };
+    }
+    if let Ok(dir) = std::env::var("XDG_CACHE_HOME") {
+        if !dir.is_empty() {
+            return Some(PathBuf::from(dir).join("pes"));
+        }
+    }
+    std::env::var("HOME").ok().map(|home| PathBuf::from(home).join(".cache").join("pes"))
+}
+
+/// Construct the path to the index file for the repository rooted at `root` within `cache_dir`. The file is named
+/// after the root, sanitized for readability, and a hash of the root, so that roots which sanitize alike (eg /a/b_c
+/// and /a_b/c) do not share an index.
+pub fn cache_file_for<C: AsRef<Path>, R: AsRef<Path>>(cache_dir: C, root: R) -> PathBuf {
+    let root = root.as_ref().to_string_lossy();
+    let name = root
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+        .collect::<String>();
+    cache_dir.as_ref().join(format!("{}-{}.yaml", name, fnv1a_hash(root.as_bytes())))
+}
+
+// retrieve the modification time of the supplied path, if available
+fn mtime(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+// retrieve the modification time and size of the supplied file, if available
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+#[path = "./unit_tests/repository_index.rs"]
+mod unit_tests;
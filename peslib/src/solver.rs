@@ -8,33 +8,42 @@
 // (package + withs) as the solver only allows you to request a single
 // package
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use log::*;
 
 use pubgrub::{
     error::PubGrubError,
-    package::Package,
     range::Range,
-    report::{DefaultStringReporter, Reporter},
-    solver::{resolve, OfflineDependencyProvider},
-    version::{Version},
+    report::{DerivationTree, External},
+    solver::resolve,
+    term::Term,
 };
 
 pub use pubgrub::type_aliases::SelectedDependencies;
 
 use crate::{
-    aliases::{DistMap, SolveResult, DistPathMap}, 
-    constants::ROOT_REQUEST,
+    aliases::{SolveResult, DistPathMap, VariantMap}, 
+    constants::{DEFAULT_TARGET, ROOT_REQUEST},
+    dependency_provider::{absent_version, parse_variant_package, variant_package, variant_version, RepositoryDependencyProvider},
     distribution_range::DistributionRange, 
+    FailureCause,
+    FailureTerm,
+    LockFile,
     manifest::Manifest,
     manifest::PackageManifest, 
-    parser::parse_consuming_package_version,
+    parser::{parse_consuming_package_version, parse_consuming_weak_package_range},
     PesError, 
     PluginMgr,
+    range::{display_range, range_intervals},
+    Relation,
     ReleaseType,PackageRepository,
     Repository, 
+    repository_index::default_cache_dir,
     SemanticVersion, 
+    SolveFailure,
+    TargetPolicy,
+    TransitiveTarget,
 };
 
 
@@ -44,13 +53,36 @@ pub fn perform_solve(
     constraints: &Vec<&str>, 
     include_pre: bool,
 ) -> Result<SolveResult, PesError> {
-    debug!("user supplied constraints: {:?}.", constraints);
+    perform_solve_for_target(plugin_mgr, constraints, DEFAULT_TARGET, include_pre)
+}
+
+/// Given a set of constraints, perform a solve in which the dependencies of every package are calculated
+/// using the supplied target (falling back to `run` for packages which do not define it)
+pub fn perform_solve_for_target(
+    plugin_mgr: &PluginMgr,
+    constraints: &Vec<&str>, 
+    target: &str,
+    include_pre: bool,
+) -> Result<SolveResult, PesError> {
+    perform_pinned_solve_for_target(plugin_mgr, constraints, target, &[], None, include_pre)
+}
+
+/// Given a set of constraints, perform a solve for the target in which each of the supplied `pins` is
+/// held at its version, should the package remain in the closure. This is used to re-solve a lockfile
+/// while changing as little as possible (see `LockFile::pins_for`). Should a `preferences` lockfile be 
+/// supplied, the versions it locks for the target are preferred, where the request allows them.
+pub fn perform_pinned_solve_for_target(
+    plugin_mgr: &PluginMgr,
+    constraints: &Vec<&str>, 
+    target: &str,
+    pins: &[(String, SemanticVersion)],
+    preferences: Option<&LockFile>,
+    include_pre: bool,
+) -> Result<SolveResult, PesError> {
+    debug!("user supplied constraints: {:?} target: {} pins: {:?}", constraints, target, pins);
 
     // construct request from a vector of constraint strings
-    let request = constraints
-        .iter()
-        .map(|x| DistributionRange::from_str(x))
-        .collect::<Result<Vec<_>, PesError>>()?;
+    let request = SolveRequest::from_strs(constraints)?.with_pins(pins);
 
     let repos = PackageRepository::from_plugin(plugin_mgr)?;
     let min_release_type = if include_pre {ReleaseType::Alpha} else {ReleaseType::Release};
@@ -60,12 +92,18 @@ pub fn perform_solve(
         constraints
             .iter()
             .filter_map(|x| parse_consuming_package_version(x).ok())
-            .filter(|(_name, version)| version.release_type < ReleaseType::Release)
             .map(|(name, version)| (name.to_string(), version))
+            .chain(pins.iter().cloned())
+            .filter(|(_name, version)| version.release_type < ReleaseType::Release)
             .collect::<Vec<_>>()
     };
     let dist_overrides = Rc::new(dist_overrides);
-    let mut solver = Solver::new_from_repos(repos, min_release_type, dist_overrides)?;
+    let target_policy = TargetPolicy::new(TransitiveTarget::Target(target.to_string()));
+    let mut solver = Solver::new_from_repos(repos, min_release_type, dist_overrides)?
+        .with_target_policy(target_policy, target);
+    if let Some(lockfile) = preferences {
+        solver = solver.with_lockfile_preferences(lockfile, target);
+    }
     // calculate the solution
     debug!("Calling solver.solve with request {:?}", &request);
     let mut solution = solver.solve(request)?;
@@ -109,20 +147,37 @@ pub fn perform_solve(
     //     }).collect::<Result<(), PesError>>()?; 
     // }
     
-    Ok((distpathmap, solution))
+    Ok((distpathmap, solution, solver.variants().clone()))
 }
 
-/// Generate a solution for the provided distribution and target
+/// Generate a solution for the provided distribution and target. The `target_policy` determines which
+/// target is used to calculate the dependencies of each transitive dependency.
 pub fn perform_solve_for_distribution_and_target(
     plugin_mgr: &PluginMgr,
     distribution: &str,
     target: &str,
+    target_policy: &TargetPolicy,
     // indicate whether or not you wish to include prereleases in the solution space.
     // By default, the function ignores all pre-releases other than potentially 
     // the supplied distribution.
     include_pre: bool
 ) -> Result<SolveResult, PesError> {
-    debug!("distribution: {} target: {}", distribution, target);
+    perform_pinned_solve_for_distribution_and_target(plugin_mgr, distribution, target, target_policy, &[], None, include_pre)
+}
+
+/// Generate a solution for the provided distribution and target in which each of the supplied `pins` is
+/// held at its version, should the package remain in the closure, and the versions locked for the target by
+/// the `preferences` lockfile, if any, are preferred. See `perform_solve_for_distribution_and_target`.
+pub fn perform_pinned_solve_for_distribution_and_target(
+    plugin_mgr: &PluginMgr,
+    distribution: &str,
+    target: &str,
+    target_policy: &TargetPolicy,
+    pins: &[(String, SemanticVersion)],
+    preferences: Option<&LockFile>,
+    include_pre: bool
+) -> Result<SolveResult, PesError> {
+    debug!("distribution: {} target: {} pins: {:?}", distribution, target, pins);
     let repos = PackageRepository::from_plugin(plugin_mgr)?;
     let mut path = None;
     for repo in &repos {
@@ -137,15 +192,30 @@ pub fn perform_solve_for_distribution_and_target(
         return Err(PesError::DistributionNotFound(distribution.to_string()));
     }
     let manifest = Manifest::from_path(path.unwrap())?;
-    let request = manifest.get_requires(target)?;
+    // should the distribution have variants, we need to select one of them as well
+    let (name, version) = parse_consuming_package_version(distribution)?;
+    let root_variant = variant_package(name, &version);
+    let mut requires = manifest.get_requires(target)?;
+    if !manifest.variants().is_empty() {
+        requires.push(DistributionRange::new(&root_variant, Range::any()));
+    }
+    let request = SolveRequest::new(requires)
+        .with_conflicts(manifest.get_conflicts(target)?)
+        .with_weak_requires(manifest.get_weak_requires(target)?)
+        .with_pins(pins);
 
     let min_release_type = if include_pre {ReleaseType::Alpha} else {ReleaseType::Release};
     let dist_overrides = if include_pre {Rc::new(Vec::new())} else {
         let (name, version) = parse_consuming_package_version(distribution)?;
-        Rc::new(vec![(name.to_string(), version)])
+        let pinned_pre = pins.iter().filter(|(_name, version)| version.release_type < ReleaseType::Release).cloned();
+        Rc::new(std::iter::once((name.to_string(), version)).chain(pinned_pre).collect())
     };
 
-    let mut solver = Solver::new_from_repos(repos, min_release_type, dist_overrides)?;
+    let mut solver = Solver::new_from_repos(repos, min_release_type, dist_overrides)?
+        .with_target_policy(target_policy.clone(), target);
+    if let Some(lockfile) = preferences {
+        solver = solver.with_lockfile_preferences(lockfile, target);
+    }
     let mut solution = solver.solve(request)?;
     solution.remove(ROOT_REQUEST);
     // store a mapping between distributions and their paths on disk
@@ -180,129 +250,350 @@ pub fn perform_solve_for_distribution_and_target(
         }).collect::<Result<(), PesError>>()?; 
     }
     
-    Ok((distpathmap, solution))
+    Ok((distpathmap, solution, solver.variants().clone()))
+}
+
+// retrieve the name used to present the supplied package in explanations. Returns None for the
+// root request
+fn package_label(package: &str) -> Option<String> {
+    if package == ROOT_REQUEST {
+        return None;
+    }
+    match parse_variant_package(package) {
+        Some((name, version)) => Some(format!("{}-{} variant", name, version)),
+        None => Some(package.to_string()),
+    }
+}
+
+// describe a range of versions, taking the version reserved for absent packages into account
+fn describe_range(range: &Range<SemanticVersion>) -> String {
+    let absent = absent_version();
+    let present = range.intersection(&Range::exact(absent.clone()).negate());
+    if present == Range::none() {
+        return "(absent)".to_string();
+    }
+    // the absent version is never a real distribution, so it is folded into a range which begins right after
+    // it, allowing an unconstrained range to be displayed as *, rather than from the successor of the absent version
+    let folded = present.union(&Range::exact(absent.clone()));
+    let description = if range_intervals(&folded).map(|i| i.len()) == range_intervals(&present).map(|i| i.len()) {
+        display_range(&folded).to_string()
+    } else {
+        display_range(&present).to_string()
+    };
+    if range.contains(&absent) {
+        format!("{} (if present)", description)
+    } else {
+        description
+    }
+}
+
+/// The constraints which a solve must satisfy
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct SolveRequest<'a> {
+    /// packages which must be present in the solution, within the supplied ranges
+    pub requires: Vec<DistributionRange<'a>>,
+    /// ranges of packages which must not be present in the solution
+    pub conflicts: Vec<DistributionRange<'a>>,
+    /// ranges which packages must fall within should they be present in the solution
+    pub weak_requires: Vec<DistributionRange<'a>>,
+}
+
+impl<'a> SolveRequest<'a> {
+    /// Construct a new request for the supplied requirements
+    pub fn new(requires: Vec<DistributionRange<'a>>) -> Self {
+        Self { requires, conflicts: Vec::new(), weak_requires: Vec::new() }
+    }
+
+    /// Construct a request from a list of package ranges (eg maya-2019), any of which may be 
+    /// weak requirements, prefixed with a tilde (eg ~maya-2019)
+    pub fn from_strs<S: AsRef<str>>(constraints: &'a [S]) -> Result<Self, PesError> {
+        let mut request = Self::default();
+        for constraint in constraints {
+            let constraint = constraint.as_ref();
+            if constraint.trim_start().starts_with('~') {
+                let (name, range) = parse_consuming_weak_package_range(constraint)?;
+                request.weak_requires.push(DistributionRange::new(name, range));
+            } else {
+                request.requires.push(DistributionRange::from_str(constraint)?);
+            }
+        }
+        Ok(request)
+    }
+
+    /// Add conflicts to the request
+    pub fn with_conflicts(mut self, mut conflicts: Vec<DistributionRange<'a>>) -> Self {
+        self.conflicts.append(&mut conflicts);
+        self
+    }
+
+    /// Add weak requirements to the request
+    pub fn with_weak_requires(mut self, mut weak_requires: Vec<DistributionRange<'a>>) -> Self {
+        self.weak_requires.append(&mut weak_requires);
+        self
+    }
+
+    /// Hold each of the supplied packages at its version, should it be pulled into the closure. Pins are
+    /// weak requirements of the exact version, so they never pull a package in themselves.
+    pub fn with_pins(mut self, pins: &'a [(String, SemanticVersion)]) -> Self {
+        self.weak_requires.extend(
+            pins.iter().map(|(name, version)| DistributionRange::new(name.as_str(), Range::exact(version.clone()))),
+        );
+        self
+    }
+}
+
+impl<'a> From<Vec<DistributionRange<'a>>> for SolveRequest<'a> {
+    fn from(requires: Vec<DistributionRange<'a>>) -> Self {
+        Self::new(requires)
+    }
 }
 
 /// Solver holds needed state to perform dependency closure solve
 #[derive(Debug)]
-pub struct Solver<P: Package, V: Version> {
-    pub dependency_provider: OfflineDependencyProvider<P, V>,
-    dist_cache: DistMap,
+pub struct Solver<R: Repository> {
+    pub dependency_provider: RepositoryDependencyProvider<R>,
+    /// the variants selected by the most recent solve
+    variants: VariantMap,
 }
 
 
-impl Default for Solver<String, SemanticVersion> {
+impl<R: Repository> Default for Solver<R> {
     fn default() -> Self {
         Self {
-            dependency_provider: OfflineDependencyProvider::new(),
-            dist_cache: DistMap::new(),
+            dependency_provider: RepositoryDependencyProvider::new(),
+            variants: VariantMap::new(),
         }
     }
 }
 
-impl Solver<String, SemanticVersion> {
-    pub fn new() -> Self {
-        Self::default()
-    }
-    /// Construct a new Solver instacne from a vec of repositories. All of the 
-    /// distributions within each repository will be appropriately registered with
-    /// the solver so that they may be considered in calculating the dependency
-    /// closure when `solve` is later invoked.
+impl<'a> Solver<PackageRepository<'a>> {
+    /// Construct a new Solver instacne from a vec of repositories. The distributions within 
+    /// each repository will be considered in calculating the dependency closure when `solve` 
+    /// is later invoked. Repository indices are cached in the `default_cache_dir`.
     pub fn new_from_repos(
-        repos: Vec<PackageRepository>,
+        repos: Vec<PackageRepository<'a>>,
         min_release_type: ReleaseType, 
         distributions_override: std::rc::Rc<Vec<(String, SemanticVersion)>>
 
-    ) -> Result<Solver<String, SemanticVersion>, PesError> {
-        let mut solver = Solver::new();
+    ) -> Result<Self, PesError> {
+        let mut solver = Solver::new().with_cache_dir(default_cache_dir());
 
         for repo in repos {
-            solver.add_repository(&repo, min_release_type, distributions_override.clone())?;
+            solver.add_repository(repo, min_release_type, distributions_override.clone())?;
         }
         Ok(solver)
     }
+}
+
+impl<R: Repository> Solver<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cache repository indices in the supplied directory. Passing None disables the cache.
+    /// This applies to repositories added subsequently.
+    pub fn with_cache_dir<P: Into<PathBuf>>(mut self, cache_dir: Option<P>) -> Self {
+        self.dependency_provider.set_cache_dir(cache_dir.map(|dir| dir.into()));
+        self
+    }
     
-    /// Retrieve an iterator over package names that have been registered via ```add_repository```
-    pub fn packages(&self) -> impl Iterator<Item = &str> {
-        self.dependency_provider.packages().map(|x| x.as_str())
+    /// Set the policy which determines the target of each transitive dependency consulted during the solve,
+    /// given the target requested for the root.
+    pub fn with_target_policy<T: Into<String>>(mut self, target_policy: TargetPolicy, root_target: T) -> Self {
+        self.dependency_provider.set_target_policy(target_policy, root_target);
+        self
+    }
+
+    /// Set the versions, and variants, of packages to try before any others. Preferences are soft, so a package
+    /// whose preferred version is not allowed by the request falls back to the newest version which is.
+    pub fn with_preferences<I>(mut self, versions: I, variants: VariantMap) -> Self
+    where
+        I: IntoIterator<Item = (String, SemanticVersion)>,
+    {
+        self.dependency_provider.set_preferences(versions.into_iter().collect(), variants);
+        self
+    }
+
+    /// Prefer the versions and variants locked for the target. Solving a request which differs from that of the
+    /// lockfile (eg adding a constraint) thus leaves the other locked versions unchanged where possible. Should the
+    /// lockfile not lock the target, nothing is preferred.
+    pub fn with_lockfile_preferences(self, lockfile: &LockFile, target: &str) -> Self {
+        let versions = lockfile
+            .dists_for(target)
+            .map(|dists| dists.map(|(package, version)| (package.clone(), version.clone())).collect::<Vec<_>>())
+            .unwrap_or_default();
+        self.with_preferences(versions, lockfile.variants_for(target))
     }
 
-    /// Retrieve an iterator over SemanticVersions for a provided package that have been registered
-    pub fn versions(&self, package: &str) -> Option<impl Iterator<Item = &SemanticVersion>> {
-        self.dependency_provider.versions(&package.to_string())
+    /// Retrieve the names of the packages which have been visited by the solver thus far. As
+    /// repositories are read lazily, this will be empty until ```solve``` or ```versions``` is called.
+    pub fn packages(&self) -> Vec<String> {
+        self.dependency_provider.packages()
+    }
+
+    /// Retrieve the SemanticVersions available for a provided package, in ascending order. Returns None if the
+    /// package does not exist in any of the repositories.
+    pub fn versions(&self, package: &str) -> Option<Vec<SemanticVersion>> {
+        let versions = self.dependency_provider.package(package).ok()?;
+        let versions = versions.versions().cloned().collect::<Vec<_>>();
+        if versions.is_empty() {None} else {Some(versions)}
+    }
+    /// Retrieve the variants selected by the most recent solve, keyed by package
+    pub fn variants(&self) -> &VariantMap {
+        &self.variants
     }
     /// Retrieve the path to the supplied distribution, assuming it exists
-    pub fn dist_path(&self, distribution: &str) -> Option<&Path> {
-        self.dist_cache.get(distribution).map(|x| x.as_path())
+    pub fn dist_path(&self, distribution: &str) -> Option<PathBuf> {
+        let (name, version) = parse_consuming_package_version(distribution).ok()?;
+        self.dependency_provider.dist_path(name, &version)
     }
-    /// Add packages from a repository to the dependency provider
-    pub fn add_repository<R: Repository>(
+    /// Register a repository with the solver. The repository is consulted lazily; only the packages
+    /// which the solver visits are read from it.
+    pub fn add_repository(
         &mut self, 
-        repository: &R, 
+        repository: R, 
         min_release_type: ReleaseType, 
         distributions_override: std::rc::Rc<Vec<(String, SemanticVersion)>>
     ) -> Result<(), PesError> {
-        for manifest_path in repository.manifests(min_release_type, distributions_override) {
-            let manifest_path =
-                manifest_path.map_err(|e| PesError::PesError(format!("{:?}", e)))?;
-            let mut dist_path = manifest_path.as_ref().to_path_buf();
-            // we currently need to know the details of manifest location. we should change repository to return
-            // dist paths and then have a manifestfactory
-            dist_path.pop();
-            let manifest = PackageManifest::from_file(manifest_path)?;
-            let requires: Vec<(String, Range<SemanticVersion>)> = manifest
-                .get_requires("run")
-                .unwrap_or_else(|_| Vec::<DistributionRange>::new())
-                .into_iter()
-                .map(|distribution_range| {
-                    let DistributionRange { name, range, .. } = distribution_range;
-                    (name.to_string(), range)
-                })
-                .collect();
-            let PackageManifest { name, version, .. } = manifest;
-            // update distribution cache so that we can print it out later
-            self.dist_cache
-                .insert(format!("{}-{}", name.as_str(), &version), dist_path);
-            self.dependency_provider
-                .add_dependencies(name, version, requires);
-        }
+        self.dependency_provider.add_repository(repository, min_release_type, distributions_override);
         Ok(())
     }
 
     /// calculate a solution
-    pub fn solve(
+    pub fn solve<'a, Q: Into<SolveRequest<'a>>>(
         &mut self,
-        request: Vec<DistributionRange>,
+        request: Q,
     ) -> Result<SelectedDependencies<String, SemanticVersion>, PesError> {
-        let requires: Vec<(String, Range<SemanticVersion>)> = request
-            .into_iter()
-            .map(|distribution_range| {
-                let DistributionRange { name, range, .. } = distribution_range;
-                (name.to_string(), range)
-            })
-            .collect();
+        let request = request.into();
         // create a fake package to house the requested version constraints
-        self.dependency_provider.add_dependencies(
-            ROOT_REQUEST.to_string(),
-            SemanticVersion::new(1, 0, 0, ReleaseType::Release),
-            requires,
-        );
-        match resolve(
+        self.dependency_provider.set_request(request);
+        let result = resolve(
             &self.dependency_provider,
             ROOT_REQUEST.to_string(),
-            SemanticVersion::new(1, 0, 0, ReleaseType::Release),
-        ) {
-            Ok(solution) => Ok(solution),
+            RepositoryDependencyProvider::<R>::root_version(),
+        );
+        // persist whatever we learned about the repositories, regardless of the outcome
+        self.dependency_provider.save();
+        match result {
+            Ok(solution) => Ok(self.extract_variants(solution)),
             Err(PubGrubError::NoSolution(mut derivation_tree)) => {
                 derivation_tree.collapse_no_versions();
-                Err(PesError::NoSolution(DefaultStringReporter::report(
-                    &derivation_tree,
-                )))
+                let failure = SolveFailure::new(self.explain(&derivation_tree));
+                Err(PesError::NoSolution(Box::new(failure)))
             }
             Err(err) => Err(PesError::PesError(err.to_string())),
         }
     }
 
+    // remove the synthetic variant packages and absent packages from the solution, recording the selected variants
+    fn extract_variants(&mut self, solution: SelectedDependencies<String, SemanticVersion>) -> SelectedDependencies<String, SemanticVersion> {
+        self.variants.clear();
+        let mut distributions = SelectedDependencies::default();
+        for (package, version) in solution {
+            if version == absent_version() {
+                continue;
+            }
+            match parse_variant_package(&package) {
+                Some((name, _)) => {
+                    if let Some(variant) = self.dependency_provider.variant_name(&package, &version) {
+                        self.variants.insert(name.to_string(), variant);
+                    }
+                }
+                None => {
+                    distributions.insert(package, version);
+                }
+            }
+        }
+        distributions
+    }
+
+    // map the derivation tree of a failed solve onto distributions, ranges, and the manifests 
+    // which declared each constraint
+    fn explain(&self, derivation_tree: &DerivationTree<String, SemanticVersion>) -> FailureCause {
+        match derivation_tree {
+            DerivationTree::External(External::NotRoot(package, version)) => FailureCause::NotRoot {
+                package: package.clone(),
+                version: version.to_string(),
+            },
+            DerivationTree::External(External::NoVersions(package, range)) => FailureCause::NoVersions {
+                package: package_label(package).unwrap_or_else(|| package.clone()),
+                range: self.describe(package, range),
+            },
+            DerivationTree::External(External::UnavailableDependencies(package, range)) => FailureCause::Unavailable {
+                package: package_label(package).unwrap_or_else(|| package.clone()),
+                range: self.describe(package, range),
+            },
+            DerivationTree::External(External::FromDependencyOf(package, range, dependency, dependency_range)) => {
+                let declared = self.dependency_provider.declared_constraint(package, range, dependency);
+                let (relation, dependency_range, distributions) = match declared {
+                    Some(declared) => {
+                        // the declared range only ever applies to distributions which are present
+                        let present = declared.range.intersection(&Range::exact(absent_version()).negate());
+                        (declared.relation, self.describe(dependency, &present), declared.distributions)
+                    }
+                    None => (Relation::Requires, self.describe(dependency, dependency_range), Vec::new()),
+                };
+                let label = package_label(package);
+                FailureCause::Dependency {
+                    distributions: match label.as_ref() {
+                        Some(label) => distributions.iter().map(|(version, _)| format!("{}-{}", label, version)).collect(),
+                        None => Vec::new(),
+                    },
+                    manifests: distributions
+                        .into_iter()
+                        .map(|(_, manifest)| manifest)
+                        .filter(|manifest| !manifest.as_os_str().is_empty())
+                        .collect(),
+                    package: label,
+                    range: self.describe(package, range),
+                    relation,
+                    dependency: package_label(dependency).unwrap_or_else(|| dependency.clone()),
+                    dependency_range,
+                }
+            }
+            DerivationTree::Derived(derived) => {
+                let mut terms = derived
+                    .terms
+                    .iter()
+                    .map(|(package, term)| {
+                        let (range, positive) = match term {
+                            Term::Positive(range) => (range, true),
+                            Term::Negative(range) => (range, false),
+                        };
+                        FailureTerm { package: package_label(package), range: self.describe(package, range), positive }
+                    })
+                    .collect::<Vec<_>>();
+                // present the request first, followed by positive terms, so that explanations read consistently
+                terms.sort_by(|a, b| {
+                    (a.package.is_some(), !a.positive, &a.package).cmp(&(b.package.is_some(), !b.positive, &b.package))
+                });
+                FailureCause::Derived {
+                    terms,
+                    shared_id: derived.shared_id,
+                    causes: vec![self.explain(&derived.cause1), self.explain(&derived.cause2)],
+                }
+            }
+        }
+    }
+
+    // describe a range of the supplied package. Ranges of variant packages are described 
+    // by the names of the variants they contain
+    fn describe(&self, package: &str, range: &Range<SemanticVersion>) -> String {
+        if package == ROOT_REQUEST {
+            return String::new();
+        }
+        if parse_variant_package(package).is_some() {
+            let variants = (0..)
+                .map(|idx| (idx, self.dependency_provider.variant_name(package, &variant_version(idx))))
+                .take_while(|(_, name)| name.is_some())
+                .filter(|(idx, _)| range.contains(&variant_version(*idx)))
+                .filter_map(|(_, name)| name)
+                .collect::<Vec<_>>();
+            return variants.join(" | ");
+        }
+        describe_range(range)
+    }
+
     /// Given the path to a manifest and the name of a target within the manifest, calculate the solution
     pub fn solve_target_from_manifest(
         &mut self,
@@ -310,26 +601,32 @@ impl Solver<String, SemanticVersion> {
         manifest: &Path,
     ) -> Result<SelectedDependencies<String, SemanticVersion>, PesError> {
         let manifest = Manifest::from_path(manifest)?;
-        let request = manifest.get_requires(target)?;
+        let request = SolveRequest::new(manifest.get_requires(target)?)
+            .with_conflicts(manifest.get_conflicts(target)?)
+            .with_weak_requires(manifest.get_weak_requires(target)?);
         self.solve(request)
     }
 
     // utility function facilitating unit testing
-    pub(crate) fn convert_request_str(request: &str) -> Vec<DistributionRange> {
-        let requested: Vec<DistributionRange> = request
-            .split(' ')
-            .map(|s| DistributionRange::from_str(s))
-            .filter_map(|s| s.ok())
-            .collect();
+    pub(crate) fn convert_request_str(request: &str) -> SolveRequest<'_> {
+        let mut requested = SolveRequest::default();
+        for s in request.split(' ') {
+            if let Ok((name, range)) = parse_consuming_weak_package_range(s) {
+                requested.weak_requires.push(DistributionRange::new(name, range));
+            } else if let Ok(distribution_range) = DistributionRange::from_str(s) {
+                requested.requires.push(distribution_range);
+            }
+        }
         requested
     }
 
-    /// Solve for the requested constraints in the provided ```requestr``` string
+    /// Solve for the requested constraints in the provided ```requestr``` string. Constraints
+    /// prefixed with a tilde are weak requirements.
     /// # Example
     /// ```ignore
     /// maya-1.0.1,maya-plugins,maya-startup-1.2.3+<4
     /// or
-    /// maya-1 maya-plugins maya-startup-1.2.3+<4
+    /// maya-1 maya-plugins maya-startup-1.2.3+<4 ~houdini-18
     /// ```
     pub fn solve_from_str(
         &mut self,
@@ -352,7 +649,9 @@ impl Solver<String, SemanticVersion> {
         // get an instance of PackageManifest from the provided manifest path
         let manifest = PackageManifest::from_file(manifest_path.as_ref())?;
         // get_requires returns a Vec<DistributionRange>
-        let requested = manifest.get_requires(target)?;
+        let requested = SolveRequest::new(manifest.get_requires(target)?)
+            .with_conflicts(manifest.get_conflicts(target)?)
+            .with_weak_requires(manifest.get_weak_requires(target)?);
         // call the solve method with the vector of versioned packages
         self.solve(requested)
     }
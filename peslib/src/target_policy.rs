@@ -0,0 +1,137 @@
+//! Components which determine the target used to calculate the dependencies of each package in a solve.
+//!
+//! When solving for a distribution and target (eg `foo-1.0.0` and `build`), the requested target is
+//! used for the root distribution. The `TargetPolicy` governs which target is used for each transitive
+//! dependency. By default, the `run` target is used for all dependencies (ie "build root, run for deps").
+//! One may instead use the same target all the way down, and override the target for individual
+//! packages (eg `gcc:build`).
+//!
+//! A policy may be parsed from a comma separated spec. Each entry is either a target name, `same`, or a
+//! `package:target` override:
+//! ```ignore
+//! run
+//! same
+//! run,gcc:build,cmake:build
+//! ```
+use std::{fmt, str::FromStr};
+
+use indexmap::IndexMap;
+
+use crate::{constants::DEFAULT_TARGET, PesError};
+
+/// The keyword used to request that dependencies be solved using the same target as the root
+pub const SAME_TARGET: &str = "same";
+
+/// Determines the target used for transitive dependencies which do not have an override
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransitiveTarget {
+    /// Use the target requested for the root distribution
+    Same,
+    /// Use the named target
+    Target(String),
+}
+
+impl Default for TransitiveTarget {
+    fn default() -> Self {
+        TransitiveTarget::Target(DEFAULT_TARGET.to_string())
+    }
+}
+
+/// Policy governing which target of each transitive dependency is consulted during a solve
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TargetPolicy {
+    transitive: TransitiveTarget,
+    /// per package target overrides
+    overrides: IndexMap<String, String>,
+}
+
+impl TargetPolicy {
+    /// Construct a new policy which uses the supplied target for transitive dependencies
+    pub fn new(transitive: TransitiveTarget) -> Self {
+        Self {
+            transitive,
+            overrides: IndexMap::new(),
+        }
+    }
+
+    /// Construct a policy which uses the `run` target for all transitive dependencies. This is the default.
+    pub fn run() -> Self {
+        Self::default()
+    }
+
+    /// Construct a policy which uses the root's target all the way down
+    pub fn same() -> Self {
+        Self::new(TransitiveTarget::Same)
+    }
+
+    /// Use `target` when calculating the dependencies of `package`
+    pub fn with_override<P: Into<String>, T: Into<String>>(mut self, package: P, target: T) -> Self {
+        self.overrides.insert(package.into(), target.into());
+        self
+    }
+
+    /// Retrieve the target to use for transitive dependencies which do not have an override
+    pub fn transitive(&self) -> &TransitiveTarget {
+        &self.transitive
+    }
+
+    /// Retrieve the target to use when calculating the dependencies of `package`, given the
+    /// target requested for the root of the solve.
+    pub fn target_for<'a>(&'a self, package: &str, root_target: &'a str) -> &'a str {
+        if let Some(target) = self.overrides.get(package) {
+            return target.as_str();
+        }
+        match &self.transitive {
+            TransitiveTarget::Same => root_target,
+            TransitiveTarget::Target(target) => target.as_str(),
+        }
+    }
+}
+
+impl FromStr for TargetPolicy {
+    type Err = PesError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut policy = TargetPolicy::default();
+        for entry in spec.split(',').map(str::trim) {
+            let pieces = entry.split(':').map(str::trim).collect::<Vec<_>>();
+            match pieces.as_slice() {
+                [target] if !target.is_empty() => {
+                    policy.transitive = if *target == SAME_TARGET {
+                        TransitiveTarget::Same
+                    } else {
+                        TransitiveTarget::Target(target.to_string())
+                    };
+                }
+                [package, target] if !package.is_empty() && !target.is_empty() => {
+                    policy.overrides.insert(package.to_string(), target.to_string());
+                }
+                _ => {
+                    return Err(PesError::ParsingFailure(format!(
+                        "invalid target policy entry '{}' in '{}'. Expected <target>, {} or <package>:<target>",
+                        entry, spec, SAME_TARGET
+                    )))
+                }
+            }
+        }
+        Ok(policy)
+    }
+}
+
+// renders the policy as a spec which parses back to the same policy
+impl fmt::Display for TargetPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.transitive {
+            TransitiveTarget::Same => write!(f, "{}", SAME_TARGET)?,
+            TransitiveTarget::Target(target) => write!(f, "{}", target)?,
+        }
+        for (package, target) in &self.overrides {
+            write!(f, ",{}:{}", package, target)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "./unit_tests/target_policy.rs"]
+mod unit_tests;
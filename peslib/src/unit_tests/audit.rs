@@ -0,0 +1,179 @@
+#![allow(non_snake_case)]
+
+use super::*;
+use std::path::PathBuf;
+
+const BAD_MANIFEST: &str = r#"---
+schema: 2
+name: mypackage
+version: 1.2.3
+description: test
+colour: blue
+targets:
+    run:
+        requires:
+            maya: 2018+
+            mypackage: 1
+        include:
+            - build
+        requirs:
+            nuke: 12
+    build:
+        include:
+            - run
+        requires: {}
+environment:
+    PATH: prepend({root}/bin)
+    PYTHONPATH: append({rooot}/python)
+    LD_LIBRARY_PATH: prepend({root}/lib
+"#;
+
+const BAD_TOML_MANIFEST: &str = r#"schema = 2
+name = "mypackage"
+version = "1.2.3"
+description = "test"
+
+[targets.run.requires]
+maya = "2018"
+nuke = "twelve"
+
+[targets.build]
+include = ["lint"]
+requires = {}
+
+[environment]
+PATH = "prepend({root}/bin)"
+"#;
+
+const MISSING_FIELD_MANIFEST: &str = r#"schema: 2
+name: mypackage
+version: 1.2.3
+description: test
+targets:
+    run:
+        include:
+            - build
+"#;
+
+const INCOMPATIBLE_INCLUDES_MANIFEST: &str = r#"schema: 2
+name: mypackage
+version: 1.2.3
+description: test
+targets:
+    run:
+        requires:
+            maya: 2019
+    build:
+        include:
+            - run
+        requires:
+            maya: 2020
+"#;
+
+fn get_repo_root() -> PathBuf {
+    let mut root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    root.push("../test_fixtures/repo");
+    root
+}
+
+// reduce the diagnostics to their locations and severities
+fn summarize(report: &AuditReport) -> Vec<(Option<usize>, Option<usize>, Severity)> {
+    report.diagnostics.iter().map(|d| (d.line, d.column, d.severity)).collect()
+}
+
+#[test]
+fn audit_manifest_str__given_bad_manifest__reports_every_problem_with_its_location() {
+    let report = audit_manifest_str(BAD_MANIFEST, "/repo/mypackage/1.2.4/manifest.yaml", ManifestFormat::Yaml);
+    assert_eq!(
+        summarize(&report),
+        vec![
+            (Some(4), Some(1), Severity::Error),
+            (Some(6), Some(1), Severity::Warning),
+            (Some(10), Some(13), Severity::Error),
+            (Some(11), Some(13), Severity::Error),
+            (Some(12), Some(9), Severity::Error),
+            (Some(14), Some(9), Severity::Warning),
+            (Some(22), Some(5), Severity::Error),
+            (Some(23), Some(5), Severity::Error),
+        ],
+        "{}",
+        report
+    );
+    assert!(report.diagnostics[4].message.contains("run -> build -> run"), "{}", report);
+    assert!(report.diagnostics[6].message.contains("{rooot}"), "{}", report);
+    assert_eq!(report.error_count(), 6);
+    assert_eq!(report.warning_count(), 2);
+    assert!(report.to_string().starts_with("/repo/mypackage/1.2.4/manifest.yaml:4:1: error: version 1.2.3"));
+}
+
+#[test]
+fn audit_manifest_str__given_bad_toml_manifest__reports_locations() {
+    let report = audit_manifest_str(BAD_TOML_MANIFEST, "manifest.toml", ManifestFormat::Toml);
+    assert_eq!(
+        summarize(&report),
+        vec![(Some(8), Some(1), Severity::Error), (Some(11), Some(1), Severity::Error)],
+        "{}",
+        report
+    );
+}
+
+#[test]
+fn audit_manifest_str__given_malformed_manifest__reports_parse_error() {
+    let report = audit_manifest_str("schema: 2\nname: [foo\n", "manifest.yaml", ManifestFormat::Yaml);
+    assert_eq!(report.diagnostics.len(), 1);
+    assert_eq!(report.diagnostics[0].severity, Severity::Error);
+    assert!(report.diagnostics[0].line.is_some());
+
+    let report = audit_manifest_str(MISSING_FIELD_MANIFEST, "manifest.yaml", ManifestFormat::Yaml);
+    assert_eq!(summarize(&report), vec![(Some(7), Some(16), Severity::Error)], "{}", report);
+    assert!(report.diagnostics[0].message.contains("missing field `requires`"), "{}", report);
+
+    let report = audit_manifest_str("schema: 3\nname: foo\n", "manifest.yaml", ManifestFormat::Yaml);
+    assert_eq!(summarize(&report), vec![(Some(1), Some(1), Severity::Error)]);
+}
+
+#[test]
+fn audit_manifest_file__given_valid_manifest__reports_no_errors() {
+    let report = audit_manifest_file(get_repo_root().join("foo/0.1.0/manifest.yaml")).unwrap();
+    assert!(!report.has_errors(), "{}", report);
+}
+
+#[test]
+fn audit_manifest_str__given_incompatible_included_ranges__reports_error() {
+    let report = audit_manifest_str(INCOMPATIBLE_INCLUDES_MANIFEST, "manifest.yaml", ManifestFormat::Yaml);
+    assert_eq!(summarize(&report), vec![(Some(10), Some(9), Severity::Error)], "{}", report);
+    assert!(report.diagnostics[0].message.contains("incompatible ranges of 'maya'"), "{}", report);
+}
+
+#[test]
+fn audit_manifest_str__given_environment_operations__reports_no_errors() {
+    let manifest = INCOMPATIBLE_INCLUDES_MANIFEST.replace("maya: 2020", "maya: 2019")
+        + "environment:\n    EDITOR: default(vim)\n    CFLAGS: 'append(-I{root}/include -DNAME=\\{name\\}, sep=\" \")'\n    PYTHONHOME: unset()\n";
+    let report = audit_manifest_str(&manifest, "manifest.yaml", ManifestFormat::Yaml);
+    assert!(report.diagnostics.is_empty(), "{}", report);
+}
+
+#[test]
+fn audit_manifest_str__given_references__checks_package_roots() {
+    let manifest = INCOMPATIBLE_INCLUDES_MANIFEST.replace("maya: 2020", "maya: 2019")
+        + "environment:\n    MAYA_PLUG_IN_PATH: 'prepend({maya.root}/plugins:{root}/{name}{major}:$HOME/plugins)'\n    NUKE_PATH: prepend({nuke.root}/lib)\n    FOO: set({maya.rot})\n";
+    let report = audit_manifest_str(&manifest, "manifest.yaml", ManifestFormat::Yaml);
+    assert_eq!(
+        summarize(&report),
+        vec![(Some(16), Some(5), Severity::Warning), (Some(17), Some(5), Severity::Error)],
+        "{}",
+        report
+    );
+}
+
+#[test]
+fn audit_manifest_str__given_legacy_target_keys__accepts_them_only_in_schema_1() {
+    let legacy = "schema = 1\nname = \"mypackage\"\nversion = \"1.2.3\"\ndescription = \"test\"\n\n[target.run]\ninclude-targets = []\nrequires = {}\n";
+    let report = audit_manifest_str(legacy, "manifest.toml", ManifestFormat::Toml);
+    assert_eq!(summarize(&report), vec![(Some(1), Some(1), Severity::Warning)], "{}", report);
+
+    let report = audit_manifest_str(&legacy.replace("schema = 1", "schema = 2"), "manifest.toml", ManifestFormat::Toml);
+    assert_eq!(report.diagnostics.len(), 1, "{}", report);
+    assert_eq!(report.diagnostics[0].severity, Severity::Warning);
+    assert!(report.diagnostics[0].message.contains("unknown key 'target'"), "{}", report);
+}
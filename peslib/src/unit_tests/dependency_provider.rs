@@ -0,0 +1,160 @@
+#![allow(non_snake_case)]
+use super::*;
+use crate::{PackageRepository, PluginMgr};
+use std::str::FromStr;
+use testutils::{tempfile::TempDir, write_distribution};
+
+//-------------//
+//   HELPERS   //
+//-------------//
+
+fn get_repo_root(repo_name: &str) -> PathBuf {
+    let mut root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    root.push("../test_fixtures");
+    root.push(repo_name);
+    root
+}
+
+fn provider_for<'a>(plugin_mgr: &'a PluginMgr, min_release_type: ReleaseType) -> RepositoryDependencyProvider<PackageRepository<'a>> {
+    let mut provider = RepositoryDependencyProvider::new();
+    provider.add_repository(
+        PackageRepository::new(get_repo_root("repo"), plugin_mgr),
+        min_release_type,
+        Rc::new(Vec::new()),
+    );
+    provider
+}
+
+fn dependency_names(provider: &RepositoryDependencyProvider<PackageRepository>, package: &str, version: &str) -> Vec<String> {
+    match provider.get_dependencies(&package.to_string(), &semver(version)).expect("unable to get dependencies") {
+        Dependencies::Known(deps) => {
+            let mut names = deps.keys().cloned().collect::<Vec<_>>();
+            names.sort();
+            names
+        }
+        Dependencies::Unknown => panic!("expected known dependencies"),
+    }
+}
+
+fn semver(version: &str) -> SemanticVersion {
+    SemanticVersion::from_str(version).unwrap()
+}
+
+//-------------//
+//    TESTS    //
+//-------------//
+
+#[test]
+fn package__only_reads_requested_packages() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let provider = provider_for(&plugin_mgr, ReleaseType::Release);
+    assert!(provider.packages().is_empty());
+
+    let bar = provider.package("bar").expect("unable to retrieve bar");
+    let versions = bar.versions().cloned().collect::<Vec<_>>();
+    assert_eq!(versions, vec![semver("0.1.0"), semver("1.0.1")]);
+    assert_eq!(provider.packages(), vec!["bar".to_string()]);
+}
+
+#[test]
+fn package__filters_pre_releases() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let provider = provider_for(&plugin_mgr, ReleaseType::Release);
+    let foo = provider.package("foo").expect("unable to retrieve foo");
+    assert_eq!(foo.versions().count(), 3);
+
+    let provider = provider_for(&plugin_mgr, ReleaseType::Alpha);
+    let foo = provider.package("foo").expect("unable to retrieve foo");
+    assert_eq!(foo.versions().count(), 4);
+}
+
+#[test]
+fn package__given_missing_package__returns_no_versions() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let provider = provider_for(&plugin_mgr, ReleaseType::Release);
+    let missing = provider.package("doesnotexist").expect("missing package should not error");
+    assert_eq!(missing.versions().count(), 0);
+}
+
+#[test]
+fn get_dependencies__returns_run_requires() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let provider = provider_for(&plugin_mgr, ReleaseType::Release);
+    let deps = provider
+        .get_dependencies(&"bar".to_string(), &semver("1.0.1"))
+        .expect("unable to get dependencies");
+    match deps {
+        Dependencies::Known(deps) => {
+            assert_eq!(deps.len(), 1);
+            assert_eq!(deps.get("foo"), Some(&Range::between(semver("0.2.0"), semver("0.3.0"))));
+        }
+        Dependencies::Unknown => panic!("expected known dependencies"),
+    }
+}
+
+#[test]
+fn get_dependencies__given_request__returns_request() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let mut provider = provider_for(&plugin_mgr, ReleaseType::Release);
+    provider.set_request(vec![DistributionRange::from_str("bar-1").unwrap()].into());
+    let deps = provider
+        .get_dependencies(&ROOT_REQUEST.to_string(), &RepositoryDependencyProvider::<PackageRepository>::root_version())
+        .expect("unable to get dependencies");
+    match deps {
+        Dependencies::Known(deps) => assert!(deps.contains_key("bar")),
+        Dependencies::Unknown => panic!("expected known dependencies"),
+    }
+    // the root request is not a real package
+    assert!(provider.packages().is_empty());
+}
+
+#[test]
+fn get_dependencies__honors_target_policy() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = TempDir::new().expect("unable to create temp dir");
+    write_distribution(repo_dir.path(), "baz", "1.0.0", "targets:\n  run:\n    requires:\n      foo: 1\n  build:\n    requires:\n      cmake: 3\n");
+    write_distribution(repo_dir.path(), "foo", "1.0.0", "targets:\n  run:\n    requires:\n      bar: 1\n");
+
+    let mut provider = RepositoryDependencyProvider::new();
+    provider.add_repository(PackageRepository::new(repo_dir.path(), &plugin_mgr), ReleaseType::Release, Rc::new(Vec::new()));
+    // build root, run for deps
+    provider.set_target_policy(TargetPolicy::default(), "build");
+    assert_eq!(dependency_names(&provider, "baz", "1.0.0"), vec!["foo"]);
+    // same target all the way down
+    provider.set_target_policy(TargetPolicy::same(), "build");
+    assert_eq!(dependency_names(&provider, "baz", "1.0.0"), vec!["cmake"]);
+    // foo does not define a build target, so we fall back to run
+    assert_eq!(dependency_names(&provider, "foo", "1.0.0"), vec!["bar"]);
+    // per package override
+    provider.set_target_policy(TargetPolicy::run().with_override("baz", "build"), "run");
+    assert_eq!(dependency_names(&provider, "baz", "1.0.0"), vec!["cmake"]);
+}
+
+#[test]
+fn package__given_indexed_manifest_with_invalid_requires__fails() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = TempDir::new().expect("unable to create temp dir");
+    let cache_dir = TempDir::new().expect("unable to create temp dir");
+    write_distribution(repo_dir.path(), "baz", "1.0.0", "targets:\n  run:\n    requires:\n      foo: 1\n");
+
+    let mut provider = RepositoryDependencyProvider::new();
+    provider.set_cache_dir(Some(cache_dir.path().to_path_buf()));
+    provider.add_repository(PackageRepository::new(repo_dir.path(), &plugin_mgr), ReleaseType::Release, Rc::new(Vec::new()));
+    assert!(provider.package("baz").is_ok());
+    provider.save();
+
+    // manifests are validated when read from disk, but not when served from the index
+    let cache_file = cache_file_for(cache_dir.path(), repo_dir.path());
+    let index = std::fs::read_to_string(&cache_file).unwrap();
+    let corrupt = index.replacen("foo: \"1\"", "foo: 1+<", 1).replacen("foo: '1'", "foo: 1+<", 1);
+    assert_ne!(index, corrupt);
+    std::fs::write(&cache_file, corrupt).unwrap();
+
+    let mut provider = RepositoryDependencyProvider::new();
+    provider.set_cache_dir(Some(cache_dir.path().to_path_buf()));
+    provider.add_repository(PackageRepository::new(repo_dir.path(), &plugin_mgr), ReleaseType::Release, Rc::new(Vec::new()));
+    assert!(provider.package("baz").is_err());
+    // the failure surfaces from the solver rather than yielding a solution without dependencies
+    let potential = vec![("baz".to_string(), Range::any())];
+    assert!(provider.choose_package_version(potential.into_iter()).is_err());
+}
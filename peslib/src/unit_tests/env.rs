@@ -94,4 +94,110 @@ mod BasicVarProvider_test {
         let result = provider.get("NOT_SET_FOR_SURE");
         assert_eq!(result, None);  
     }
-}
\ No newline at end of file
+}
+mod PathMode_test {
+    use super::*;
+
+    fn paths(paths: &[&str]) -> VecDeque<String> {
+        paths.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn apply__given_modes__composes_value() {
+        assert_eq!(PathMode::Prepend(paths(&["/a", "/b"])).apply(Some("/c")), Some("/a:/b:/c".to_string()));
+        assert_eq!(PathMode::Append(paths(&["/a", "/b"])).apply(Some("/c")), Some("/c:/a:/b".to_string()));
+        assert_eq!(PathMode::Prepend(paths(&["/a"])).apply(Some("")), Some("/a".to_string()));
+        assert_eq!(PathMode::Exact(paths(&["/a", "/b"])).apply(Some("/c")), Some("/a:/b".to_string()));
+        assert_eq!(PathMode::Set("a b=c".into()).apply(Some("/c")), Some("a b=c".to_string()));
+        assert_eq!(PathMode::Default("vim".into()).apply(Some("emacs")), Some("emacs".to_string()));
+        assert_eq!(PathMode::Default("vim".into()).apply(None), Some("vim".to_string()));
+        assert_eq!(PathMode::Unset.apply(Some("/c")), None);
+        let flags = PathMode::Separated(Box::new(PathMode::Append(paths(&["-g"]))), " ".into());
+        assert_eq!(flags.apply(Some("-O2")), Some("-O2 -g".to_string()));
+    }
+
+    #[test]
+    fn apply_to__given_env__updates_env() {
+        let mut env = HashMap::new();
+        PathMode::Default("vim".into()).apply_to(&mut env, "EDITOR");
+        PathMode::Prepend(paths(&["/a"])).apply_to(&mut env, "PATH");
+        PathMode::Prepend(paths(&["/b"])).apply_to(&mut env, "PATH");
+        assert_eq!(env.get("EDITOR"), Some(&"vim".to_string()));
+        assert_eq!(env.get("PATH"), Some(&"/b:/a".to_string()));
+        PathMode::Unset.apply_to(&mut env, "EDITOR");
+        assert_eq!(env.get("EDITOR"), None);
+    }
+
+    #[test]
+    fn add_assign__given_mode__composes_modes() {
+        let mut mode = PathMode::Exact(paths(&["/c"]));
+        mode += PathMode::Prepend(paths(&["/a", "/b"]));
+        assert_eq!(mode, PathMode::Exact(paths(&["/a", "/b", "/c"])));
+        mode += PathMode::Unset;
+        assert_eq!(mode, PathMode::Unset);
+        mode += PathMode::Default("x".into());
+        assert_eq!(mode, PathMode::Exact(paths(&["x"])));
+    }
+}
+
+mod composition_order_test {
+    use super::*;
+
+    #[test]
+    fn composition_order__given_dependencies__orders_dependents_first() {
+        let mut dependencies = HashMap::new();
+        dependencies.insert("python", vec![]);
+        dependencies.insert("numpy", vec!["python"]);
+        dependencies.insert("maya", vec!["numpy", "python"]);
+        assert_eq!(composition_order(&dependencies, &["maya"]), vec!["maya", "numpy", "python"]);
+    }
+
+    #[test]
+    fn composition_order__given_ties__orders_by_request_then_name() {
+        let mut dependencies = HashMap::new();
+        dependencies.insert("zlib", vec![]);
+        dependencies.insert("abc", vec![]);
+        dependencies.insert("nuke", vec!["zlib"]);
+        dependencies.insert("houdini", vec!["zlib", "abc"]);
+        assert_eq!(
+            composition_order(&dependencies, &["nuke", "houdini"]),
+            vec!["nuke", "houdini", "abc", "zlib"]
+        );
+    }
+
+    #[test]
+    fn composition_order__given_unknown_dependency__ignores_it() {
+        let mut dependencies = HashMap::new();
+        dependencies.insert("maya", vec!["missing"]);
+        assert_eq!(composition_order(&dependencies, &["maya"]), vec!["maya"]);
+    }
+
+    #[test]
+    fn composition_order__given_cycle__breaks_it_at_earliest_package() {
+        let mut dependencies = HashMap::new();
+        dependencies.insert("a", vec!["b"]);
+        dependencies.insert("b", vec!["a"]);
+        dependencies.insert("root", vec!["b"]);
+        dependencies.insert("c", vec!["a"]);
+        assert_eq!(composition_order(&dependencies, &["root", "c"]), vec!["root", "c", "a", "b"]);
+    }
+
+    #[test]
+    fn composition_order__given_same_input__is_stable() {
+        let dependencies = (0..20)
+            .map(|i| (PACKAGE_NAMES[i], vec![]))
+            .collect::<HashMap<_, Vec<&str>>>();
+        let first = composition_order(&dependencies, &[] as &[&str]);
+        for _ in 0..10 {
+            let dependencies = dependencies.clone().into_iter().collect::<HashMap<_, _>>();
+            assert_eq!(composition_order(&dependencies, &[] as &[&str]), first);
+        }
+        let mut sorted = first.clone();
+        sorted.sort();
+        assert_eq!(first, sorted);
+    }
+
+    const PACKAGE_NAMES: &[&str] = &[
+        "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m", "n", "o", "p", "q", "r", "s", "t",
+    ];
+}
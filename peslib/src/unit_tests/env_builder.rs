@@ -0,0 +1,191 @@
+#![allow(non_snake_case)]
+
+use super::*;
+use crate::manifest::PackageManifest;
+use crate::LockFile;
+use testutils::write_distribution;
+
+//-------------//
+//   HELPERS   //
+//-------------//
+
+// construct the manifest of package-version with the supplied body (targets, environment, etc)
+fn manifest(package: &str, version: &str, body: &str) -> PackageManifest {
+    let manifest = format!("---\nschema: 2\nname: {}\nversion: {}\ndescription: test\n{}", package, version, body);
+    PackageManifest::from_str(&manifest).expect("unable to parse manifest")
+}
+
+fn env_package(root: &str, manifest: PackageManifest) -> EnvPackage {
+    EnvPackage::new(root, Manifest::new(root, manifest), None)
+}
+
+fn base_env() -> HashMap<String, String> {
+    let mut env = HashMap::new();
+    env.insert("PATH".to_string(), "/usr/bin".to_string());
+    env.insert("HOME".to_string(), "/home/jgerber".to_string());
+    env
+}
+
+const PYTHON_BODY: &str = r#"
+targets:
+    run:
+        requires: {}
+environment:
+    PATH: prepend({root}/bin)
+    PYTHONHOME: "{root}"
+"#;
+
+const MAYA_BODY: &str = r#"
+targets:
+    run:
+        requires:
+            python: 3
+        environment:
+            MAYA_PYTHON: "{python.root}/bin/python"
+    build:
+        include:
+            - run
+        requires: {}
+        environment:
+            CPATH: prepend({root}/include)
+environment:
+    PATH: prepend({root}/bin)
+    MAYA_CONFIG: $HOME/maya
+"#;
+
+//-------------//
+//    TESTS    //
+//-------------//
+
+#[test]
+fn build__given_packages__composes_dependents_before_dependencies() {
+    let packages = vec![
+        env_package("/packages/python/3.7.0", manifest("python", "3.7.0", PYTHON_BODY)),
+        env_package("/packages/maya/2019.0.0", manifest("maya", "2019.0.0", MAYA_BODY)),
+    ];
+    let env = EnvBuilder::new(base_env()).build(&packages).unwrap();
+    assert_eq!(env["PATH"], "/packages/python/3.7.0/bin:/packages/maya/2019.0.0/bin:/usr/bin");
+    assert_eq!(env["PYTHONHOME"], "/packages/python/3.7.0");
+    assert_eq!(env["MAYA_PYTHON"], "/packages/python/3.7.0/bin/python");
+    assert_eq!(env["MAYA_CONFIG"], "/home/jgerber/maya");
+    assert_eq!(env["HOME"], "/home/jgerber");
+    assert!(!env.contains_key("CPATH"));
+}
+
+#[test]
+fn build__given_variant_with_requires__composes_variant_dependencies_after_dependent() {
+    // the plugin only depends upon alib via its variant, and would otherwise be composed after it, by name
+    let alib = "targets:\n    run:\n        requires: {}\nenvironment:\n    LIBPATH: prepend({root}/lib)\n";
+    let plugin = "targets:\n    run:\n        requires: {}\nenvironment:\n    LIBPATH: prepend({root}/lib)\nvariants:\n    - name: v1\n      requires:\n          alib: 1\n";
+    let packages = vec![
+        env_package("/packages/alib/1.0.0", manifest("alib", "1.0.0", alib)),
+        EnvPackage::new("/packages/plugin/1.0.0/v1", Manifest::new("/packages/plugin/1.0.0", manifest("plugin", "1.0.0", plugin)), Some("v1".to_string())),
+    ];
+    let env = EnvBuilder::new(base_env()).build(&packages).unwrap();
+    assert_eq!(env["LIBPATH"], "/packages/alib/1.0.0/lib:/packages/plugin/1.0.0/v1/lib");
+}
+
+#[test]
+fn build__given_target__applies_target_environment() {
+    let packages = vec![
+        env_package("/packages/python/3.7.0", manifest("python", "3.7.0", PYTHON_BODY)),
+        env_package("/packages/maya/2019.0.0", manifest("maya", "2019.0.0", MAYA_BODY)),
+    ];
+    let env = EnvBuilder::new(base_env()).with_target("build").build(&packages).unwrap();
+    assert_eq!(env["CPATH"], "/packages/maya/2019.0.0/include");
+    assert_eq!(env["MAYA_PYTHON"], "/packages/python/3.7.0/bin/python");
+}
+
+#[test]
+fn build__given_packages_in_any_order__yields_same_environment() {
+    let build = |names: &[&str]| {
+        let packages = names
+            .iter()
+            .map(|name| env_package(&format!("/packages/{}/1.0.0", name), manifest(name, "1.0.0", PYTHON_BODY)))
+            .collect::<Vec<_>>();
+        EnvBuilder::new(base_env()).with_requested(vec!["b"]).build(&packages).unwrap()
+    };
+    let env = build(&["a", "b", "c"]);
+    assert_eq!(env, build(&["c", "a", "b"]));
+    assert_eq!(env["PATH"], "/packages/c/1.0.0/bin:/packages/a/1.0.0/bin:/packages/b/1.0.0/bin:/usr/bin");
+}
+
+#[test]
+fn build_for_lockfile__given_locked_target__composes_environment() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = testutils::tempfile::TempDir::new().expect("unable to create temp dir");
+    write_distribution(repo_dir.path(), "python", "3.7.0", PYTHON_BODY);
+    write_distribution(repo_dir.path(), "maya", "2019.0.0", MAYA_BODY);
+    let repos = vec![PackageRepository::new(repo_dir.path(), &plugin_mgr)];
+
+    let mut lockfile = LockFile::new("pes env maya-2019", "jgerber");
+    lockfile.add_dist("run", "maya-2019.0.0").unwrap();
+    lockfile.add_dist("run", "python-3.7.0").unwrap();
+
+    let env = EnvBuilder::new(base_env()).build_for_lockfile(&repos, &lockfile).unwrap();
+    let python = repo_dir.path().join("python").join("3.7.0");
+    assert_eq!(env["MAYA_PYTHON"], format!("{}/bin/python", python.display()));
+    assert!(EnvBuilder::new(base_env()).with_target("build").build_for_lockfile(&repos, &lockfile).is_err());
+}
+
+#[test]
+fn build_for_lockfile__given_multiple_locked_targets__composes_selected_target() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = testutils::tempfile::TempDir::new().expect("unable to create temp dir");
+    write_distribution(repo_dir.path(), "python", "3.7.0", PYTHON_BODY);
+    write_distribution(repo_dir.path(), "python", "3.8.0", PYTHON_BODY);
+    write_distribution(repo_dir.path(), "maya", "2019.0.0", MAYA_BODY);
+    let repos = vec![PackageRepository::new(repo_dir.path(), &plugin_mgr)];
+
+    let mut lockfile = LockFile::new("pes env -d maya-2019.0.0 -t run -t build", "jgerber");
+    lockfile.add_dist("run", "maya-2019.0.0").unwrap();
+    lockfile.add_dist("run", "python-3.7.0").unwrap();
+    lockfile.add_dist("build", "maya-2019.0.0").unwrap();
+    lockfile.add_dist("build", "python-3.8.0").unwrap();
+
+    let builder = EnvBuilder::new(base_env());
+    let run = builder.clone().build_for_lockfile(&repos, &lockfile).unwrap();
+    let build = builder.with_target("build").build_for_lockfile(&repos, &lockfile).unwrap();
+    assert_eq!(run["PYTHONHOME"], repo_dir.path().join("python").join("3.7.0").display().to_string());
+    assert_eq!(build["PYTHONHOME"], repo_dir.path().join("python").join("3.8.0").display().to_string());
+    assert!(!run.contains_key("CPATH"));
+    assert!(build.contains_key("CPATH"));
+}
+
+#[test]
+fn from_solution__given_missing_distribution__fails() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = testutils::tempfile::TempDir::new().expect("unable to create temp dir");
+    write_distribution(repo_dir.path(), "python", "3.7.0", PYTHON_BODY);
+    let repos = vec![PackageRepository::new(repo_dir.path(), &plugin_mgr)];
+
+    let mut solution = SelectedDependencies::default();
+    solution.insert("python".to_string(), SemanticVersion::new(3, 7, 0, crate::ReleaseType::Release));
+    solution.insert("maya".to_string(), SemanticVersion::new(2019, 0, 0, crate::ReleaseType::Release));
+    match EnvPackage::from_solution(&repos, &solution, &VariantMap::new()) {
+        Err(PesError::MissingManifests(missing)) => assert_eq!(missing, vec!["maya-2019.0.0".to_string()]),
+        other => panic!("expected MissingManifests. got {:?}", other),
+    }
+}
+
+#[test]
+fn from_lockfile__given_recorded_distribution__prefers_recorded_path() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let locked_dir = testutils::tempfile::TempDir::new().expect("unable to create temp dir");
+    let repo_dir = testutils::tempfile::TempDir::new().expect("unable to create temp dir");
+    write_distribution(locked_dir.path(), "python", "3.7.0", PYTHON_BODY);
+    write_distribution(repo_dir.path(), "python", "3.7.0", PYTHON_BODY);
+
+    let mut lockfile = LockFile::new("pes env python-3.7.0", "jgerber");
+    lockfile.add_dist("run", "python-3.7.0").unwrap();
+    lockfile.record_distributions(&[PackageRepository::new(locked_dir.path(), &plugin_mgr)]).unwrap();
+
+    let repos = vec![PackageRepository::new(repo_dir.path(), &plugin_mgr)];
+    let packages = EnvPackage::from_lockfile(&repos, &lockfile, "run").unwrap();
+    assert_eq!(packages[0].root, locked_dir.path().join("python").join("3.7.0"));
+
+    // should the recorded distribution disappear, we fall back to searching the repositories
+    std::fs::remove_dir_all(locked_dir.path().join("python")).unwrap();
+    let packages = EnvPackage::from_lockfile(&repos, &lockfile, "run").unwrap();
+    assert_eq!(packages[0].root, repo_dir.path().join("python").join("3.7.0"));
+}
@@ -3,7 +3,7 @@
 
 use super::*;
 use crate::ReleaseType;
-use testutils::{rand_file, tempfile};
+use testutils::{rand_file, tempfile, write_distribution};
 use std::fs::File;
 //use std::io::prelude::*;
 
@@ -52,10 +52,21 @@ fn add_dist__when_given_a_new_target_and_dist__succeeds() {
     lock.insert("run".to_string(), versionmap);
 
     let expect = LockFile {
-        schema: 1,
+        schema: LOCKFILE_SCHEMA,
         request: String::new(),
         author: "jgerber".to_string(),
+        timestamp: lockfile.timestamp.clone(),
+        hostname: None,
+        pes_version: Some(PES_VERSION.to_string()),
+        repos: Vec::new(),
+        include_pre: false,
+        root_distribution: None,
+        transitive: None,
         lock,
+        variants: LockVariantMap::new(),
+        distributions: LockDistributionMap::new(),
+        env: LockEnvMap::new(),
+        constraints: Vec::new(),
     };
     assert_eq!(lockfile, expect);
 }
@@ -72,10 +83,21 @@ fn add_dist__when_given_a_existing_target_and_dist__succeeds() {
     lock.insert("run".to_string(), versionmap);
 
     let expect = LockFile {
-        schema: 1,
+        schema: LOCKFILE_SCHEMA,
         request: String::new(),
         author: "jgerber".to_string(),
+        timestamp: lockfile.timestamp.clone(),
+        hostname: None,
+        pes_version: Some(PES_VERSION.to_string()),
+        repos: Vec::new(),
+        include_pre: false,
+        root_distribution: None,
+        transitive: None,
         lock,
+        variants: LockVariantMap::new(),
+        distributions: LockDistributionMap::new(),
+        env: LockEnvMap::new(),
+        constraints: Vec::new(),
     };
     assert_eq!(lockfile, expect);
 }
@@ -133,6 +155,15 @@ fn dists_for__when_given_valid_target__returns_some_iter_preserving_insertion_or
     assert_eq!(result, expected);
 }
 
+#[test]
+fn packages_for__when_given_target__returns_packages_in_insertion_order() {
+    let mut lockfile = LockFile::new("", "jgerber");
+    lockfile.add_dist("run", "zebra-0.2.3").unwrap();
+    lockfile.add_dist("run", "maya-1.0.0").unwrap();
+    assert_eq!(lockfile.packages_for("run"), vec!["zebra", "maya"]);
+    assert!(lockfile.packages_for("build").is_empty());
+}
+
 #[test]
 fn dists_for__when_given_invalid_target__returns_None() {
     let mut lockfile = LockFile::new("", "jgerber");
@@ -153,4 +184,275 @@ fn to_writer__when_given_valid_file__writes_file() {
     lockfile.to_writer(&mut file, true).expect("unable to write tempfile");
     let lockfile2 = LockFile::from_file(file.path()).expect("unable to open temp file");
     assert_eq!(lockfile, lockfile2);
-}
\ No newline at end of file
+}
+#[test]
+fn add_variant__round_trips_through_toml() {
+    let mut lockfile = LockFile::new("", "jgerber");
+    lockfile.add_dist("run", "maya_plugin-1.0.0").unwrap();
+    lockfile.add_variant("run", "maya_plugin", "maya2019");
+
+    let mut output = Vec::new();
+    lockfile.to_writer(&mut output, true).expect("unable to write lockfile");
+    let lockfile = LockFile::from_str(std::str::from_utf8(&output).unwrap()).expect("unable to read lockfile");
+    assert_eq!(lockfile.variant("run", "maya_plugin"), Some("maya2019"));
+    assert_eq!(lockfile.variant("build", "maya_plugin"), None);
+    assert_eq!(lockfile.variants_for("run").len(), 1);
+}
+
+#[test]
+fn from_str__given_lockfile_without_variants__has_no_variants() {
+    let lockfile = LockFile::from_str(LOCKFILE1).unwrap();
+    assert!(lockfile.variants_for("run").is_empty());
+}
+
+#[test]
+fn fnv1a_hash__given_bytes__matches_reference_values() {
+    assert_eq!(fnv1a_hash(b""), "cbf29ce484222325");
+    assert_eq!(fnv1a_hash(b"a"), "af63dc4c8601ec8c");
+    assert_eq!(fnv1a_hash(b"foobar"), "85944171f73967e8");
+}
+
+#[test]
+fn from_str__given_schema_1_lockfile__succeeds() {
+    let lockfile = LockFile::from_str(LOCKFILE1).unwrap();
+    assert_eq!(lockfile.schema(), 1);
+    assert!(lockfile.verify().is_empty());
+    assert_eq!(lockfile.env_for("run"), None);
+}
+
+#[test]
+fn from_str__given_unsupported_schema__fails() {
+    let lockfile = LOCKFILE1.replace("schema = 1", "schema = 3");
+    match LockFile::from_str(&lockfile) {
+        Err(PesError::UnsupportedLockFileSchema { schema, latest }) => {
+            assert_eq!(schema, 3);
+            assert_eq!(latest, LOCKFILE_SCHEMA as u64);
+        }
+        other => panic!("expected UnsupportedLockFileSchema. got {:?}", other),
+    }
+}
+
+#[test]
+fn record_distributions__given_repo__records_paths_and_hashes() {
+    let plugin_mgr = crate::PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = tempfile::TempDir::new().expect("unable to create temp dir");
+    let manifest = write_distribution(repo_dir.path(), "maya", "1.0.0", "");
+    let repos = vec![PackageRepository::new(repo_dir.path(), &plugin_mgr)];
+
+    let mut lockfile = LockFile::new("", "jgerber");
+    lockfile.add_dist("run", "maya-1.0.0").unwrap();
+    lockfile.add_dist("build", "maya-1.0.0").unwrap();
+    lockfile.record_distributions(&repos).unwrap();
+
+    let version = SemanticVersion::new(1, 0, 0, ReleaseType::Release);
+    let locked = lockfile.distribution("maya", &version).expect("distribution should be recorded");
+    assert_eq!(locked.path, repo_dir.path().join("maya").join("1.0.0"));
+    assert_eq!(locked.repo, repo_dir.path());
+    assert_eq!(locked.manifest, manifest);
+    assert_eq!(locked.manifest_hash, fnv1a_hash(&std::fs::read(&manifest).unwrap()));
+
+    // round trips through toml
+    let mut output = Vec::new();
+    lockfile.to_writer(&mut output, true).unwrap();
+    assert_eq!(LockFile::from_str(std::str::from_utf8(&output).unwrap()).unwrap(), lockfile);
+
+    lockfile.add_dist("run", "nuke-1.0.0").unwrap();
+    match lockfile.record_distributions(&repos) {
+        Err(PesError::MissingManifests(missing)) => assert_eq!(missing, vec!["nuke-1.0.0".to_string()]),
+        other => panic!("expected MissingManifests. got {:?}", other),
+    }
+}
+
+#[test]
+fn verify__given_changed_repository__reports_mismatches() {
+    let repo_dir = tempfile::TempDir::new().expect("unable to create temp dir");
+    let maya = write_distribution(repo_dir.path(), "maya", "1.0.0", "");
+    let nuke = write_distribution(repo_dir.path(), "nuke", "1.0.0", "");
+    let mut lockfile = LockFile::new("", "jgerber");
+    lockfile.add_distribution("maya-1.0.0", LockedDistribution::from_manifest(repo_dir.path(), &maya).unwrap());
+    lockfile.add_distribution("nuke-1.0.0", LockedDistribution::from_manifest(repo_dir.path(), &nuke).unwrap());
+    assert!(lockfile.verify().is_empty());
+
+    std::fs::write(&maya, "---\nschema: 2\nname: maya\nversion: 1.0.0\ndescription: changed\n").unwrap();
+    std::fs::remove_dir_all(repo_dir.path().join("nuke")).unwrap();
+    assert_eq!(
+        lockfile.verify(),
+        vec![
+            LockMismatch::ManifestChanged { distribution: "maya-1.0.0".into(), manifest: maya },
+            LockMismatch::MissingDistribution {
+                distribution: "nuke-1.0.0".into(),
+                path: repo_dir.path().join("nuke").join("1.0.0"),
+            },
+        ]
+    );
+}
+
+#[test]
+fn verify_env__given_recorded_env__reports_changed_vars() {
+    let env = |vars: &[(&str, &str)]| vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<std::collections::HashMap<_, _>>();
+    let mut lockfile = LockFile::new("", "jgerber");
+    assert_eq!(lockfile.verify_env("run", &env(&[("PATH", "/bin")])), None);
+
+    lockfile.set_env("run", env(&[("PATH", "/bin"), ("HOME", "/home/jgerber")]));
+    assert_eq!(lockfile.env_for("run").unwrap().keys().collect::<Vec<_>>(), vec!["HOME", "PATH"]);
+    assert_eq!(lockfile.verify_env("run", &env(&[("PATH", "/bin"), ("HOME", "/home/jgerber")])), None);
+    assert_eq!(
+        lockfile.verify_env("run", &env(&[("PATH", "/usr/bin"), ("EDITOR", "vim")])),
+        Some(LockMismatch::EnvironmentChanged {
+            target: "run".into(),
+            vars: vec!["EDITOR".into(), "HOME".into(), "PATH".into()],
+        })
+    );
+}
+
+#[test]
+fn rfc3339__given_time__formats_utc_timestamp() {
+    assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+    assert_eq!(rfc3339(UNIX_EPOCH + std::time::Duration::from_secs(951_782_400)), "2000-02-29T00:00:00Z");
+    assert_eq!(rfc3339(UNIX_EPOCH + std::time::Duration::from_secs(1_614_834_367)), "2021-03-04T05:06:07Z");
+}
+
+#[test]
+fn rfc3339__given_known_dates__formats_civil_date() {
+    let at = |secs| rfc3339(UNIX_EPOCH + std::time::Duration::from_secs(secs));
+    assert_eq!(at(946_684_799), "1999-12-31T23:59:59Z");
+    assert_eq!(at(1_709_210_096), "2024-02-29T12:34:56Z");
+    // 2100 is not a leap year
+    assert_eq!(at(4_107_542_399), "2100-02-28T23:59:59Z");
+    assert_eq!(at(4_107_542_400), "2100-03-01T00:00:00Z");
+    // times before the epoch are clamped to it
+    assert_eq!(rfc3339(UNIX_EPOCH - std::time::Duration::from_secs(1)), "1970-01-01T00:00:00Z");
+}
+
+#[test]
+fn from_str__given_constraint__parses_package_range_and_weakness() {
+    let constraint = LockConstraint::from_str("~python-3.7+<4").unwrap();
+    assert_eq!(constraint, LockConstraint { package: "python".into(), range: Some("3.7+<4".into()), weak: true });
+    assert_eq!(constraint.to_string(), "~python-3.7+<4");
+    let constraint = LockConstraint::from_str("maya").unwrap();
+    assert_eq!(constraint, LockConstraint { package: "maya".into(), range: None, weak: false });
+    assert_eq!(constraint.to_string(), "maya");
+    assert!(LockConstraint::from_str("maya-2019+<").is_err());
+}
+
+#[test]
+fn from_str__given_padded_or_weak_constraint__round_trips() {
+    for input in &[" ~foo-1.2 ", "~foo-1.2", "foo-1.2 | 2", "\tfoo-1.2+<2\n"] {
+        let constraint = LockConstraint::from_str(input).unwrap();
+        assert_eq!(LockConstraint::from_str(&constraint.to_string()).unwrap(), constraint, "{}", input);
+    }
+    assert_eq!(LockConstraint::from_str(" ~foo-1.2 ").unwrap().to_string(), "~foo-1.2");
+    assert_eq!(LockConstraint::from_str("foo-1.2 | 2").unwrap().range.as_deref(), Some("1.2 | 2"));
+    // as with the solve, whitespace may not separate the name from the range, or from the tilde
+    assert!(LockConstraint::from_str("foo- 1.2").is_err());
+    assert!(LockConstraint::from_str("~ foo-1.2").is_err());
+}
+
+#[test]
+fn to_writer__given_provenance__round_trips_through_toml() {
+    let lockfile = LockFile::new("pes env maya-2019 ~python-3", "jgerber")
+        .with_hostname("bigboy")
+        .with_repos(vec![PathBuf::from("/packages"), PathBuf::from("/dev/packages")])
+        .with_include_pre(true)
+        .with_constraints(&["maya-2019", "~python-3"])
+        .unwrap();
+    let mut lockfile = lockfile;
+    lockfile.add_dist("run", "maya-2019.0.0").unwrap();
+
+    let mut output = Vec::new();
+    lockfile.to_writer(&mut output, true).unwrap();
+    let read = LockFile::from_str(std::str::from_utf8(&output).unwrap()).unwrap();
+    assert_eq!(read, lockfile);
+    assert_eq!(read.hostname(), Some("bigboy"));
+    assert_eq!(read.pes_version(), Some(PES_VERSION));
+    assert!(read.timestamp().is_some());
+    assert_eq!(read.repos().len(), 2);
+    assert!(read.include_pre());
+    assert_eq!(read.constraints().iter().map(ToString::to_string).collect::<Vec<_>>(), vec!["maya-2019", "~python-3"]);
+    assert_eq!(read.root_distribution(), None);
+}
+
+#[test]
+fn with_root_distribution__records_distribution_and_policy() {
+    let policy = "run,gcc:build".parse::<TargetPolicy>().unwrap();
+    let lockfile = LockFile::new("", "jgerber").with_root_distribution("foo-1.0.0", &policy);
+    assert_eq!(lockfile.root_distribution(), Some("foo-1.0.0"));
+    assert_eq!(lockfile.transitive(), Some("run,gcc:build"));
+}
+
+#[test]
+fn from_str__given_schema_1_lockfile__has_no_provenance() {
+    let lockfile = LockFile::from_str(LOCKFILE1).unwrap();
+    assert_eq!(lockfile.request(), "pez env -p bar-2.4.3 -t run -t build");
+    assert_eq!(lockfile.author(), "jgerber");
+    assert_eq!(lockfile.timestamp(), None);
+    assert_eq!(lockfile.hostname(), None);
+    assert!(!lockfile.include_pre());
+    assert!(lockfile.constraints().is_empty());
+}
+
+#[test]
+fn diff__given_changed_lockfiles__reports_changes_per_target() {
+    let mut from = LockFile::new("", "jgerber");
+    from.add_dist("run", "maya-2019.0.0").unwrap();
+    from.add_dist("run", "python-3.7.0").unwrap();
+    from.add_dist("run", "numpy-1.19.0").unwrap();
+    from.add_dist("build", "gcc-9.1.0").unwrap();
+    let mut to = LockFile::new("", "jgerber");
+    to.add_dist("run", "python-3.6.0").unwrap();
+    to.add_dist("run", "maya-2020.0.0").unwrap();
+    to.add_dist("run", "pyside-2.0.0").unwrap();
+    to.add_dist("build", "gcc-9.1.0").unwrap();
+    to.add_dist("test", "pytest-6.0.0").unwrap();
+
+    let version = |major, minor| SemanticVersion::new(major, minor, 0, ReleaseType::Release);
+    let diff = from.diff(&to);
+    assert_eq!(diff.keys().collect::<Vec<_>>(), vec!["run", "test"]);
+    assert_eq!(
+        diff["run"],
+        vec![
+            LockChange::Upgraded { package: "maya".into(), from: version(2019, 0), to: version(2020, 0) },
+            LockChange::Removed { package: "numpy".into(), version: version(1, 19) },
+            LockChange::Added { package: "pyside".into(), version: version(2, 0) },
+            LockChange::Downgraded { package: "python".into(), from: version(3, 7), to: version(3, 6) },
+        ]
+    );
+    assert_eq!(diff["test"], vec![LockChange::Added { package: "pytest".into(), version: version(6, 0) }]);
+    assert_eq!(diff["run"][0].to_string(), "^ maya 2019.0.0 -> 2020.0.0");
+    assert!(from.diff(&from).is_empty());
+}
+
+#[test]
+fn diff__given_changed_variant__reports_variant_change() {
+    let mut from = LockFile::new("", "jgerber");
+    from.add_dist("run", "maya_plugin-1.0.0").unwrap();
+    from.add_variant("run", "maya_plugin", "maya2018");
+    let mut to = LockFile::new("", "jgerber");
+    to.add_dist("run", "maya_plugin-1.0.0").unwrap();
+    to.add_variant("run", "maya_plugin", "maya2019");
+
+    let diff = from.diff(&to);
+    let change = LockChange::Variant {
+        package: "maya_plugin".into(),
+        version: SemanticVersion::new(1, 0, 0, ReleaseType::Release),
+        from: "maya2018".into(),
+        to: "maya2019".into(),
+    };
+    assert_eq!(diff["run"], vec![change]);
+    assert_eq!(diff["run"][0].to_string(), "~ maya_plugin-1.0.0 maya2018 -> maya2019");
+}
+
+#[test]
+fn pins_for__given_unpinned_packages__pins_the_rest() {
+    let lockfile = LockFile::from_str(LOCKFILE1).unwrap();
+    let pins = lockfile.pins_for("build", &["bar"]);
+    assert_eq!(
+        pins,
+        vec![
+            ("foo".to_string(), SemanticVersion::new(1, 2, 4, ReleaseType::Release)),
+            ("somelib".to_string(), SemanticVersion::new(1, 2, 3, ReleaseType::Release)),
+        ]
+    );
+    assert_eq!(lockfile.pins_for("run", &[] as &[&str]).len(), 2);
+    assert!(lockfile.pins_for("test", &["foo"]).is_empty());
+}
@@ -2,8 +2,9 @@
 
 use super::*;
 use crate::manifest::package_target::PackageTarget;
+use crate::manifest::{ManifestFormat, MANIFEST_SCHEMA};
 use crate::{SemanticVersion, ReleaseType};
-//use pubgrub::range::Range;
+use pubgrub::range::Range;
 use crate::DistributionRange;
 use crate::EnvMap;
 use crate::TargetMap;
@@ -34,6 +35,25 @@ targets:
             maya: 1.2.3+<4
 "#;
 
+// P1 in toml, following the layout described in the Readme
+const TOML_P1: &str = r#"
+# the toml flavor of P1
+schema = 1
+name = "mypackage"
+version = "1.2.3"
+description = "this is the description"
+
+[target.run.requires]
+maya-plugins = "^4.3"
+maya-core = "2+<4"
+
+[target.build]
+include-targets = ["run"]
+
+[target.build.requires]
+maya = "1.2.3+<4"
+"#;
+
 const P2: &str = r#"
 ---
 schema: 1
@@ -95,12 +115,13 @@ fn from_str_unchecked__succeeds() {
     assert_eq!(
         manifest.unwrap(),
         PackageManifest {
-            schema: 1,
+            schema: MANIFEST_SCHEMA,
             name: "mypackage".into(),
             version: SemanticVersion::new(1, 2, 3,ReleaseType::Release),
             description: "this is the description".into(),
             targets: target_map,
-            environment: EnvMap::new()
+            environment: EnvMap::new(),
+            variants: Vec::new(),
         }
     );
 }
@@ -122,12 +143,13 @@ fn from_str__succeeds_when_given_valid_manifest_str() {
     assert_eq!(
         manifest.unwrap(),
         PackageManifest {
-            schema: 1,
+            schema: MANIFEST_SCHEMA,
             name: "mypackage".into(),
             version: SemanticVersion::new(1, 2, 3,ReleaseType::Release),
             description: "this is the description".into(),
             targets: target_map,
-            environment: EnvMap::new()
+            environment: EnvMap::new(),
+            variants: Vec::new(),
         }
     );
 }
@@ -140,12 +162,13 @@ fn from_str__succeeds_when_given_valid_manifest_str_without_targets() {
     assert_eq!(
         manifest.unwrap(),
         PackageManifest {
-            schema: 1,
+            schema: MANIFEST_SCHEMA,
             name: "mypackage".into(),
             version: SemanticVersion::new(1, 2, 3,ReleaseType::Release),
             description: "this is the description".into(),
             targets: target_map,
-            environment: EnvMap::new()
+            environment: EnvMap::new(),
+            variants: Vec::new(),
         }
     );
 }
@@ -229,3 +252,357 @@ fn distribution__returns_name_version() {
     let dist = manifest.distribution();
     assert_eq!(dist.as_str(), "foo-0.1.0");
 }
+
+const VARIANTS_MANIFEST: &str = r#"
+---
+schema: 1
+name: maya_plugin
+version: 1.0.0
+description: this is the description
+
+targets:
+    run:
+        requires:
+            openexr: 2
+variants:
+    - name: maya2019
+      requires:
+          maya: 2019
+      environment:
+          MAYA_PLUGIN_PATH: "prepend({root}/plugins)"
+    - name: maya2018
+      requires:
+          maya: 2018
+"#;
+
+#[test]
+fn from_str__given_manifest_with_variants__succeeds() {
+    let manifest = PackageManifest::from_str(VARIANTS_MANIFEST).expect("unable to parse manifest");
+    assert!(manifest.has_variants());
+    assert_eq!(manifest.variants.len(), 2);
+    let variant = manifest.variant("maya2019").expect("missing variant");
+    assert_eq!(variant.environment.len(), 1);
+    assert_eq!(variant.get_all_requires().unwrap(), vec![DistributionRange::from_str("maya-2019").unwrap()]);
+    assert_eq!(variant.root("/repo/maya_plugin/1.0.0"), PathBuf::from("/repo/maya_plugin/1.0.0/maya2019"));
+    assert!(manifest.variant("maya2020").is_none());
+}
+
+#[test]
+fn validate__given_duplicate_variants__fails() {
+    let mut manifest = PackageManifest::from_str(VARIANTS_MANIFEST).unwrap();
+    manifest.variants[1].name = "maya2019".to_string();
+    assert!(manifest.validate().is_err());
+}
+
+#[test]
+fn validate__given_invalid_variant_name__fails() {
+    let mut manifest = PackageManifest::from_str(VARIANTS_MANIFEST).unwrap();
+    manifest.variants[1].name = "maya/2018".to_string();
+    assert!(manifest.validate().is_err());
+}
+
+const CONFLICTS_MANIFEST: &str = r#"
+---
+schema: 1
+name: mypackage
+version: 1.2.3
+description: this is the description
+
+targets:
+    run:
+        requires:
+            maya: 2019
+        conflicts:
+            openexr: 1+<2
+    build:
+        include:
+            - run
+        requires:
+            cmake: 3
+"#;
+
+#[test]
+fn get_conflicts__given_target_with_includes__returns_conflicts() {
+    let manifest = PackageManifest::from_str(CONFLICTS_MANIFEST).expect("unable to parse manifest");
+    let expected = vec![DistributionRange::from_strs("openexr", "1+<2").unwrap()];
+    assert_eq!(manifest.get_conflicts("run").unwrap(), expected);
+    assert_eq!(manifest.get_conflicts("build").unwrap(), expected);
+}
+
+#[test]
+fn validate__given_package_both_required_and_conflicting__fails() {
+    let mut manifest = PackageManifest::from_str(CONFLICTS_MANIFEST).unwrap();
+    manifest.targets.get_mut("run").unwrap().conflicts("maya", "2018");
+    assert!(manifest.validate().is_err());
+}
+
+#[test]
+fn get_weak_requires__given_target__returns_weak_requires() {
+    let mut manifest = PackageManifest::from_str(CONFLICTS_MANIFEST).unwrap();
+    manifest.targets.get_mut("run").unwrap().weak_requires("houdini", "18+<19");
+    let expected = vec![DistributionRange::from_strs("houdini", "18+<19").unwrap()];
+    assert_eq!(manifest.get_weak_requires("build").unwrap(), expected);
+    assert!(manifest.validate().is_ok());
+}
+
+#[test]
+fn validate__given_package_both_required_and_weakly_required__fails() {
+    let mut manifest = PackageManifest::from_str(CONFLICTS_MANIFEST).unwrap();
+    manifest.targets.get_mut("run").unwrap().weak_requires("maya", "2018");
+    assert!(manifest.validate().is_err());
+}
+
+#[test]
+fn normalize__given_target__rewrites_ranges_in_canonical_form() {
+    let mut target = PackageTarget::new();
+    target.requires("maya-core", "2+<3");
+    target.requires("maya-plugins", "^4.3");
+    target.conflicts("houdini", "<=18.5");
+    target.weak_requires("nuke", ">=12!12.1.3");
+    target.normalize().unwrap();
+    assert_eq!(target.requires.get("maya-core").unwrap(), "2.*");
+    assert_eq!(target.requires.get("maya-plugins").unwrap(), "4.3.*");
+    assert_eq!(target.conflicts.get("houdini").unwrap(), "<18.6.0");
+    assert_eq!(target.weak_requires.get("nuke").unwrap(), ">=12.0.0!12.1.3");
+
+    target.requires("maya", "2018+");
+    assert!(target.normalize().is_err());
+    assert_eq!(target.requires.get("maya-core").unwrap(), "2.*");
+}
+
+#[test]
+fn requires_range__given_range__inserts_canonical_range() {
+    let mut target = PackageTarget::new();
+    let range = Range::between(SemanticVersion::new(1, 2, 3, ReleaseType::Release), SemanticVersion::new(1, 3, 0, ReleaseType::Release));
+    target.requires_range("maya", &range);
+    assert_eq!(target.requires.get("maya").unwrap(), "~1.2.3");
+    assert_eq!(target.get_requires("maya").unwrap(), range);
+}
+
+#[test]
+fn from_str__given_toml_manifest__matches_yaml_manifest() {
+    let manifest = PackageManifest::from_str(TOML_P1).unwrap();
+    assert_eq!(manifest, PackageManifest::from_str(P1).unwrap());
+}
+
+#[test]
+fn to_string_as__given_toml__round_trips() {
+    let mut manifest = PackageManifest::from_str(CONFLICTS_MANIFEST).unwrap();
+    manifest.environment.insert("LD_LIBRARY_PATH".into(), "prepend({root}/lib)".into());
+    let toml = manifest.to_string_as(ManifestFormat::Toml).unwrap();
+    assert_eq!(ManifestFormat::detect(&toml), ManifestFormat::Toml);
+    assert_eq!(PackageManifest::from_str(&toml).unwrap(), manifest);
+}
+
+#[test]
+fn ManifestFormat_detect__given_manifests__detects_format() {
+    assert_eq!(ManifestFormat::detect(P1), ManifestFormat::Yaml);
+    assert_eq!(ManifestFormat::detect(TOML_P1), ManifestFormat::Toml);
+    assert_eq!(ManifestFormat::detect("[target.run]\nrequires = {}"), ManifestFormat::Toml);
+    assert_eq!(ManifestFormat::detect("description: a=b"), ManifestFormat::Yaml);
+    assert_eq!(ManifestFormat::from_path("foo/manifest.toml"), Some(ManifestFormat::Toml));
+    assert_eq!(ManifestFormat::from_path("foo/manifest.yml"), Some(ManifestFormat::Yaml));
+    assert_eq!(ManifestFormat::from_path("foo/manifest"), None);
+}
+
+#[test]
+fn to_file__given_toml_path__writes_toml_manifest() {
+    let dir = testutils::tempfile::TempDir::new().expect("unable to create temp dir");
+    let path = dir.path().join("manifest.toml");
+    let manifest = PackageManifest::from_str(P1).unwrap();
+    manifest.to_file(&path).unwrap();
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(ManifestFormat::detect(&contents), ManifestFormat::Toml);
+    assert_eq!(PackageManifest::from_file(&path).unwrap(), manifest);
+}
+
+#[test]
+fn from_str_migrated__given_schema_1__migrates_to_latest_schema() {
+    let (manifest, schema) = PackageManifest::from_str_migrated(TOML_P1, ManifestFormat::Toml).unwrap();
+    assert_eq!(schema, 1);
+    assert_eq!(manifest.schema, MANIFEST_SCHEMA);
+    let (manifest, schema) = PackageManifest::from_str_migrated(&manifest.to_string_as(ManifestFormat::Yaml).unwrap(), ManifestFormat::Yaml).unwrap();
+    assert_eq!(schema, MANIFEST_SCHEMA);
+    assert_eq!(manifest, PackageManifest::from_str(P1).unwrap());
+}
+
+#[test]
+fn from_str_migrated__given_schema_1__preserves_scalar_text() {
+    let manifest = P1.replace("maya: 1.2.3+<4", "maya: 3.10");
+    let (manifest, schema) = PackageManifest::from_str_migrated(&manifest, ManifestFormat::Yaml).unwrap();
+    assert_eq!(schema, 1);
+    assert_eq!(manifest.targets["build"].requires["maya"], "3.10");
+}
+
+#[test]
+fn from_str_migrated__given_schema_2__does_not_rename_legacy_keys() {
+    let (manifest, _) = PackageManifest::from_str_migrated(&TOML_P1.replace("schema = 1", "schema = 2"), ManifestFormat::Toml).unwrap();
+    assert!(manifest.targets.is_empty());
+}
+
+#[test]
+fn from_str_migrated__given_schema_1_with_both_legacy_and_current_keys__fails() {
+    let manifest = format!("{}\n[targets.lint]\nrequires = {{}}\n", TOML_P1);
+    match PackageManifest::from_str_migrated(&manifest, ManifestFormat::Toml) {
+        Err(PesError::DuplicateKey(key)) => assert_eq!(key, "targets"),
+        other => panic!("expected DuplicateKey. got {:?}", other),
+    }
+}
+
+#[test]
+fn from_str__given_unsupported_schema__fails() {
+    for schema in &[0, MANIFEST_SCHEMA + 1] {
+        let manifest = P2.replace("schema: 1", &format!("schema: {}", schema));
+        match PackageManifest::from_str(&manifest) {
+            Err(PesError::UnsupportedSchema { schema: s, latest }) => {
+                assert_eq!(s, *schema as u64);
+                assert_eq!(latest, MANIFEST_SCHEMA as u64);
+            }
+            other => panic!("expected UnsupportedSchema. got {:?}", other),
+        }
+    }
+}
+
+const NESTED_MANIFEST: &str = r#"
+---
+schema: 2
+name: mypackage
+version: 1.2.3
+description: this is the description
+
+targets:
+    run:
+        requires:
+            maya: 2019+<2021
+        conflicts:
+            openexr: 1.*
+    build:
+        include:
+            - run
+        requires:
+            cmake: 3
+        conflicts:
+            openexr: 2.*
+    test:
+        include:
+            - build
+        requires:
+            pytest: 6
+    lint:
+        include:
+            - test
+            - run
+        requires:
+            maya: 2020.*
+"#;
+
+#[test]
+fn resolve_includes__given_nested_includes__returns_includes_first() {
+    let manifest = PackageManifest::from_str(NESTED_MANIFEST).unwrap();
+    assert_eq!(manifest.resolve_includes("run").unwrap(), vec!["run"]);
+    assert_eq!(manifest.resolve_includes("test").unwrap(), vec!["run", "build", "test"]);
+    assert_eq!(manifest.resolve_includes("lint").unwrap(), vec!["run", "build", "test", "lint"]);
+    assert!(matches!(manifest.resolve_includes("docs"), Err(PesError::MissingKey(_))));
+}
+
+#[test]
+fn resolve_includes__given_circular_includes__fails() {
+    let mut manifest = PackageManifest::from_str(NESTED_MANIFEST).unwrap();
+    manifest.targets.get_mut("run").unwrap().include("test").unwrap();
+    match manifest.resolve_includes("build") {
+        Err(PesError::CircularInclude(cycle)) => assert_eq!(cycle, vec!["build", "run", "test", "build"]),
+        other => panic!("expected CircularInclude. got {:?}", other),
+    }
+    assert!(matches!(manifest.validate(), Err(PesError::CircularInclude(_))));
+}
+
+#[test]
+fn get_requires__given_nested_includes__returns_transitive_requires() {
+    let manifest = PackageManifest::from_str(NESTED_MANIFEST).unwrap();
+    let expected = vec![
+        DistributionRange::from_strs("maya", "2019+<2021").unwrap(),
+        DistributionRange::from_strs("cmake", "3").unwrap(),
+        DistributionRange::from_strs("pytest", "6").unwrap(),
+    ];
+    assert_eq!(manifest.get_requires("test").unwrap(), expected);
+}
+
+#[test]
+fn get_requires__given_package_required_by_several_targets__intersects_ranges() {
+    let manifest = PackageManifest::from_str(NESTED_MANIFEST).unwrap();
+    let requires = manifest.get_requires("lint").unwrap();
+    assert_eq!(requires.len(), 3);
+    assert_eq!(requires[0], DistributionRange::from_strs("maya", "2020.*").unwrap());
+}
+
+#[test]
+fn get_requires__given_incompatible_ranges__fails() {
+    let mut manifest = PackageManifest::from_str(NESTED_MANIFEST).unwrap();
+    manifest.targets.get_mut("lint").unwrap().requires("maya", "2021");
+    match manifest.get_requires("lint") {
+        Err(PesError::IncompatibleRanges { target, package, .. }) => {
+            assert_eq!(target, "lint");
+            assert_eq!(package, "maya");
+        }
+        other => panic!("expected IncompatibleRanges. got {:?}", other),
+    }
+    assert!(manifest.validate().is_err());
+}
+
+#[test]
+fn get_conflicts__given_package_conflicting_in_several_targets__unions_ranges() {
+    let manifest = PackageManifest::from_str(NESTED_MANIFEST).unwrap();
+    let expected = vec![DistributionRange::from_strs("openexr", "1+<3").unwrap()];
+    assert_eq!(manifest.get_conflicts("test").unwrap(), expected);
+}
+
+const TARGET_ENVIRONMENT_MANIFEST: &str = r#"
+---
+schema: 2
+name: mypackage
+version: 1.2.3
+description: this is the description
+
+targets:
+    run:
+        requires: {}
+        environment:
+            PATH: prepend({root}/bin)
+    build:
+        include:
+            - run
+        requires: {}
+        environment:
+            CPATH: prepend({root}/include)
+    docs:
+        requires: {}
+environment:
+    PYTHONPATH: prepend({root}/python)
+"#;
+
+#[test]
+fn get_environment__given_target_with_includes__returns_package_then_target_environments() {
+    let manifest = PackageManifest::from_str(TARGET_ENVIRONMENT_MANIFEST).unwrap();
+    let keys = |target| manifest.get_environment(target).unwrap().into_iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>();
+    assert_eq!(keys("build"), vec!["PYTHONPATH", "PATH", "CPATH"]);
+    assert_eq!(keys("run"), vec!["PYTHONPATH", "PATH"]);
+    assert_eq!(keys("docs"), vec!["PYTHONPATH"]);
+    // undefined targets fall back to run
+    assert_eq!(keys("test"), vec!["PYTHONPATH", "PATH"]);
+}
+
+#[test]
+fn from_str__given_toml_target_environment__matches_yaml() {
+    let manifest = PackageManifest::from_str(TARGET_ENVIRONMENT_MANIFEST).unwrap();
+    let toml = manifest.to_string_as(ManifestFormat::Toml).unwrap();
+    assert!(toml.contains("[targets.build.environment]"), "{}", toml);
+    assert_eq!(PackageManifest::from_str(&toml).unwrap(), manifest);
+}
+
+#[test]
+fn get_environment_dependencies__given_target__returns_requires_of_target_or_default() {
+    let manifest = PackageManifest::from_str(NESTED_MANIFEST).unwrap();
+    assert_eq!(manifest.get_environment_dependencies("test", None).unwrap(), vec!["maya", "cmake", "pytest"]);
+    // undefined targets fall back to run
+    assert_eq!(manifest.get_environment_dependencies("docs", None).unwrap(), vec!["maya"]);
+}
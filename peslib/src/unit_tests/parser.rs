@@ -101,6 +101,118 @@ mod semver_parsing {
         }
     }
 
+    #[test]
+    fn parse_package_name_and_numbered_pre_release() {
+        let result = parse_consuming_package_version("maya-1.2.3-rc.10");
+        assert_eq!(result.unwrap(), ("maya", SemanticVersion::new(1,2,3,ReleaseType::ReleaseCandidate).with_pre_release(10)));
+    }
+
+    #[test]
+    fn parse_semver_range_with_build_metadata() {
+        let result = parse_consuming_semver_range("1.2.3+build.77+<2").unwrap();
+        assert_eq!(
+            result, 
+            Range::between(SemanticVersion::new(1,2,3,ReleaseType::Release), SemanticVersion::new(2,0,0,ReleaseType::Release))
+        );
+        // an exact pre-release excludes the numbered pre-releases which follow it
+        let result = parse_consuming_semver_range("1.2.3-beta").unwrap();
+        assert!(result.contains(&SemanticVersion::new(1,2,3,ReleaseType::Beta).with_build("77")));
+        assert!(!result.contains(&SemanticVersion::new(1,2,3,ReleaseType::Beta).with_pre_release(1)));
+    }
+
+    #[test]
+    fn parse_weak_package_name_and_range() {
+        let result = parse_consuming_weak_package_range("~maya-2+<3");
+        assert_eq!(
+            result.unwrap(), 
+            ("maya", Range::between(SemanticVersion::new(2,0,0,ReleaseType::Release), SemanticVersion::new(3,0,0,ReleaseType::Release)))
+        );
+        // a weak requirement must carry the tilde
+        assert!(parse_consuming_weak_package_range("maya-2+<3").is_err());
+    }
+
+    fn release(major: u32, minor: u32, patch: u32) -> SemanticVersion {
+        SemanticVersion::new(major, minor, patch, ReleaseType::Release)
+    }
+
+    #[test]
+    fn parse_semver_range__given_comparisons__honors_precision() {
+        let ranges = vec![
+            (">=1.2", Range::higher_than(release(1,2,0))),
+            (">1.2", Range::higher_than(release(1,3,0))),
+            ("> 1.2.3", Range::higher_than(release(1,2,3).next())),
+            ("<1.2", Range::strictly_lower_than(release(1,2,0))),
+            ("<=1.2", Range::strictly_lower_than(release(1,3,0))),
+            ("<=1", Range::strictly_lower_than(release(2,0,0))),
+            ("<=1.2.3-beta", Range::strictly_lower_than(SemanticVersion::new(1,2,3,ReleaseType::Beta).next())),
+        ];
+        for (input, expected) in ranges {
+            assert_eq!(parse_consuming_semver_range(input).unwrap(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn parse_semver_range__given_exclusive_upper_bound__admits_pre_releases_of_bound() {
+        let pre_release = |major, minor, patch, release_type| SemanticVersion::new(major, minor, patch, release_type).with_pre_release(1);
+        let range = parse_consuming_semver_range("<1.2.3").unwrap();
+        assert!(range.contains(&pre_release(1,2,3,ReleaseType::Alpha)));
+        assert!(!range.contains(&release(1,2,3)));
+        let range = parse_consuming_semver_range("<=1.2").unwrap();
+        assert!(range.contains(&pre_release(1,3,0,ReleaseType::Beta)));
+        let range = parse_consuming_semver_range("1+<2").unwrap();
+        assert!(range.contains(&pre_release(2,0,0,ReleaseType::ReleaseCandidate)));
+        assert!(!range.contains(&release(2,0,0)));
+        // the pre-releases of an exclusive lower bound precede it, and so are not admitted
+        let range = parse_consuming_semver_range(">1.2").unwrap();
+        assert!(!range.contains(&pre_release(1,3,0,ReleaseType::Alpha)));
+    }
+
+    #[test]
+    fn parse_semver_range__given_tilde_and_wildcards__returns_expected_ranges() {
+        let ranges = vec![
+            ("~1.2.3", Range::between(release(1,2,3), release(1,3,0))),
+            ("~1.2", Range::between(release(1,2,0), release(1,3,0))),
+            ("~1", Range::between(release(1,0,0), release(2,0,0))),
+            ("1.2.*", Range::between(release(1,2,0), release(1,3,0))),
+            ("1.*", Range::between(release(1,0,0), release(2,0,0))),
+            ("*", Range::any()),
+        ];
+        for (input, expected) in ranges {
+            assert_eq!(parse_consuming_semver_range(input).unwrap(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn parse_semver_range__given_unions_and_exclusions__combines_ranges() {
+        let result = parse_consuming_semver_range("1.2.*|>=2 !2.1.0").unwrap_err();
+        assert!(matches!(result, PesError::ParsingFailure(_)));
+
+        let result = parse_consuming_semver_range("1.2.* | >=2!2.1.0").unwrap();
+        assert_eq!(
+            result, 
+            Range::between(release(1,2,0), release(1,3,0))
+                .union(&Range::higher_than(release(2,0,0)).intersection(&Range::exact(release(2,1,0)).negate()))
+        );
+
+        let result = parse_consuming_semver_range("1+<2!1.4.3!1.5.*").unwrap();
+        assert!(result.contains(&release(1,4,2)));
+        assert!(!result.contains(&release(1,4,3)));
+        assert!(!result.contains(&release(1,5,1)));
+        assert!(result.contains(&release(1,6,0)));
+
+        let result = parse_consuming_semver_range("!1.4.3").unwrap();
+        assert_eq!(result, Range::exact(release(1,4,3)).negate());
+    }
+
+    #[test]
+    fn parse_package_range__given_extended_grammar__returns_name_and_range() {
+        let result = parse_consuming_package_range("maya->=2018|2016.*").unwrap();
+        assert_eq!(
+            result, 
+            ("maya", Range::higher_than(release(2018,0,0)).union(&Range::between(release(2016,0,0), release(2017,0,0))))
+        );
+    }
+
 }
 //-----------------//
 // ENV PARSE TESTS //
@@ -147,6 +259,7 @@ mod BasicVarProvider_test {
     //use nom::Err::Error as NomErr;
     use std::rc::Rc;
     use std::cell::RefCell;
+    use std::path::Path;
     use std::collections::VecDeque;
 
     #[test]
@@ -305,4 +418,88 @@ mod BasicVarProvider_test {
         assert!(result.is_err());
 
     }
+
+    #[test]
+    fn parse_consuming_all_paths_with_provider__given_operations__succeeds() {
+        let mut provider = BasicVarProvider::new();
+        provider.insert("root", "/packages/foo");
+        let provider = Rc::new(RefCell::new(provider));
+        let parse = |value| parse_consuming_all_paths_with_provider(Rc::clone(&provider), value).unwrap();
+
+        assert_eq!(parse("set(http://proxy.example.com:8080/?a=b c)"), PathMode::Set("http://proxy.example.com:8080/?a=b c".to_string()));
+        assert_eq!(parse("set({root}/bin)"), PathMode::Set("/packages/foo/bin".to_string()));
+        assert_eq!(parse("default(vim)"), PathMode::Default("vim".to_string()));
+        assert_eq!(parse(" unset( ) "), PathMode::Unset);
+        assert_eq!(
+            parse(r#"append(-O2 -I{root}/include, sep=" ")"#),
+            PathMode::Separated(
+                Box::new(PathMode::Append(VecDeque::from(vec!["-O2".to_string(), "-I/packages/foo/include".to_string()]))),
+                " ".to_string()
+            )
+        );
+        assert_eq!(
+            parse(r#"prepend(a;b, sep=";")"#),
+            PathMode::Separated(Box::new(PathMode::Prepend(VecDeque::from(vec!["a".to_string(), "b".to_string()]))), ";".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_consuming_all_paths_with_provider__given_escapes__takes_them_literally() {
+        let provider = Rc::new(RefCell::new(BasicVarProvider::new()));
+        let parse = |value| parse_consuming_all_paths_with_provider(Rc::clone(&provider), value).unwrap();
+
+        assert_eq!(parse(r"set(f\(x\) = \{root\} \\)"), PathMode::Set(r"f(x) = {root} \".to_string()));
+        assert_eq!(
+            parse(r#"append(a\ b c\,d, sep=" ")"#),
+            PathMode::Separated(Box::new(PathMode::Append(VecDeque::from(vec!["a b".to_string(), "c,d".to_string()]))), " ".to_string())
+        );
+        // unknown variables and unterminated expressions are errors
+        assert!(parse_consuming_all_paths_with_provider(Rc::clone(&provider), "set({root})").is_err());
+        assert!(parse_consuming_all_paths_with_provider(Rc::clone(&provider), "set(foo").is_err());
+    }
+
+    #[test]
+    fn parse_consuming_all_paths_with_provider__given_references__resolves_them() {
+        let mut provider = BasicVarProvider::new();
+        provider.insert_package_vars("foo", &SemanticVersion::new(1, 2, 3, ReleaseType::Release), Path::new("/packages/foo/1.2.3"));
+        provider.insert_package_root("maya", Path::new("/packages/maya/2020.1.0"));
+        provider.set_environment(vec![("HOME".to_string(), "/home/fred".to_string())].into_iter().collect());
+        let provider = Rc::new(RefCell::new(provider));
+        let parse = |value| parse_consuming_all_paths_with_provider(Rc::clone(&provider), value).unwrap();
+
+        assert_eq!(
+            parse("prepend({maya.root}/lib:$HOME/lib:${HOME}/bin)"),
+            PathMode::Prepend(VecDeque::from(vec![
+                "/packages/maya/2020.1.0/lib".to_string(),
+                "/home/fred/lib".to_string(),
+                "/home/fred/bin".to_string(),
+            ]))
+        );
+        assert_eq!(parse("set({name}-{version} {major}.{minor} $HOME $5)"), PathMode::Set("foo-1.2.3 1.2 /home/fred $5".to_string()));
+        assert_eq!(parse(r"set(\$HOME)"), PathMode::Set("$HOME".to_string()));
+    }
+
+    #[test]
+    fn parse_consuming_all_paths_with_provider__given_unknown_variables__lists_them() {
+        let mut provider = BasicVarProvider::new();
+        provider.insert("root", "/packages/foo");
+        let provider = Rc::new(RefCell::new(provider));
+        match parse_consuming_all_paths_with_provider(provider, "prepend({rooot}/bin:$NOPE/bin:{root}/lib:{rooot}/lib)") {
+            Err(PesError::UnknownVariables { variables, .. }) => assert_eq!(variables, vec!["{rooot}", "$NOPE"]),
+            other => panic!("expected UnknownVariables. got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn referenced_variables__given_expression__returns_unescaped_references() {
+        assert_eq!(
+            referenced_variables(r"set({root} \{name\} $HOME \$USER ${PATH} {maya.root})"),
+            vec![
+                PathToken::Variable("root"),
+                PathToken::EnvVariable("HOME"),
+                PathToken::EnvVariable("PATH"),
+                PathToken::Variable("maya.root"),
+            ]
+        );
+    }
 }
@@ -52,4 +52,73 @@ fn frm_str__when_given_valid_str_returns_ok() {
         
         assert_eq!(range.unwrap(), range_tup.1);
     }
-}
\ No newline at end of file
+}
+#[test]
+fn display_range__given_ranges__displays_pes_syntax() {
+    let ranges = &[
+        ("1.2.3", "1.2.3"),
+        ("1.2.3+<2", "1.2.3+<2.0.0"),
+        (">=1.2", ">=1.2.0"),
+        ("<1.2", "<1.2.0"),
+        ("*", "*"),
+        ("!*", "!*"),
+        ("1.*|>=3", "1.*|>=3.0.0"),
+        ("!1.4.3", "!1.4.3"),
+        ("^1.2", "1.2.*"),
+        ("~1.2.3", "~1.2.3"),
+        ("<=1.2.3", "<1.2.4-alpha"),
+        ("1+<2!1.4.3!1.5.0", "1.*!1.4.3!1.5.0"),
+        ("1.2.3+<1.2.5!1.2.4", "1.2.3+<1.2.5!1.2.4"),
+        ("1.*!1.5.*", "1.0.0+<1.5.0|1.6.0+<2.0.0"),
+    ];
+    for (input, expected) in ranges {
+        let range = SemVerRange::frm_str(input).unwrap();
+        assert_eq!(display_range(&range).to_string(), *expected);
+    }
+}
+
+#[test]
+fn display_range__given_ranges__round_trips() {
+    let ranges = &[
+        "1.2.3-beta.2",
+        "~1.2.3",
+        "^1|^3.1",
+        "<=1.2.3|>2",
+        ">=1.0.0-rc!1.2.*!1.4.3",
+        "0.0.0-alpha",
+        "!0.0.0-alpha",
+    ];
+    for input in ranges {
+        let range = SemVerRange::frm_str(input).unwrap();
+        let displayed = display_range(&range).to_string();
+        assert_eq!(SemVerRange::frm_str(&displayed).unwrap(), range, "{} displayed as {}", input, displayed);
+    }
+}
+
+#[test]
+fn display_range__given_equivalent_ranges__displays_identically() {
+    let ranges = &[
+        ("1.*", &["^1", "~1", "1+<2", ">=1!>=2", "1.0.0+<1.5|1.5+<2"][..]),
+        ("*", &["*", "<1|>=1", "0.0.0-alpha+<3|>=2"][..]),
+        ("1.2.3", &["1.2.3", "1.2.3+build.1", "1.2.*!<1.2.3!>=1.2.4-alpha"][..]),
+    ];
+    for (expected, inputs) in ranges {
+        for input in inputs.iter() {
+            let range = SemVerRange::frm_str(input).unwrap();
+            assert_eq!(display_range(&range).to_string(), *expected, "{}", input);
+        }
+    }
+}
+
+#[test]
+fn range_intervals__given_range__returns_ascending_intervals() {
+    let range = SemVerRange::frm_str("1.2.3|>=2").unwrap();
+    let intervals = range_intervals(&range).expect("unable to retrieve intervals");
+    assert_eq!(
+        intervals,
+        vec![
+            (SemanticVersion::new(1, 2, 3, ReleaseType::Release), Some(SemanticVersion::new(1, 2, 3, ReleaseType::Release).next())),
+            (SemanticVersion::new(2, 0, 0, ReleaseType::Release), None),
+        ]
+    );
+}
@@ -0,0 +1,237 @@
+#![allow(non_snake_case)]
+use super::*;
+use crate::{PackageRepository, PluginMgr};
+use testutils::{tempfile::TempDir, write_distribution};
+
+//---------------------//
+//    UTILITIES        //
+//---------------------//
+
+// Retrieve the root of the testing package repo, which
+// should be located in $ROOT/test_fixtures/repo
+fn get_repo_root() -> PathBuf {
+    let mut root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    root.push("../test_fixtures/repo");
+    root
+}
+
+// write a manifest for package-version, with the supplied run requires, into the repository rooted at root
+fn write_run_requires(root: &Path, package: &str, version: &str, requires: &str) {
+    write_distribution(root, package, version, &format!("targets:\n  run:\n    requires:\n{}", requires));
+}
+
+fn versions_of(index: &RepositoryIndex, package: &str) -> Vec<String> {
+    index
+        .distributions(ReleaseType::Alpha, Rc::new(Vec::new()))
+        .filter(|(name, _)| *name == package)
+        .map(|(_, dist)| dist.version.to_string())
+        .collect()
+}
+
+//------------//
+//   TESTS    //
+//------------//
+
+#[test]
+fn refresh__indexes_all_distributions_in_repository() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo = PackageRepository::new(get_repo_root(), &plugin_mgr);
+    let mut index = RepositoryIndex::new(repo.root());
+    index.refresh(&repo).expect("unable to refresh index");
+
+    assert_eq!(versions_of(&index, "foo"), vec!["0.1.0", "0.2.0", "0.2.1", "0.2.2-beta"]);
+    assert_eq!(versions_of(&index, "bar"), vec!["0.1.0", "1.0.1"]);
+    assert!(index.is_dirty());
+}
+
+#[test]
+fn distributions__filters_by_release_type_and_honors_overrides() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo = PackageRepository::new(get_repo_root(), &plugin_mgr);
+    let mut index = RepositoryIndex::new(repo.root());
+    index.refresh(&repo).expect("unable to refresh index");
+
+    let released = index
+        .distributions(ReleaseType::Release, Rc::new(Vec::new()))
+        .filter(|(name, _)| *name == "foo")
+        .count();
+    assert_eq!(released, 3);
+
+    let overrides = Rc::new(vec![("foo".to_string(), SemanticVersion::from_str("0.2.2-beta").unwrap())]);
+    let with_override = index
+        .distributions(ReleaseType::Release, overrides)
+        .filter(|(name, _)| *name == "foo")
+        .count();
+    assert_eq!(with_override, 4);
+}
+
+#[test]
+fn save_and_load__round_trip() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo = PackageRepository::new(get_repo_root(), &plugin_mgr);
+    let cache_dir = TempDir::new().expect("unable to create temp dir");
+    let cache_file = cache_file_for(cache_dir.path(), repo.root());
+
+    let mut index = RepositoryIndex::new(repo.root());
+    index.refresh(&repo).expect("unable to refresh index");
+    index.save(&cache_file).expect("unable to save index");
+    assert!(!index.is_dirty());
+
+    let mut loaded = RepositoryIndex::load(repo.root(), &cache_file);
+    assert_eq!(loaded, index);
+    loaded.refresh(&repo).expect("unable to refresh index");
+    assert!(!loaded.is_dirty());
+}
+
+#[test]
+fn load__returns_empty_index_for_different_repository() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo = PackageRepository::new(get_repo_root(), &plugin_mgr);
+    let cache_dir = TempDir::new().expect("unable to create temp dir");
+    let cache_file = cache_dir.path().join("index.yaml");
+
+    let mut index = RepositoryIndex::new(repo.root());
+    index.refresh(&repo).expect("unable to refresh index");
+    index.save(&cache_file).expect("unable to save index");
+
+    let loaded = RepositoryIndex::load("/some/other/repo", &cache_file);
+    assert_eq!(loaded, RepositoryIndex::new("/some/other/repo"));
+}
+
+#[test]
+fn load__returns_empty_index_for_different_pes_version() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo = PackageRepository::new(get_repo_root(), &plugin_mgr);
+    let cache_dir = TempDir::new().expect("unable to create temp dir");
+    let cache_file = cache_dir.path().join("index.yaml");
+
+    let mut index = RepositoryIndex::new(repo.root());
+    index.refresh(&repo).expect("unable to refresh index");
+    index.save(&cache_file).expect("unable to save index");
+
+    let contents = std::fs::read_to_string(&cache_file).unwrap();
+    let stale = contents.replacen(&format!("pes_version: {}", INDEX_VERSION), "pes_version: 0.0.1", 1);
+    assert_ne!(contents, stale);
+    std::fs::write(&cache_file, stale).unwrap();
+    assert_eq!(RepositoryIndex::load(repo.root(), &cache_file), RepositoryIndex::new(repo.root()));
+
+    // indices which predate recording the version are discarded as well
+    let unversioned = contents.replacen(&format!("pes_version: {}\n", INDEX_VERSION), "schema: 2\n", 1);
+    std::fs::write(&cache_file, unversioned).unwrap();
+    assert_eq!(RepositoryIndex::load(repo.root(), &cache_file), RepositoryIndex::new(repo.root()));
+}
+
+#[test]
+fn refresh__picks_up_added_and_removed_distributions() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = TempDir::new().expect("unable to create temp dir");
+    write_run_requires(repo_dir.path(), "baz", "1.0.0", "      foo: 0.1\n");
+    let repo = PackageRepository::new(repo_dir.path(), &plugin_mgr);
+
+    let mut index = RepositoryIndex::new(repo.root());
+    index.refresh(&repo).expect("unable to refresh index");
+    assert_eq!(versions_of(&index, "baz"), vec!["1.0.0"]);
+
+    write_run_requires(repo_dir.path(), "baz", "1.1.0", "      foo: 0.2\n");
+    // directory mtimes may have a coarse resolution; force the package to be considered stale
+    index.packages.get_mut("baz").unwrap().modified = Some(SystemTime::UNIX_EPOCH);
+    index.refresh(&repo).expect("unable to refresh index");
+    assert_eq!(versions_of(&index, "baz"), vec!["1.0.0", "1.1.0"]);
+
+    std::fs::remove_dir_all(repo_dir.path().join("baz")).expect("unable to remove package");
+    index.refresh(&repo).expect("unable to refresh index");
+    assert!(versions_of(&index, "baz").is_empty());
+}
+
+#[test]
+fn package__rereads_manifest_when_distribution_changes() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = TempDir::new().expect("unable to create temp dir");
+    write_run_requires(repo_dir.path(), "baz", "1.0.0", "      foo: 0.1\n");
+    let repo = PackageRepository::new(repo_dir.path(), &plugin_mgr);
+
+    let mut index = RepositoryIndex::new(repo.root());
+    index.refresh(&repo).expect("unable to refresh index");
+
+    write_run_requires(repo_dir.path(), "baz", "1.0.0", "      bar: 1\n");
+    index.packages.get_mut("baz").unwrap().distributions[0].modified = Some(SystemTime::UNIX_EPOCH);
+    let package = index.package(&repo, "baz").expect("unable to index package").expect("missing package");
+    let requires = package.distributions().next().unwrap().manifest.get_requires("run").unwrap();
+    assert_eq!(requires.len(), 1);
+    assert_eq!(requires[0].name, "bar");
+}
+
+#[test]
+fn package__rereads_manifest_edited_in_place() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = TempDir::new().expect("unable to create temp dir");
+    write_run_requires(repo_dir.path(), "baz", "1.0.0", "      foo: 0.1\n");
+    let repo = PackageRepository::new(repo_dir.path(), &plugin_mgr);
+
+    let mut index = RepositoryIndex::new(repo.root());
+    index.refresh(&repo).expect("unable to refresh index");
+    index.dirty = false;
+
+    // rewriting the existing manifest leaves the mtime of the distribution's directory untouched
+    let dist = repo_dir.path().join("baz").join("1.0.0");
+    let modified = mtime(&dist);
+    write_run_requires(repo_dir.path(), "baz", "1.0.0", "      barbaz: 1\n");
+    assert_eq!(mtime(&dist), modified);
+
+    let package = index.package(&repo, "baz").expect("unable to index package").expect("missing package");
+    let requires = package.distributions().next().unwrap().manifest.get_requires("run").unwrap();
+    assert_eq!(requires[0].name, "barbaz");
+    assert!(index.is_dirty());
+}
+
+#[test]
+fn cache_file_for__sanitizes_repository_path() {
+    let cache_file = cache_file_for("/tmp/cache", "/packages/repo one");
+    let expected = format!("/tmp/cache/_packages_repo_one-{}.yaml", fnv1a_hash(b"/packages/repo one"));
+    assert_eq!(cache_file, PathBuf::from(expected));
+}
+
+#[test]
+fn cache_file_for__given_roots_which_sanitize_alike__differs() {
+    assert_ne!(cache_file_for("/tmp/cache", "/a/b_c"), cache_file_for("/tmp/cache", "/a_b/c"));
+}
+
+#[test]
+fn package__given_numbered_pre_releases_and_build_metadata__sorts_by_precedence() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = TempDir::new().expect("unable to create temp dir");
+    for version in &["1.0.0+build.3", "1.0.0-beta.10", "1.0.0-beta.2", "1.0.0-beta"] {
+        write_run_requires(repo_dir.path(), "foo", version, "      {}\n");
+    }
+    let repo = PackageRepository::new(repo_dir.path(), &plugin_mgr);
+    let mut index = RepositoryIndex::new(repo.root());
+    index.refresh(&repo).expect("unable to refresh index");
+
+    assert_eq!(versions_of(&index, "foo"), vec!["1.0.0-beta", "1.0.0-beta.2", "1.0.0-beta.10", "1.0.0+build.3"]);
+}
+
+#[test]
+fn package__given_versions_differing_only_by_build_metadata__fails() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = TempDir::new().expect("unable to create temp dir");
+    for version in &["1.0.0", "1.0.0+build.3"] {
+        write_run_requires(repo_dir.path(), "foo", version, "      {}\n");
+    }
+    let repo = PackageRepository::new(repo_dir.path(), &plugin_mgr);
+    let mut index = RepositoryIndex::new(repo.root());
+
+    let result = index.package(&repo, "foo");
+    assert!(matches!(result, Err(PesError::DuplicateDistribution { ref package, .. }) if package == "foo"));
+}
+
+#[test]
+fn package__given_reserved_absent_version__fails() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = TempDir::new().expect("unable to create temp dir");
+    write_run_requires(repo_dir.path(), "foo", "0.0.0-alpha", "      {}\n");
+    let repo = PackageRepository::new(repo_dir.path(), &plugin_mgr);
+    let mut index = RepositoryIndex::new(repo.root());
+
+    let result = index.package(&repo, "foo");
+    assert!(matches!(result, Err(PesError::ReservedVersion(_))));
+}
@@ -4,6 +4,7 @@ use super::*;
 use crate::repository::PackageRepository;
 use crate::distribution_range::DistributionRange;
 use crate::plugin_mgr::PluginMgr;
+use testutils::{tempfile::TempDir, write_distribution};
 
 use std::path::PathBuf;
 
@@ -18,6 +19,34 @@ fn get_repo_root(repo_name: &str) -> PathBuf {
     root
 }
 
+// construct a solver over the repository rooted at root
+fn solver_for<'a>(plugin_mgr: &'a PluginMgr, root: &Path) -> Solver<PackageRepository<'a>> {
+    let mut solver = Solver::new();
+    solver
+        .add_repository(PackageRepository::new(root, plugin_mgr), ReleaseType::Release, Rc::new(Vec::new()))
+        .expect("should be able to add repository");
+    solver
+}
+
+// construct a temporary repository, populated by the supplied fixture
+fn repo_with(fixture: fn(&Path)) -> TempDir {
+    let repo_dir = TempDir::new().expect("unable to create temp dir");
+    fixture(repo_dir.path());
+    repo_dir
+}
+
+// construct a repository containing maya 2018 and 2019, and a plugin with a variant for each
+fn write_variant_repo(root: &Path) {
+    write_distribution(root, "maya", "2018.0.0", "");
+    write_distribution(root, "maya", "2019.0.0", "");
+    write_distribution(
+        root,
+        "maya_plugin",
+        "1.0.0",
+        "variants:\n  - name: maya2019\n    requires:\n      maya: 2019\n  - name: maya2018\n    requires:\n      maya: 2018\n",
+    );
+}
+
 //-------------//
 //    TESTS    //
 //-------------//
@@ -26,12 +55,8 @@ fn get_repo_root(repo_name: &str) -> PathBuf {
 fn add_repository__given_a_repository_with_a_package_without_run_target__succeeds() {
     let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
 
-    let pkg_repo = PackageRepository::new(get_repo_root("repo2"), &plugin_mgr);
-    let mut solver = Solver::new();
-    solver
-        .add_repository(&pkg_repo, ReleaseType::Release, Rc::new(Vec::new()))
-        .expect("should be able to add repository");
-    let versions: Vec<&SemanticVersion> = solver.versions("foo").unwrap().collect();
+    let mut solver = solver_for(&plugin_mgr, &get_repo_root("repo2"));
+    let versions: Vec<SemanticVersion> = solver.versions("foo").unwrap();
     assert_eq!(versions.len(), 1);
     let solution = solver.solve_from_str("foo-0.1.0");
 
@@ -41,24 +66,250 @@ fn add_repository__given_a_repository_with_a_package_without_run_target__succeed
 #[test]
 fn convert_request_str__given_space_separated_list__succeeds() {
     let request = "maya  maya_plugins-1.2.3+<3";
-    let result = Solver::convert_request_str(request);
+    let result = Solver::<PackageRepository>::convert_request_str(request);
     assert_eq!(
         result,
-        vec![
+        SolveRequest::new(vec![
             DistributionRange::from_str("maya").unwrap(),
             DistributionRange::from_str("maya_plugins-1.2.3+<3").unwrap()
-        ]
+        ])
+    );
+}
+
+#[test]
+fn convert_request_str__given_weak_requirement__succeeds() {
+    let request = "maya_plugins ~maya-2019";
+    let result = Solver::<PackageRepository>::convert_request_str(request);
+    assert_eq!(
+        result,
+        SolveRequest::new(vec![DistributionRange::from_str("maya_plugins").unwrap()])
+            .with_weak_requires(vec![DistributionRange::from_str("maya-2019").unwrap()])
     );
 }
 
 #[test]
 fn solve_from_str__given_a_valid_distribution__succeeds() {
     let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
-    let package_repo = PackageRepository::new(get_repo_root("repo"), &plugin_mgr);
-    let mut solver = Solver::new();
-    solver
-        .add_repository(&package_repo, ReleaseType::Release, Rc::new(Vec::new()))
-        .expect("should be able to add repository");
+    let mut solver = solver_for(&plugin_mgr, &get_repo_root("repo"));
     let solution = solver.solve_from_str("bar-0.1.0");
     assert!(solution.is_ok());
 }
+
+#[test]
+fn solve_from_str__given_package_with_variants__selects_preferred_variant() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = repo_with(write_variant_repo);
+    let mut solver = solver_for(&plugin_mgr, repo_dir.path());
+    let solution = solver.solve_from_str("maya_plugin").expect("unable to solve");
+    assert_eq!(solution.get("maya"), Some(&SemanticVersion::new(2019, 0, 0, ReleaseType::Release)));
+    // the synthetic variant package should not appear in the solution
+    assert_eq!(solution.len(), 3);
+    assert_eq!(solver.variants().get("maya_plugin").map(|v| v.as_str()), Some("maya2019"));
+}
+
+#[test]
+fn solve_from_str__given_package_with_variants__selects_compatible_variant() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = repo_with(write_variant_repo);
+    let mut solver = solver_for(&plugin_mgr, repo_dir.path());
+    let solution = solver.solve_from_str("maya_plugin maya-2018").expect("unable to solve");
+    assert_eq!(solution.get("maya"), Some(&SemanticVersion::new(2018, 0, 0, ReleaseType::Release)));
+    assert_eq!(solver.variants().get("maya_plugin").map(|v| v.as_str()), Some("maya2018"));
+}
+
+// construct a repository in which foo-2.0.0 conflicts with openexr < 2, and bar requires openexr 1
+fn write_conflict_repo(root: &Path) {
+    write_distribution(root, "openexr", "1.0.0", "");
+    write_distribution(root, "openexr", "2.0.0", "");
+    write_distribution(root, "foo", "1.0.0", "");
+    write_distribution(root, "foo", "2.0.0", "targets:\n  run:\n    requires: {}\n    conflicts:\n      openexr: 1+<2\n");
+    write_distribution(root, "bar", "1.0.0", "targets:\n  run:\n    requires:\n      openexr: 1\n");
+}
+
+#[test]
+fn solve_from_str__given_conflict_with_absent_package__does_not_pull_package_in() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = repo_with(write_conflict_repo);
+    let mut solver = solver_for(&plugin_mgr, repo_dir.path());
+    let solution = solver.solve_from_str("foo").expect("unable to solve");
+    assert_eq!(solution.get("foo"), Some(&SemanticVersion::new(2, 0, 0, ReleaseType::Release)));
+    assert!(solution.get("openexr").is_none());
+}
+
+#[test]
+fn solve_from_str__given_conflict__selects_compatible_version() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = repo_with(write_conflict_repo);
+    let mut solver = solver_for(&plugin_mgr, repo_dir.path());
+    let solution = solver.solve_from_str("foo bar").expect("unable to solve");
+    assert_eq!(solution.get("foo"), Some(&SemanticVersion::new(1, 0, 0, ReleaseType::Release)));
+    assert_eq!(solution.get("openexr"), Some(&SemanticVersion::new(1, 0, 0, ReleaseType::Release)));
+}
+
+#[test]
+fn solve_from_str__given_unsatisfiable_conflict__reports_conflict() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = repo_with(write_conflict_repo);
+    let mut solver = solver_for(&plugin_mgr, repo_dir.path());
+    match solver.solve_from_str("foo-2 bar") {
+        Err(PesError::NoSolution(failure)) => {
+            let report = failure.to_string();
+            assert!(report.contains("foo 2.0.0 conflicts with openexr 1.*\n"), "{}", report);
+            assert!(report.contains(&format!("declared in {}", repo_dir.path().join("foo/2.0.0/manifest.yaml").display())), "{}", report);
+        }
+        other => panic!("expected NoSolution. got {:?}", other),
+    }
+}
+
+// construct a repository in which the maya plugin weakly requires maya 2019
+fn write_weak_repo(root: &Path) {
+    write_distribution(root, "maya", "2018.0.0", "");
+    write_distribution(root, "maya", "2019.0.0", "");
+    write_distribution(root, "maya", "2020.0.0", "");
+    write_distribution(root, "plugin", "1.0.0", "targets:\n  run:\n    requires: {}\n    weak_requires:\n      maya: 2019\n");
+}
+
+#[test]
+fn solve_from_str__given_weak_requirement__does_not_pull_package_in() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = repo_with(write_weak_repo);
+    let mut solver = solver_for(&plugin_mgr, repo_dir.path());
+    let solution = solver.solve_from_str("plugin").expect("unable to solve");
+    assert_eq!(solution.get("plugin"), Some(&SemanticVersion::new(1, 0, 0, ReleaseType::Release)));
+    assert!(solution.get("maya").is_none());
+}
+
+#[test]
+fn solve_from_str__given_weak_requirement__constrains_package_in_closure() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = repo_with(write_weak_repo);
+    let mut solver = solver_for(&plugin_mgr, repo_dir.path());
+    let solution = solver.solve_from_str("plugin maya").expect("unable to solve");
+    assert_eq!(solution.get("maya"), Some(&SemanticVersion::new(2019, 0, 0, ReleaseType::Release)));
+}
+
+#[test]
+fn solve_from_str__given_weak_request__constrains_package_in_closure() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = repo_with(write_weak_repo);
+    let mut solver = solver_for(&plugin_mgr, repo_dir.path());
+    let solution = solver.solve_from_str("~maya-2018").expect("unable to solve");
+    assert!(solution.get("maya").is_none());
+    let solution = solver.solve_from_str("~maya-2018 maya").expect("unable to solve");
+    assert_eq!(solution.get("maya"), Some(&SemanticVersion::new(2018, 0, 0, ReleaseType::Release)));
+    assert!(solver.solve_from_str("~maya-2018 plugin maya").is_err());
+}
+
+#[test]
+fn solve_from_str__given_missing_package__reports_no_versions() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = repo_with(write_conflict_repo);
+    let mut solver = solver_for(&plugin_mgr, repo_dir.path());
+    match solver.solve_from_str("bar openexr-2") {
+        Err(PesError::NoSolution(failure)) => {
+            match &failure.cause {
+                FailureCause::Derived { causes, .. } => assert_eq!(causes.len(), 2),
+                other => panic!("expected derived cause. got {:?}", other),
+            }
+            let report = failure.to_string();
+            assert!(report.starts_with("the request cannot be satisfied because:"), "{}", report);
+            assert!(report.contains("requires openexr 1.0.0\n"), "{}", report);
+            assert!(report.contains("the request requires bar *\n"), "{}", report);
+        }
+        other => panic!("expected NoSolution. got {:?}", other),
+    }
+}
+
+#[test]
+fn with_pins__given_pinned_packages__holds_them_at_their_versions() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = repo_with(write_weak_repo);
+    write_distribution(repo_dir.path(), "plugin", "1.1.0", "");
+    let mut solver = solver_for(&plugin_mgr, repo_dir.path());
+    let constraints = ["maya", "plugin"];
+    let pins = vec![
+        ("maya".to_string(), SemanticVersion::new(2018, 0, 0, ReleaseType::Release)),
+        ("python".to_string(), SemanticVersion::new(3, 7, 0, ReleaseType::Release)),
+    ];
+    let solution = solver
+        .solve(SolveRequest::from_strs(&constraints).unwrap().with_pins(&pins))
+        .expect("unable to solve");
+    assert_eq!(solution.get("maya"), Some(&SemanticVersion::new(2018, 0, 0, ReleaseType::Release)));
+    // unpinned packages remain free to change, while pinned packages outside of the closure are not pulled in
+    assert_eq!(solution.get("plugin"), Some(&SemanticVersion::new(1, 1, 0, ReleaseType::Release)));
+    assert!(solution.get("python").is_none());
+}
+
+// construct a repository containing three versions of maya and two of python
+fn write_preference_repo(root: &Path) {
+    write_distribution(root, "maya", "2018.0.0", "");
+    write_distribution(root, "maya", "2019.0.0", "targets:\n  run:\n    requires:\n      python: 3+<4\n");
+    write_distribution(root, "maya", "2020.0.0", "targets:\n  run:\n    requires:\n      python: 3+<4\n");
+    write_distribution(root, "python", "3.7.0", "");
+    write_distribution(root, "python", "3.8.0", "");
+}
+
+#[test]
+fn with_lockfile_preferences__given_new_constraint__keeps_other_locked_versions() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = repo_with(write_preference_repo);
+    write_distribution(repo_dir.path(), "nuke", "12.0.0", "");
+    let mut lockfile = LockFile::new("pes env maya", "jgerber");
+    lockfile.add_dist("run", "maya-2019.0.0").unwrap();
+    lockfile.add_dist("run", "python-3.7.0").unwrap();
+
+    let mut solver = solver_for(&plugin_mgr, repo_dir.path()).with_lockfile_preferences(&lockfile, "run");
+    let solution = solver.solve_from_str("maya nuke").expect("unable to solve");
+    assert_eq!(solution.get("maya"), Some(&SemanticVersion::new(2019, 0, 0, ReleaseType::Release)));
+    assert_eq!(solution.get("python"), Some(&SemanticVersion::new(3, 7, 0, ReleaseType::Release)));
+    assert_eq!(solution.get("nuke"), Some(&SemanticVersion::new(12, 0, 0, ReleaseType::Release)));
+}
+
+#[test]
+fn with_lockfile_preferences__given_lockfile_without_freed_package__keeps_locked_variant() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = repo_with(write_variant_repo);
+    write_distribution(repo_dir.path(), "nuke", "12.0.0", "");
+    write_distribution(repo_dir.path(), "nuke", "13.0.0", "");
+    let mut lockfile = LockFile::new("pes env maya_plugin nuke", "jgerber");
+    lockfile.add_dist("run", "maya_plugin-1.0.0").unwrap();
+    lockfile.add_dist("run", "maya-2018.0.0").unwrap();
+    lockfile.add_dist("run", "nuke-12.0.0").unwrap();
+    lockfile.add_variant("run", "maya_plugin", "maya2018");
+
+    let preferences = lockfile.without_packages(&["nuke"]);
+    let mut solver = solver_for(&plugin_mgr, repo_dir.path()).with_lockfile_preferences(&preferences, "run");
+    let solution = solver.solve_from_str("maya_plugin nuke").expect("unable to solve");
+    assert_eq!(solution.get("maya"), Some(&SemanticVersion::new(2018, 0, 0, ReleaseType::Release)));
+    assert_eq!(solution.get("nuke"), Some(&SemanticVersion::new(13, 0, 0, ReleaseType::Release)));
+    assert_eq!(solver.variants().get("maya_plugin").map(|v| v.as_str()), Some("maya2018"));
+}
+
+#[test]
+fn with_preferences__given_disallowed_preference__falls_back_to_newest_allowed() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = repo_with(write_preference_repo);
+    let preferences = vec![
+        ("maya".to_string(), SemanticVersion::new(2019, 0, 0, ReleaseType::Release)),
+        ("python".to_string(), SemanticVersion::new(3, 7, 0, ReleaseType::Release)),
+    ];
+    let mut solver = solver_for(&plugin_mgr, repo_dir.path()).with_preferences(preferences, VariantMap::new());
+    let solution = solver.solve_from_str("maya python-3.8").expect("unable to solve");
+    assert_eq!(solution.get("maya"), Some(&SemanticVersion::new(2019, 0, 0, ReleaseType::Release)));
+    assert_eq!(solution.get("python"), Some(&SemanticVersion::new(3, 8, 0, ReleaseType::Release)));
+    let solution = solver.solve_from_str("maya-2018+<2019").expect("unable to solve");
+    assert_eq!(solution.get("maya"), Some(&SemanticVersion::new(2018, 0, 0, ReleaseType::Release)));
+    assert!(solution.get("python").is_none());
+}
+
+#[test]
+fn with_preferences__given_preferred_variant__selects_it() {
+    let plugin_mgr = PluginMgr::new().expect("unable to load plugin manager");
+    let repo_dir = repo_with(write_variant_repo);
+    let mut variants = VariantMap::new();
+    variants.insert("maya_plugin".to_string(), "maya2018".to_string());
+    let mut solver = solver_for(&plugin_mgr, repo_dir.path()).with_preferences(Vec::new(), variants);
+    let solution = solver.solve_from_str("maya_plugin").expect("unable to solve");
+    assert_eq!(solution.get("maya"), Some(&SemanticVersion::new(2018, 0, 0, ReleaseType::Release)));
+    assert_eq!(solver.variants().get("maya_plugin").map(|v| v.as_str()), Some("maya2018"));
+}
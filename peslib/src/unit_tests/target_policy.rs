@@ -0,0 +1,55 @@
+#![allow(non_snake_case)]
+use super::*;
+
+#[test]
+fn target_for__given_default_policy__returns_run() {
+    let policy = TargetPolicy::default();
+    assert_eq!(policy.target_for("foo", "build"), "run");
+}
+
+#[test]
+fn target_for__given_same_policy__returns_root_target() {
+    let policy = TargetPolicy::same();
+    assert_eq!(policy.target_for("foo", "build"), "build");
+}
+
+#[test]
+fn target_for__given_override__returns_override() {
+    let policy = TargetPolicy::run().with_override("gcc", "build");
+    assert_eq!(policy.target_for("gcc", "test"), "build");
+    assert_eq!(policy.target_for("foo", "test"), "run");
+}
+
+#[test]
+fn from_str__given_valid_specs__succeeds() {
+    assert_eq!(TargetPolicy::from_str("run").unwrap(), TargetPolicy::run());
+    assert_eq!(TargetPolicy::from_str("same").unwrap(), TargetPolicy::same());
+    assert_eq!(
+        TargetPolicy::from_str("lint").unwrap(),
+        TargetPolicy::new(TransitiveTarget::Target("lint".into()))
+    );
+    assert_eq!(
+        TargetPolicy::from_str("same, gcc:build,cmake:build").unwrap(),
+        TargetPolicy::same().with_override("gcc", "build").with_override("cmake", "build")
+    );
+    assert_eq!(
+        TargetPolicy::from_str("gcc:build").unwrap(),
+        TargetPolicy::run().with_override("gcc", "build")
+    );
+}
+
+#[test]
+fn from_str__given_invalid_specs__fails() {
+    for spec in &["", "gcc:", ":build", "gcc:build:run", "run,,same"] {
+        assert!(TargetPolicy::from_str(spec).is_err(), "expected '{}' to fail", spec);
+    }
+}
+
+#[test]
+fn to_string__given_policy__round_trips_through_from_str() {
+    for spec in &["run", "same", "build,gcc:build,cmake:run"] {
+        let policy = spec.parse::<TargetPolicy>().unwrap();
+        assert_eq!(&policy.to_string(), spec);
+        assert_eq!(policy.to_string().parse::<TargetPolicy>().unwrap(), policy);
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
 pub use tempfile;
@@ -20,4 +20,16 @@ pub fn rand_file<P: Into<PathBuf>>(prefix: Option<&str>, suffix: Option<&str>, l
     let mut location = location.into();
     location.push(name);
     location
-}
\ No newline at end of file
+}
+
+/// write a distribution of the package to the repository rooted at `root`, with a `manifest.yaml` written
+/// against schema 2, whose name, version and description are followed by `body` (eg its targets). Returns the
+/// path to the manifest.
+pub fn write_distribution<P: AsRef<Path>>(root: P, package: &str, version: &str, body: &str) -> PathBuf {
+    let dist = root.as_ref().join(package).join(version);
+    std::fs::create_dir_all(&dist).expect("unable to create distribution");
+    let manifest = dist.join("manifest.yaml");
+    let contents = format!("---\nschema: 2\nname: {}\nversion: {}\ndescription: test\n{}", package, version, body);
+    std::fs::write(&manifest, contents).expect("unable to write manifest");
+    manifest
+}